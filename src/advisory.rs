@@ -0,0 +1,161 @@
+use crate::error::{CliError, CliResult};
+use crate::registry;
+use crate::util;
+use semver::{Version, VersionReq};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The git repository of the RustSec advisory database.
+const ADVISORY_DB_URL: &str = "https://github.com/rustsec/advisory-db.git";
+
+/// A security advisory of the RustSec advisory database.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Advisory {
+    /// The id of the advisory, like `RUSTSEC-2020-0071`.
+    pub id: String,
+    pub title: String,
+    patched: Vec<VersionReq>,
+    unaffected: Vec<VersionReq>,
+}
+
+impl Advisory {
+    /// Whether a version of the crate is neither patched nor unaffected. Versions that cannot be
+    /// parsed are not affected.
+    pub fn affects(&self, ver: &str) -> bool {
+        match Version::parse(ver) {
+            Ok(ver) => !self
+                .patched
+                .iter()
+                .chain(&self.unaffected)
+                .any(|req| req.matches(&ver)),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Finds the advisory database: `path` if it is given, which is used as it is. Otherwise this is
+/// the checkout in the cargo home directory that cargo-audit uses too, which is cloned or updated
+/// with git first. A checkout that cannot be updated is used as it is.
+pub fn find_db(path: Option<&str>) -> CliResult<PathBuf> {
+    if let Some(path) = path {
+        return Ok(PathBuf::from(path));
+    }
+
+    let db = registry::cargo_home()
+        .ok_or_else(|| CliError::Generic("Could not find the cargo home directory".into()))?
+        .join("advisory-db");
+    if db.join(".git").is_dir() {
+        let pull = util::run(
+            OsStr::new("git"),
+            Some(&db),
+            ["pull", "--ff-only", "--quiet"],
+        );
+        if let Err(e) = pull {
+            eprintln!(
+                "Could not update the advisory database, using it as it is: {}",
+                e
+            );
+        }
+    } else {
+        eprintln!("Cloning the advisory database into {:?}.", db);
+        util::run(
+            OsStr::new("git"),
+            None,
+            [
+                OsStr::new("clone"),
+                OsStr::new("--depth"),
+                OsStr::new("1"),
+                OsStr::new("--quiet"),
+                OsStr::new(ADVISORY_DB_URL),
+                db.as_os_str(),
+            ],
+        )?;
+    }
+
+    Ok(db)
+}
+
+/// Reads the advisories of the crates in the database, by crate name. Informational advisories,
+/// e.g. about unmaintained crates, and withdrawn ones are left out.
+pub fn load(db: &Path) -> CliResult<HashMap<String, Vec<Advisory>>> {
+    let crates_dir = db.join("crates");
+    let crate_dirs = fs::read_dir(&crates_dir).map_err(|e| {
+        CliError::Generic(format!(
+            "Could not read the advisory database in {:?}: {}",
+            db, e
+        ))
+    })?;
+
+    let mut advisories: HashMap<String, Vec<Advisory>> = HashMap::new();
+    for crate_dir in crate_dirs.filter_map(|entry| entry.ok()) {
+        let files = match fs::read_dir(crate_dir.path()) {
+            Ok(files) => files,
+            Err(_) => continue,
+        };
+        for file in files.filter_map(|entry| entry.ok()) {
+            let path = file.path();
+            if path.extension() != Some(OsStr::new("md")) {
+                continue;
+            }
+            let (package, advisory) = match fs::read_to_string(&path)
+                .ok()
+                .and_then(|text| parse_advisory(&text))
+            {
+                Some(advisory) => advisory,
+                None => {
+                    eprintln!("Could not parse the advisory {:?}, skipping it.", path);
+                    continue;
+                }
+            };
+            if let Some(advisory) = advisory {
+                advisories.entry(package).or_default().push(advisory);
+            }
+        }
+    }
+
+    for advisories in advisories.values_mut() {
+        advisories.sort_by(|a, b| a.id.cmp(&b.id));
+    }
+
+    Ok(advisories)
+}
+
+/// Parses an advisory file: the TOML front matter in a ```toml block, followed by Markdown whose
+/// first heading is the title. Returns the crate name, and the advisory unless it is left out.
+fn parse_advisory(text: &str) -> Option<(String, Option<Advisory>)> {
+    let front_matter = text.trim_start().strip_prefix("```toml")?;
+    let end = front_matter.find("```")?;
+    let toml: toml::Value = toml::from_str(&front_matter[..end]).ok()?;
+    let title = front_matter[end + 3..]
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .unwrap_or("")
+        .trim()
+        .to_string();
+
+    let table = toml.get("advisory")?;
+    let package = table.get("package")?.as_str()?.to_string();
+    if table.get("informational").is_some() || table.get("withdrawn").is_some() {
+        return Some((package, None));
+    }
+
+    let reqs = |key| -> Option<Vec<VersionReq>> {
+        let reqs = match toml.get("versions").and_then(|v| v.get(key)) {
+            Some(reqs) => reqs.as_array()?,
+            None => return Some(vec![]),
+        };
+        reqs.iter()
+            .map(|req| VersionReq::parse(req.as_str()?).ok())
+            .collect()
+    };
+
+    let advisory = Advisory {
+        id: table.get("id")?.as_str()?.to_string(),
+        title,
+        patched: reqs("patched")?,
+        unaffected: reqs("unaffected")?,
+    };
+    Some((package, Some(advisory)))
+}
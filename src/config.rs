@@ -1,43 +1,1026 @@
-use crate::error::CliResult;
+use crate::dep::{self, DepKind, SourceKind};
+use crate::error::{CliError, CliResult};
+use crate::features::FeatureRequest;
+use crate::license::LicensePolicy;
+use crate::render::Format;
+use crate::theme::{self, Theme};
+use crate::util::{self, Pattern};
 use clap::ArgMatches;
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+/// How the dependency graph is resolved.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    /// Parse Cargo.toml and Cargo.lock directly.
+    Lockfile,
+    /// Run `cargo metadata` and use its resolve graph.
+    Metadata,
+}
+
+impl Backend {
+    pub const NAMES: &'static [&'static str] = &["lockfile", "metadata"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lockfile" => Some(Backend::Lockfile),
+            "metadata" => Some(Backend::Metadata),
+            _ => None,
+        }
+    }
+}
+
+/// Where the nodes link to with `--link`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Link {
+    /// The documentation on docs.rs.
+    DocsRs,
+    /// The page on crates.io.
+    CratesIo,
+    /// The `repository` of the manifest, or the repository of a git dependency.
+    Repository,
+}
+
+impl Link {
+    pub const NAMES: &'static [&'static str] = &["docs-rs", "crates-io", "repository"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "docs-rs" => Some(Link::DocsRs),
+            "crates-io" => Some(Link::CratesIo),
+            "repository" => Some(Link::Repository),
+            _ => None,
+        }
+    }
+}
+
+/// What the nodes are grouped by with `--cluster-by`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClusterBy {
+    /// Where the crates come from: the local file system, a registry or a git repository.
+    Source,
+}
+
+impl ClusterBy {
+    pub const NAMES: &'static [&'static str] = &["source"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "source" => Some(ClusterBy::Source),
+            _ => None,
+        }
+    }
+}
+
+/// What the nodes are sized by with `--size-by`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SizeBy {
+    /// The number of crates that depend on the crate.
+    FanIn,
+    /// The number of crates that the crate depends on.
+    FanOut,
+    /// The number of crates that the crate depends on, directly or not.
+    Subtree,
+    /// The compile time of the crate, from `--timings`.
+    BuildTime,
+    /// The size of the crate, unpacked or downloaded.
+    Bytes,
+}
+
+impl SizeBy {
+    pub const NAMES: &'static [&'static str] =
+        &["fan-in", "fan-out", "subtree", "build-time", "bytes"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "fan-in" => Some(SizeBy::FanIn),
+            "fan-out" => Some(SizeBy::FanOut),
+            "subtree" => Some(SizeBy::Subtree),
+            "build-time" => Some(SizeBy::BuildTime),
+            "bytes" => Some(SizeBy::Bytes),
+            _ => None,
+        }
+    }
+}
+
+/// Crates drawn in a color of their own with `--highlight`.
+#[derive(Clone, Debug)]
+pub struct Highlight {
+    /// The pattern as it was passed, for the legend.
+    pub name: String,
+    pub pattern: Pattern,
+    pub color: String,
+}
+
+impl Highlight {
+    /// The color of highlighted crates that are not given one.
+    pub const DEFAULT_COLOR: &'static str = "red";
+
+    /// Parses a pattern with an optional color, like `serde` or `tokio-*:#ff8800`.
+    fn new(value: &str) -> CliResult<Self> {
+        let (name, color) = match value.rsplit_once(':') {
+            Some((name, color)) if !color.is_empty() => (name, color),
+            _ => (value, Self::DEFAULT_COLOR),
+        };
+        Ok(Highlight {
+            name: name.into(),
+            pattern: Pattern::new(name)?,
+            color: color.into(),
+        })
+    }
+}
+
+/// A group of crates drawn in a cluster of its own with `--subgraph`.
+#[derive(Clone, Debug)]
+pub struct Subgraph {
+    pub patterns: Vec<Pattern>,
+    pub name: Option<String>,
+    pub color: Option<String>,
+}
+
+/// Overrides of the look of the crates matching some patterns, from a `[[style]]` table of the
+/// file of `--styles`.
+#[derive(Clone, Debug)]
+pub struct Style {
+    pub patterns: Vec<Pattern>,
+    pub color: Option<String>,
+    pub shape: Option<String>,
+}
+
+/// Crates that nearly every graph contains, hidden by `--hide-common`.
+pub const COMMON_CRATES: &[&str] = &[
+    "autocfg",
+    "bitflags",
+    "cfg-if",
+    "lazy_static",
+    "libc",
+    "once_cell",
+    "proc-macro2",
+    "quote",
+    "syn",
+    "unicode-ident",
+    "unicode-xid",
+    "version_check",
+];
 
 #[derive(Clone, Debug)]
 pub struct Config {
+    /// Whether the crates are looked up in the RustSec advisory database.
+    pub advisories: bool,
+    /// The checkout of the advisory database of `--advisory-db`.
+    pub advisory_db: Option<String>,
+    pub ascii: bool,
+    pub audit: bool,
+    pub backend: Backend,
+    pub cluster_by: Option<ClusterBy>,
+    pub collapse: Option<Vec<Pattern>>,
+    /// The colors of the kinds that were given one with `--color-<kind>`.
+    pub colors: HashMap<DepKind, String>,
+    pub crate_spec: Option<String>,
+    pub depth: Option<usize>,
     pub dot_file: Option<String>,
-    pub filter: Option<Vec<String>>,
-    pub include_orphans: bool,
+    pub duplicates: bool,
+    /// The edge styles of the kinds that were given one with `--edge-style-<kind>`.
+    pub edge_styles: HashMap<DepKind, String>,
+    pub edge_version_reqs: bool,
+    /// The graphviz attributes of `--graph-attr`, `--node-attr` and `--edge-attr`.
+    pub edge_attrs: Vec<(String, String)>,
+    pub exclude: Option<Vec<Pattern>>,
+    /// The features of `--features`, split at commas and spaces like cargo does.
+    pub features: Vec<String>,
+    pub all_features: bool,
+    pub no_default_features: bool,
+    pub filter: Option<Vec<Pattern>>,
+    pub flag_loose_reqs: bool,
+    pub focus: Option<Vec<Pattern>>,
+    pub font: Option<String>,
+    pub font_size: Option<f64>,
+    pub format: Format,
+    /// The crates hidden by `--hide` and `--hide-common`.
+    pub hide: Vec<Pattern>,
+    pub generate_lockfile: bool,
+    pub graph_attrs: Vec<(String, String)>,
+    pub git: Option<String>,
+    /// The branch, tag or commit of the git repository.
+    pub git_ref: Option<String>,
+    pub highlight: Vec<Highlight>,
+    pub highlight_edges: bool,
+    /// The fill color of the crates that are in the graph in several versions, with
+    /// `--highlight-duplicates` or `--duplicate-fill`.
+    pub highlight_duplicates: Option<String>,
+    pub highlight_patched: bool,
+    pub highlight_proc_macros: bool,
+    pub badges: bool,
+    pub html_labels: bool,
+    pub license_summary: bool,
+    pub license_policy: LicensePolicy,
+    /// The orphans that `--include-orphans` keeps, all of them if there are no patterns.
+    pub include_orphans: Option<Vec<Pattern>>,
     pub include_vers: bool,
-    pub manifest_path: String,
-    pub subgraph: Option<Vec<String>>,
-    pub subgraph_name: Option<String>,
+    /// The local clone of the crates.io index of `--index-path`.
+    pub index_path: Option<String>,
+    pub invert: Option<Vec<Pattern>>,
+    pub label_format: Option<String>,
+    /// The graphviz layout engine, like `neato`.
+    pub layout: Option<String>,
+    pub legend: bool,
+    pub link: Option<Link>,
+    pub lock_only: Option<String>,
+    /// The old and the new lock file of `--diff`.
+    pub diff: Option<(String, String)>,
+    /// The git revision of `--diff-rev`.
+    pub diff_rev: Option<String>,
+    pub watch: bool,
+    pub critical_path: bool,
+    /// The number of direct dependencies that `--heaviest` lists.
+    pub heaviest: Option<usize>,
+    pub highlight_heaviest: bool,
+    /// The output of `cargo build --timings=json` that `--timings` reads.
+    pub timings: Option<String>,
+    pub crate_sizes: bool,
+    pub downloads: bool,
+    /// The number of downloads below which `--obscure-below` highlights a crate.
+    pub obscure_below: Option<u64>,
+    pub lockfile_path: Option<String>,
+    /// The manifests to graph. Several projects are merged into a single graph.
+    pub manifest_paths: Vec<String>,
+    pub max_nodes: Option<usize>,
+    pub member_clusters: bool,
+    pub merge_versions: bool,
+    pub node_attrs: Vec<(String, String)>,
+    pub only_proc_macros: bool,
+    /// Whether the labels show the `rust-version` of the crates.
+    pub msrv: bool,
+    /// The Rust version of `--check-msrv`.
+    pub check_msrv: Option<String>,
+    pub outdated: bool,
+    /// Whether yanked crates are marked, with `--yanked` or `--deny-yanked`.
+    pub yanked: bool,
+    pub deny_yanked: bool,
+    pub overlap: Option<String>,
+    /// The sources of `--only-git`, `--only-path` and `--only-registry`.
+    pub only_sources: Vec<SourceKind>,
+    pub rank_by_depth: bool,
+    pub rank_by_layer: bool,
+    pub rankdir: Option<String>,
+    pub root: Option<Vec<Pattern>>,
+    pub root_color: Option<String>,
+    pub root_double_border: bool,
+    pub root_fill: Option<String>,
+    pub root_shape: Option<String>,
+    /// The node shapes of the kinds that were given one with `--shape-<kind>`.
+    pub shapes: HashMap<DepKind, String>,
+    pub show_registry: bool,
+    pub show_source: bool,
+    pub stats: bool,
+    /// The file of `--stats-file`, which gets the metrics of `--stats` as JSON.
+    pub stats_file: Option<String>,
+    pub transitive_counts: bool,
+    pub splines: Option<String>,
+    pub size_by: Option<SizeBy>,
+    /// The subgraphs of `--subgraph`, one per occurrence of the option.
+    pub subgraphs: Vec<Subgraph>,
+    /// The rules of `--styles`, in the order of the file.
+    pub styles: Vec<Style>,
+    pub subtree: Option<Vec<Pattern>>,
+    pub target: Option<String>,
+    pub template: Option<String>,
+    pub theme: &'static Theme,
+    pub tooltips: bool,
+    pub transitive_reduction: bool,
+    pub why: Option<Vec<Pattern>>,
+    pub why_from: Option<Vec<Pattern>>,
+    pub workspace_members: Option<Vec<String>>,
 
     pub regular_deps: bool,
     pub build_deps: bool,
     pub dev_deps: bool,
     pub optional_deps: bool,
+    /// Root crates whose dependencies of a kind are included even if the kind is not.
+    pub build_deps_for: Vec<String>,
+    pub dev_deps_for: Vec<String>,
+    pub optional_deps_for: Vec<String>,
 }
 
 impl Config {
     pub fn from_matches(m: &ArgMatches) -> CliResult<Self> {
         let all_deps = m.is_present("all-deps");
+        let dot_file = m.value_of("dot-file");
+
+        // An explicit --format wins, otherwise guess from the extension of the output file.
+        let format = match m.value_of("format") {
+            Some(name) => Format::from_name(name)
+                .ok_or_else(|| CliError::Generic(format!("Unknown output format '{}'", name)))?,
+            None => dot_file.and_then(Format::from_path).unwrap_or(Format::Dot),
+        };
+
+        // The clusters of the styles are subgraphs, after the ones of --subgraph.
+        let mut subgraphs = subgraphs(m)?;
+        let styles = match m.value_of("styles") {
+            Some(path) => {
+                let (styles, clusters) = styles(path)?;
+                subgraphs.extend(clusters);
+                styles
+            }
+            None => vec![],
+        };
+
+        if let Some(toolchain) = m.value_of("check-msrv") {
+            if util::parse_rust_version(toolchain).is_none() {
+                return Err(CliError::Generic(format!(
+                    "Invalid Rust version '{}' for --check-msrv, expected one like 1.70",
+                    toolchain
+                )));
+            }
+        }
+
+        let label_format = m.value_of("label-format").map(String::from);
+        if let Some(label_format) = &label_format {
+            for placeholder in label_format
+                .split('{')
+                .skip(1)
+                .filter_map(|rest| rest.split_once('}'))
+                .map(|(placeholder, _)| placeholder)
+            {
+                if !dep::LABEL_PLACEHOLDERS.contains(&placeholder) {
+                    return Err(CliError::Generic(format!(
+                        "Unknown placeholder '{{{}}}' in --label-format, expected one of {}",
+                        placeholder,
+                        dep::LABEL_PLACEHOLDERS.join(", ")
+                    )));
+                }
+            }
+        }
+
+        let backend = match m.value_of("backend") {
+            Some(name) => Backend::from_name(name)
+                .ok_or_else(|| CliError::Generic(format!("Unknown backend '{}'", name)))?,
+            None => Backend::Lockfile,
+        };
+
+        let depth = match m.value_of("depth") {
+            Some(depth) => Some(depth.parse().map_err(|_| {
+                CliError::Generic(format!("The depth must be a number, not '{}'", depth))
+            })?),
+            None => None,
+        };
+
+        let font_size = match m.value_of("font-size") {
+            Some(size) => Some(
+                size.parse::<f64>()
+                    .ok()
+                    .filter(|&size| size > 0.0)
+                    .ok_or_else(|| {
+                        CliError::Generic(format!(
+                            "The font size must be a positive number, not '{}'",
+                            size
+                        ))
+                    })?,
+            ),
+            None => None,
+        };
+
+        let max_nodes = match m.value_of("max-nodes") {
+            Some(max_nodes) => Some(max_nodes.parse().map_err(|_| {
+                CliError::Generic(format!(
+                    "The maximum number of nodes must be a number, not '{}'",
+                    max_nodes
+                ))
+            })?),
+            None => None,
+        };
+
+        if m.value_of("size-by") == Some("build-time") && !m.is_present("timings") {
+            return Err(CliError::Generic(
+                "--size-by build-time needs the compile times of --timings".into(),
+            ));
+        }
+
+        let heaviest = match m.value_of("heaviest") {
+            Some(heaviest) => Some(heaviest.parse().map_err(|_| {
+                CliError::Generic(format!(
+                    "The number of dependencies of --heaviest must be a number, not '{}'",
+                    heaviest
+                ))
+            })?),
+            None => None,
+        };
+
+        let obscure_below = match m.value_of("obscure-below") {
+            Some(downloads) => Some(downloads.parse().map_err(|_| {
+                CliError::Generic(format!(
+                    "The number of downloads of --obscure-below must be a number, not '{}'",
+                    downloads
+                ))
+            })?),
+            None => None,
+        };
+
+        let mut hide = patterns(m, "hide")?.unwrap_or_default();
+        if m.is_present("hide-common") {
+            hide.extend(
+                COMMON_CRATES
+                    .iter()
+                    .map(|name| Pattern::Exact(name.to_string())),
+            );
+        }
 
         Ok(Config {
-            dot_file: m.value_of("dot-file").map(|s| s.into()),
-            filter: m
-                .values_of("filter")
-                .map(|deps| deps.map(|dep| dep.into()).collect()),
-            include_orphans: m.is_present("include-orphans"),
+            advisories: m.is_present("advisories")
+                || m.is_present("advisory-db")
+                || m.is_present("audit"),
+            advisory_db: m.value_of("advisory-db").map(|s| s.into()),
+            ascii: m.is_present("ascii"),
+            audit: m.is_present("audit"),
+            backend,
+            cluster_by: match m.value_of("cluster-by") {
+                Some(name) => Some(ClusterBy::from_name(name).ok_or_else(|| {
+                    CliError::Generic(format!("Unknown cluster grouping '{}'", name))
+                })?),
+                None => None,
+            },
+            collapse: patterns(m, "collapse")?,
+            colors: per_kind(m, "color"),
+            crate_spec: m.value_of("crate").map(|s| s.into()),
+            depth,
+            dot_file: dot_file.map(|s| s.into()),
+            duplicates: m.is_present("duplicates"),
+            edge_styles: per_kind(m, "edge-style"),
+            edge_version_reqs: m.is_present("edge-version-reqs"),
+            edge_attrs: attributes(m, "edge-attr")?,
+            exclude: patterns(m, "exclude")?,
+            features: m
+                .values_of("features")
+                .into_iter()
+                .flatten()
+                .flat_map(|features| features.split(&[',', ' '][..]))
+                .filter(|feature| !feature.is_empty())
+                .map(|feature| feature.into())
+                .collect(),
+            all_features: m.is_present("all-features"),
+            no_default_features: m.is_present("no-default-features"),
+            filter: patterns(m, "filter")?,
+            flag_loose_reqs: m.is_present("flag-loose-reqs"),
+            focus: patterns(m, "focus")?,
+            font: m.value_of("font").map(String::from),
+            font_size,
+            format,
+            hide,
+            generate_lockfile: m.is_present("generate-lockfile"),
+            graph_attrs: attributes(m, "graph-attr")?,
+            git: m.value_of("git").map(|s| s.into()),
+            git_ref: m
+                .value_of("rev")
+                .or_else(|| m.value_of("branch"))
+                .map(|s| s.into()),
+            highlight: m
+                .values_of("highlight")
+                .into_iter()
+                .flatten()
+                .map(Highlight::new)
+                .collect::<CliResult<_>>()?,
+            highlight_edges: m.is_present("highlight-edges"),
+            highlight_duplicates: if m.is_present("highlight-duplicates")
+                || m.is_present("duplicate-fill")
+            {
+                Some(m.value_of("duplicate-fill").unwrap_or("orange").into())
+            } else {
+                None
+            },
+            highlight_patched: m.is_present("highlight-patched"),
+            highlight_proc_macros: m.is_present("highlight-proc-macros"),
+            badges: m.is_present("badges"),
+            html_labels: m.is_present("html-labels"),
+            license_summary: m.is_present("license-summary"),
+            license_policy: LicensePolicy {
+                allow: m
+                    .values_of("allow-license")
+                    .into_iter()
+                    .flatten()
+                    .map(String::from)
+                    .collect(),
+                deny: m
+                    .values_of("deny-license")
+                    .into_iter()
+                    .flatten()
+                    .map(String::from)
+                    .collect(),
+            },
+            include_orphans: patterns(m, "include-orphans")?,
             include_vers: m.is_present("include-versions"),
-            manifest_path: m.value_of("manifest-path").unwrap_or("Cargo.toml").into(),
-            subgraph: m
-                .values_of("subgraph")
-                .map(|deps| deps.map(|dep| dep.into()).collect()),
-            subgraph_name: m.value_of("subgraph-name").map(|s| s.into()),
+            index_path: m.value_of("index-path").map(|s| s.into()),
+            invert: patterns(m, "invert")?,
+            label_format,
+            layout: m.value_of("layout").map(|s| s.into()),
+            legend: m.is_present("legend"),
+            link: match m.value_of("link") {
+                Some(name) => Some(
+                    Link::from_name(name)
+                        .ok_or_else(|| CliError::Generic(format!("Unknown link '{}'", name)))?,
+                ),
+                None => None,
+            },
+            lock_only: m.value_of("lock-only").map(|s| s.into()),
+            diff: m.values_of("diff").map(|mut paths| {
+                let old = paths.next().unwrap_or_default().to_string();
+                let new = paths.next().unwrap_or_default().to_string();
+                (old, new)
+            }),
+            diff_rev: m.value_of("diff-rev").map(|s| s.into()),
+            watch: m.is_present("watch"),
+            critical_path: m.is_present("critical-path"),
+            heaviest,
+            highlight_heaviest: m.is_present("highlight-heaviest"),
+            timings: m.value_of("timings").map(|s| s.into()),
+            crate_sizes: m.is_present("crate-sizes"),
+            downloads: m.is_present("downloads"),
+            obscure_below,
+            lockfile_path: m.value_of("lockfile-path").map(|s| s.into()),
+            manifest_paths: m
+                .values_of("manifest-path")
+                .map(|paths| paths.map(|path| path.into()).collect())
+                .unwrap_or_else(|| vec!["Cargo.toml".into()]),
+            max_nodes,
+            member_clusters: !m.is_present("no-member-clusters"),
+            merge_versions: m.is_present("merge-versions"),
+            node_attrs: attributes(m, "node-attr")?,
+            only_proc_macros: m.is_present("only-proc-macros"),
+            msrv: m.is_present("msrv"),
+            check_msrv: m.value_of("check-msrv").map(|s| s.into()),
+            outdated: m.is_present("outdated"),
+            yanked: m.is_present("yanked") || m.is_present("deny-yanked"),
+            deny_yanked: m.is_present("deny-yanked"),
+            overlap: m.value_of("overlap").map(|s| s.into()),
+            only_sources: [
+                ("only-git", SourceKind::Git),
+                ("only-path", SourceKind::Path),
+                ("only-registry", SourceKind::Registry),
+            ]
+            .iter()
+            .filter(|(flag, _)| m.is_present(flag))
+            .map(|&(_, kind)| kind)
+            .collect(),
+            rank_by_depth: m.is_present("rank-by-depth"),
+            rank_by_layer: m.is_present("rank-by-layer"),
+            rankdir: m.value_of("rankdir").map(|s| s.into()),
+            root: patterns(m, "root")?,
+            root_color: m.value_of("root-color").map(|s| s.into()),
+            root_double_border: m.is_present("root-double-border"),
+            root_fill: m.value_of("root-fill").map(|s| s.into()),
+            root_shape: m.value_of("root-shape").map(|s| s.into()),
+            shapes: per_kind(m, "shape"),
+            show_registry: m.is_present("show-registry"),
+            show_source: m.is_present("show-source"),
+            stats: m.is_present("stats"),
+            stats_file: m.value_of("stats-file").map(|s| s.into()),
+            transitive_counts: m.is_present("transitive-counts"),
+            splines: m.value_of("splines").map(|s| s.into()),
+            size_by: match m.value_of("size-by") {
+                Some(name) => Some(SizeBy::from_name(name).ok_or_else(|| {
+                    CliError::Generic(format!("Unknown size measure '{}'", name))
+                })?),
+                None => None,
+            },
+            subgraphs,
+            styles,
+            subtree: patterns(m, "subtree")?,
+            target: m.value_of("target").map(|s| s.into()),
+            template: m.value_of("template").map(|s| s.into()),
+            theme: match m.value_of("theme") {
+                Some(name) => Theme::from_name(name)
+                    .ok_or_else(|| CliError::Generic(format!("Unknown theme '{}'", name)))?,
+                None => &theme::LIGHT,
+            },
+            tooltips: m.is_present("tooltips"),
+            transitive_reduction: m.is_present("transitive-reduction"),
+            why: patterns(m, "why")?,
+            why_from: patterns(m, "from")?,
+            workspace_members: m
+                .values_of("workspace-member")
+                .map(|members| members.map(|member| member.into()).collect()),
 
             regular_deps: !m.is_present("no-regular-deps"),
             build_deps: all_deps || m.is_present("build-deps"),
             dev_deps: all_deps || m.is_present("dev-deps"),
             optional_deps: all_deps || m.is_present("optional-deps"),
+            build_deps_for: m
+                .values_of("build-deps-for")
+                .into_iter()
+                .flatten()
+                .map(String::from)
+                .collect(),
+            dev_deps_for: m
+                .values_of("dev-deps-for")
+                .into_iter()
+                .flatten()
+                .map(String::from)
+                .collect(),
+            optional_deps_for: m
+                .values_of("optional-deps-for")
+                .into_iter()
+                .flatten()
+                .map(String::from)
+                .collect(),
         })
     }
 }
+
+impl Config {
+    /// The features requested for the root crates, if any of the feature options were passed.
+    /// Without them, optional dependencies are shown or not as a whole with `--optional-deps`.
+    pub fn feature_request(&self) -> Option<FeatureRequest> {
+        if self.features.is_empty() && !self.all_features && !self.no_default_features {
+            return None;
+        }
+
+        Some(FeatureRequest {
+            features: self.features.clone(),
+            default_features: !self.no_default_features,
+            all_features: self.all_features,
+        })
+    }
+
+    /// The color of a kind of dependency in the graph, from `--color-<kind>` or the theme.
+    pub fn color(&self, kind: DepKind) -> &str {
+        self.colors
+            .get(&kind)
+            .map_or(self.theme.color(kind), |color| color.as_str())
+    }
+
+    /// The color of a crate highlighted with `--highlight`, from the first pattern it matches.
+    pub fn highlight_color(&self, name: &str) -> Option<&str> {
+        self.highlight
+            .iter()
+            .find(|highlight| highlight.pattern.matches(name))
+            .map(|highlight| highlight.color.as_str())
+    }
+
+    /// Whether an orphan crate is kept by `--include-orphans`, which keeps every orphan without
+    /// patterns.
+    pub fn keeps_orphan(&self, name: &str) -> bool {
+        match &self.include_orphans {
+            Some(patterns) => patterns.is_empty() || util::matches_any(patterns, name),
+            None => false,
+        }
+    }
+
+    /// The index of the first subgraph with a pattern that the crate matches.
+    pub fn subgraph_of(&self, name: &str) -> Option<usize> {
+        self.subgraphs
+            .iter()
+            .position(|subgraph| util::matches_any(&subgraph.patterns, name))
+    }
+
+    /// Whether the sizes of the crates are looked up, for `--crate-sizes` or `--size-by bytes`.
+    pub fn shows_crate_sizes(&self) -> bool {
+        self.crate_sizes || self.size_by == Some(SizeBy::Bytes)
+    }
+
+    /// Whether the download counts of the crates are looked up, for `--downloads` or
+    /// `--obscure-below`.
+    pub fn shows_downloads(&self) -> bool {
+        self.downloads || self.obscure_below.is_some()
+    }
+
+    /// The node shape of the root crates, from `--root-shape` or the default.
+    pub fn root_shape(&self) -> &str {
+        self.root_shape.as_deref().unwrap_or("box")
+    }
+
+    /// The color of the crate from the first style that matches it and sets one.
+    pub fn style_color(&self, name: &str) -> Option<&str> {
+        self.styles
+            .iter()
+            .filter(|style| util::matches_any(&style.patterns, name))
+            .find_map(|style| style.color.as_deref())
+    }
+
+    /// The shape of the crate from the first style that matches it and sets one.
+    pub fn style_shape(&self, name: &str) -> Option<&str> {
+        self.styles
+            .iter()
+            .filter(|style| util::matches_any(&style.patterns, name))
+            .find_map(|style| style.shape.as_deref())
+    }
+
+    /// Whether the crate is in any of the subgraphs.
+    pub fn in_subgraph(&self, name: &str) -> bool {
+        self.subgraph_of(name).is_some()
+    }
+
+    /// The style of the edges of a kind of dependency, from `--edge-style-<kind>` or the default,
+    /// if they are not solid.
+    pub fn edge_style(&self, kind: DepKind) -> Option<&str> {
+        match self.edge_styles.get(&kind) {
+            Some(style) => Some(style),
+            None => kind.edge_style(),
+        }
+    }
+
+    /// Whether the labels, the template or `--check-msrv` need the `rust-version` of the crates,
+    /// which the lockfile backend then has to look up.
+    pub fn shows_rust_versions(&self) -> bool {
+        self.msrv
+            || self.check_msrv.is_some()
+            || self.template.is_some()
+            || self
+                .label_format
+                .as_ref()
+                .is_some_and(|format| format.contains("{msrv}"))
+    }
+
+    /// Whether the labels, the template, the summary, the policy or an SPDX document need
+    /// licenses, which the lockfile backend then has to look up.
+    pub fn shows_licenses(&self) -> bool {
+        self.html_labels
+            || matches!(self.format, Format::Spdx | Format::SpdxJson)
+            || self.license_summary
+            || !self.license_policy.is_empty()
+            || self.template.is_some()
+            || self
+                .label_format
+                .as_ref()
+                .is_some_and(|format| format.contains("{license}"))
+    }
+
+    /// Whether the dependencies of a kind are included, for all crates or, with
+    /// `--<kind>-deps-for`, for the given root crate.
+    pub fn includes_kind(&self, kind: DepKind, root: Option<&str>) -> bool {
+        let (all, members) = match kind {
+            DepKind::Regular => return self.regular_deps,
+            DepKind::Build => (self.build_deps, &self.build_deps_for),
+            DepKind::Dev => (self.dev_deps, &self.dev_deps_for),
+            DepKind::Optional => (self.optional_deps, &self.optional_deps_for),
+            DepKind::Unknown => return false,
+        };
+        all || root.is_some_and(|root| members.iter().any(|member| member == root))
+    }
+
+    /// The crate names passed to any of the `--<kind>-deps-for` options.
+    pub fn per_member_kinds(&self) -> impl Iterator<Item = &String> {
+        self.build_deps_for
+            .iter()
+            .chain(&self.dev_deps_for)
+            .chain(&self.optional_deps_for)
+    }
+}
+
+/// The default options of the `[package.metadata.deps]` table of the manifest (or
+/// `[workspace.metadata.deps]` of a virtual manifest) as command line arguments, with the path of
+/// the manifest. Keys are the long names of the options, like `include-versions = true`, `format =
+/// "svg"` or `filter = ["serde*", "tokio"]`. An array of arrays passes an option several times, as
+/// for several subgraphs. The paths of `styles` and `template` are relative to the manifest.
+/// Options that are given on the command line are left out, so that the command line wins.
+pub fn manifest_args(m: &ArgMatches) -> CliResult<Option<(PathBuf, Vec<OsString>)>> {
+    if m.is_present("no-manifest-options")
+        || ["lock-only", "diff", "diff-rev", "crate", "git"]
+            .iter()
+            .any(|&name| m.is_present(name))
+    {
+        return Ok(None);
+    }
+
+    // Look for the manifest like the graph does, without reporting where it was found twice.
+    let manifest_path = match m.value_of("manifest-path") {
+        Some("-") => return Ok(None),
+        Some(path) if m.occurrences_of("manifest-path") > 0 => Some(PathBuf::from(path)),
+        _ => env::current_dir()?
+            .ancestors()
+            .map(|dir| dir.join("Cargo.toml"))
+            .find(|path| path.is_file()),
+    };
+    let manifest_path = match manifest_path {
+        Some(path) if path.is_file() => path,
+        _ => return Ok(None),
+    };
+    let manifest = util::toml_from_file(&manifest_path)?;
+    let table = ["package", "workspace"]
+        .iter()
+        .find_map(|section| manifest.get(section)?.get("metadata")?.get("deps"));
+    let table = match table {
+        Some(Value::Table(table)) => table,
+        Some(_) => {
+            return Err(CliError::Toml(format!(
+                "[package.metadata.deps] of {} must be a table",
+                manifest_path.display()
+            )))
+        }
+        None => return Ok(None),
+    };
+
+    let invalid = |key: &str| {
+        CliError::Toml(format!(
+            "Unsupported value of '{}' in [package.metadata.deps] of {}",
+            key,
+            manifest_path.display()
+        ))
+    };
+    let scalar = |key: &str, value: &Value| match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Integer(i) => Ok(i.to_string()),
+        Value::Float(f) => Ok(f.to_string()),
+        _ => Err(invalid(key)),
+    };
+
+    let mut args = vec![];
+    for (key, value) in table {
+        let name = key.replace('_', "-");
+        // The names and colors of subgraphs go with the subgraphs they are given with.
+        let given_with = match name.as_str() {
+            "subgraph-name" | "subgraph-color" => "subgraph",
+            name => name,
+        };
+        if m.occurrences_of(&name) > 0 || m.occurrences_of(given_with) > 0 {
+            continue;
+        }
+        let option = OsString::from(format!("--{}", name));
+        match value {
+            Value::Boolean(true) => args.push(option),
+            Value::Boolean(false) => {}
+            Value::Array(values) => {
+                for value in values {
+                    args.push(option.clone());
+                    match value {
+                        Value::Array(values) => {
+                            for value in values {
+                                args.push(scalar(key, value)?.into());
+                            }
+                        }
+                        value => args.push(scalar(key, value)?.into()),
+                    }
+                }
+            }
+            // Files next to the manifest are found from any directory.
+            value if ["styles", "template"].contains(&name.as_str()) => {
+                let dir = manifest_path.parent().unwrap_or_else(|| Path::new(""));
+                args.push(option);
+                args.push(dir.join(scalar(key, value)?).into());
+            }
+            value => {
+                args.push(option);
+                args.push(scalar(key, value)?.into());
+            }
+        }
+    }
+
+    Ok(Some((manifest_path, args)))
+}
+
+/// The values passed to the `--<option>-<kind>` arguments, by kind.
+fn per_kind(m: &ArgMatches, option: &str) -> HashMap<DepKind, String> {
+    DepKind::ALL
+        .iter()
+        .filter_map(|&kind| {
+            let value = m.value_of(format!("{}-{}", option, kind.name()))?;
+            Some((kind, value.to_string()))
+        })
+        .collect()
+}
+
+/// The graphviz attributes passed to an argument, like `splines=ortho`.
+fn attributes(m: &ArgMatches, name: &str) -> CliResult<Vec<(String, String)>> {
+    m.values_of(name)
+        .into_iter()
+        .flatten()
+        .map(|attr| match attr.split_once('=') {
+            Some((key, value))
+                if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+            {
+                Ok((key.to_string(), value.to_string()))
+            }
+            _ => Err(CliError::Generic(format!(
+                "Graphviz attributes must be given like key=value, not '{}'",
+                attr
+            ))),
+        })
+        .collect()
+}
+
+/// The subgraphs of `--subgraph`. The values of each occurrence of the option make up one
+/// subgraph, which gets the `--subgraph-name` and `--subgraph-color` at the same position.
+fn subgraphs(m: &ArgMatches) -> CliResult<Vec<Subgraph>> {
+    let (values, indices) = match (m.values_of("subgraph"), m.indices_of("subgraph")) {
+        (Some(values), Some(indices)) => (values, indices),
+        _ => return Ok(vec![]),
+    };
+
+    // The values of one occurrence have consecutive indices.
+    let mut groups: Vec<Vec<Pattern>> = vec![];
+    let mut last_index = None;
+    for (value, index) in values.zip(indices) {
+        if last_index.is_none_or(|last_index| index != last_index + 1) {
+            groups.push(vec![]);
+        }
+        groups.last_mut().unwrap().push(Pattern::new(value)?);
+        last_index = Some(index);
+    }
+
+    let names: Vec<&str> = m.values_of("subgraph-name").into_iter().flatten().collect();
+    let colors: Vec<&str> = m
+        .values_of("subgraph-color")
+        .into_iter()
+        .flatten()
+        .collect();
+    if names.len() > groups.len() || colors.len() > groups.len() {
+        return Err(CliError::Generic(
+            "There are more --subgraph-name or --subgraph-color options than subgraphs".into(),
+        ));
+    }
+
+    Ok(groups
+        .into_iter()
+        .enumerate()
+        .map(|(i, patterns)| Subgraph {
+            patterns,
+            name: names.get(i).map(|name| name.to_string()),
+            color: colors.get(i).map(|color| color.to_string()),
+        })
+        .collect())
+}
+
+/// Reads the `[[style]]` tables of a styles file, like:
+///
+/// ```toml
+/// [[style]]
+/// crates = ["our-company-*"]
+/// color = "darkgreen"
+/// shape = "box"
+/// cluster = "In-house crates"
+/// ```
+///
+/// Crates with the same `cluster` are grouped in a subgraph of that name, in the order in which
+/// the clusters first appear.
+fn styles(path: &str) -> CliResult<(Vec<Style>, Vec<Subgraph>)> {
+    let file = util::toml_from_file(path)?;
+    let invalid = |msg: String| CliError::Toml(format!("{} in {}", msg, path));
+
+    let tables = match file.get("style") {
+        Some(Value::Array(tables)) => tables.as_slice(),
+        Some(_) => return Err(invalid("'style' must be an array of tables".into())),
+        None => &[],
+    };
+    let mut styles = vec![];
+    let mut clusters: Vec<Subgraph> = vec![];
+    for table in tables {
+        let table = table
+            .as_table()
+            .ok_or_else(|| invalid("'style' must be an array of tables".into()))?;
+        let string = |key: &str| -> CliResult<Option<String>> {
+            match table.get(key) {
+                Some(Value::String(s)) => Ok(Some(s.clone())),
+                Some(_) => Err(invalid(format!("'{}' of a style must be a string", key))),
+                None => Ok(None),
+            }
+        };
+        if let Some(key) = table
+            .keys()
+            .find(|key| !["crates", "color", "shape", "cluster"].contains(&key.as_str()))
+        {
+            return Err(invalid(format!("Unknown style attribute '{}'", key)));
+        }
+
+        let patterns = match table.get("crates") {
+            Some(Value::String(pattern)) => vec![Pattern::new(pattern)?],
+            Some(Value::Array(patterns)) => patterns
+                .iter()
+                .map(|pattern| match pattern.as_str() {
+                    Some(pattern) => Pattern::new(pattern),
+                    None => Err(invalid("'crates' of a style must be strings".into())),
+                })
+                .collect::<CliResult<_>>()?,
+            _ => return Err(invalid("A style needs the 'crates' it applies to".into())),
+        };
+
+        if let Some(cluster) = string("cluster")? {
+            match clusters
+                .iter_mut()
+                .find(|subgraph| subgraph.name.as_ref() == Some(&cluster))
+            {
+                Some(subgraph) => subgraph.patterns.extend(patterns.iter().cloned()),
+                None => clusters.push(Subgraph {
+                    patterns: patterns.clone(),
+                    name: Some(cluster),
+                    color: None,
+                }),
+            }
+        }
+        styles.push(Style {
+            patterns,
+            color: string("color")?,
+            shape: string("shape")?,
+        });
+    }
+
+    Ok((styles, clusters))
+}
+
+/// The crate name patterns passed to an argument.
+fn patterns(m: &ArgMatches, name: &str) -> CliResult<Option<Vec<Pattern>>> {
+    match m.values_of(name) {
+        Some(values) => Ok(Some(values.map(Pattern::new).collect::<CliResult<_>>()?)),
+        None => Ok(None),
+    }
+}
@@ -1,7 +1,10 @@
+use crate::advisory::Advisory;
+use crate::config::Link;
 use crate::graph::{DepGraph, Node};
+use crate::registry;
 use std::io::{Result, Write};
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum DepKind {
     Regular,
     Build,
@@ -10,6 +13,148 @@ pub enum DepKind {
     Unknown,
 }
 
+/// Marker that `--badges` puts in front of the labels of proc-macro crates.
+pub const PROC_MACRO_BADGE: &str = "\u{2699}";
+
+/// The placeholders that `--label-format` accepts.
+pub const LABEL_PLACEHOLDERS: &[&str] = &[
+    "name",
+    "version",
+    "kind",
+    "source",
+    "license",
+    "registry",
+    "advisories",
+    "latest",
+    "msrv",
+    "transitive",
+    "build_time",
+    "size",
+];
+
+impl DepKind {
+    pub const ALL: [DepKind; 5] = [
+        DepKind::Regular,
+        DepKind::Build,
+        DepKind::Dev,
+        DepKind::Optional,
+        DepKind::Unknown,
+    ];
+
+    /// Lowercase name of the kind, as used in non-DOT output formats.
+    pub fn name(self) -> &'static str {
+        match self {
+            DepKind::Regular => "regular",
+            DepKind::Build => "build",
+            DepKind::Dev => "dev",
+            DepKind::Optional => "optional",
+            DepKind::Unknown => "orphan",
+        }
+    }
+
+    /// Default style of the edges of the kind in the graph, if they are not solid.
+    pub fn edge_style(self) -> Option<&'static str> {
+        match self {
+            DepKind::Regular => None,
+            DepKind::Build => Some("dotted"),
+            _ => Some("dashed"),
+        }
+    }
+
+    /// Marker that `--badges` puts in front of the labels of the kind, if any.
+    pub fn badge(self) -> Option<&'static str> {
+        match self {
+            DepKind::Build => Some("\u{1f527}"),
+            DepKind::Dev => Some("\u{1f9ea}"),
+            DepKind::Optional => Some("\u{2754}"),
+            _ => None,
+        }
+    }
+
+    /// Symbol shown next to the kind in table labels.
+    pub fn icon(self) -> &'static str {
+        match self {
+            DepKind::Regular => "\u{25cf}",
+            DepKind::Build => "\u{2692}",
+            DepKind::Dev => "\u{2697}",
+            DepKind::Optional => "\u{25cc}",
+            DepKind::Unknown => "\u{25cb}",
+        }
+    }
+}
+
+/// A compile time in seconds, like `2.5s`.
+pub fn format_build_time(secs: f64) -> String {
+    format!("{:.1}s", secs)
+}
+
+/// A size in bytes, like `45.2 KiB`.
+pub fn format_size(bytes: u64) -> String {
+    let units = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < units.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, units[unit])
+}
+
+/// A count with its thousands separated, like `1,234,567`.
+pub fn format_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Where a crate comes from.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SourceKind {
+    /// A local crate: a root crate or a path dependency.
+    Path,
+    Git,
+    /// crates.io, an alternate registry or a source replacing one, like a vendor directory.
+    Registry,
+}
+
+/// How a crate changed from the old lock file to the new one, with `--diff`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiffStatus {
+    Added,
+    Removed,
+    /// The version changed, from the given one to the version of the crate.
+    Changed(String),
+}
+
+impl DiffStatus {
+    /// Lowercase name of the change, as used in non-DOT output formats.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DiffStatus::Added => "added",
+            DiffStatus::Removed => "removed",
+            DiffStatus::Changed(_) => "changed",
+        }
+    }
+
+    /// The fill color of the nodes of crates with the change.
+    pub fn fill(&self) -> &'static str {
+        match self {
+            DiffStatus::Added => "palegreen",
+            DiffStatus::Removed => "lightcoral",
+            DiffStatus::Changed(_) => "yellow",
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct RootCrate {
     pub name: String,
@@ -20,7 +165,69 @@ pub struct RootCrate {
 pub struct ResolvedDep {
     pub name: String,
     pub ver: String,
+    /// Every version of the crate if `--merge-versions` merged several into the node. `ver` stays
+    /// the first one, so that outputs that need a single version still get a valid one.
+    pub merged_vers: Vec<String>,
     pub force_write_ver: bool,
+    pub license: Option<String>,
+    /// The `source` field in the lock file. Path dependencies and root crates have none.
+    pub source: Option<String>,
+    /// Whether the crate is overridden by a [patch] or [replace] section of the manifest.
+    pub patched: bool,
+    /// The name of the registry the crate comes from. Path and git dependencies have none.
+    pub registry: Option<String>,
+    /// Whether the crate is a proc-macro crate. This is only looked up for the registry crates of
+    /// the lockfile backend if it is needed.
+    pub proc_macro: bool,
+    /// The number of dependencies folded into the node by `--collapse`.
+    pub collapsed: Option<usize>,
+    /// The number of crates below the node that `--depth` or `--max-nodes` left out.
+    pub truncated: Option<usize>,
+    /// The number of crates that the crate depends on, directly or transitively, with
+    /// `--transitive-counts`.
+    pub transitive: Option<usize>,
+    /// How much larger `--size-by` draws the node than the graphviz default.
+    pub scale: Option<f64>,
+    /// The `repository` of the manifest. This is only looked up for the registry crates of the
+    /// lockfile backend if it is needed.
+    pub repository: Option<String>,
+    /// The `description` of the manifest. This is only looked up for the lockfile backend if it
+    /// is needed.
+    pub description: Option<String>,
+    /// Whether the license of the crate is rejected by `--allow-license` or `--deny-license`.
+    pub license_violation: bool,
+    /// The security advisories that affect the version of the crate, with `--advisories`.
+    pub advisories: Vec<Advisory>,
+    /// The newest version on crates.io, if it is newer than the version of the crate, with
+    /// `--outdated`.
+    pub latest: Option<String>,
+    /// Whether the version of the crate is yanked from crates.io, with `--yanked`.
+    pub yanked: bool,
+    /// Whether the crate is in the graph in several versions, with `--highlight-duplicates`.
+    pub duplicate: bool,
+    /// How the crate changed between the lock files, with `--diff`.
+    pub diff: Option<DiffStatus>,
+    /// The position of the crate on the longest chain of dependencies, with `--critical-path`.
+    pub critical_path: Option<usize>,
+    /// Whether the crate is one of the direct dependencies that pull in the most crates of their
+    /// own, with `--highlight-heaviest`.
+    pub heavy: bool,
+    /// How long the crate took to compile in seconds, from `--timings`.
+    pub build_time: Option<f64>,
+    /// The size of the `.crate` file in bytes, with `--crate-sizes`.
+    pub download_size: Option<u64>,
+    /// The size of the unpacked files in bytes, with `--crate-sizes`.
+    pub unpacked_size: Option<u64>,
+    /// How often all versions of the crate have been downloaded from crates.io, with
+    /// `--downloads`.
+    pub downloads: Option<u64>,
+    /// Whether the crate has fewer downloads than the threshold of `--obscure-below`.
+    pub obscure: bool,
+    /// The `rust-version` of the manifest. This is only looked up for the lockfile backend if it
+    /// is needed.
+    pub rust_version: Option<String>,
+    /// Whether the `rust-version` is newer than the Rust version of `--check-msrv`.
+    pub msrv_violation: bool,
 
     pub is_regular: bool,
     pub is_build: bool,
@@ -36,7 +243,34 @@ impl ResolvedDep {
         ResolvedDep {
             name,
             ver,
+            merged_vers: vec![],
             force_write_ver: false,
+            license: None,
+            source: None,
+            patched: false,
+            registry: None,
+            proc_macro: false,
+            collapsed: None,
+            truncated: None,
+            transitive: None,
+            scale: None,
+            repository: None,
+            description: None,
+            license_violation: false,
+            advisories: vec![],
+            latest: None,
+            yanked: false,
+            duplicate: false,
+            diff: None,
+            critical_path: None,
+            heavy: false,
+            build_time: None,
+            download_size: None,
+            unpacked_size: None,
+            downloads: None,
+            obscure: false,
+            rust_version: None,
+            msrv_violation: false,
 
             is_regular: false,
             is_build: false,
@@ -62,25 +296,502 @@ impl ResolvedDep {
         }
     }
 
+    pub fn source_kind(&self) -> SourceKind {
+        match &self.source {
+            None => SourceKind::Path,
+            Some(source) if source.starts_with("git+") => SourceKind::Git,
+            Some(_) => SourceKind::Registry,
+        }
+    }
+
+    /// Package URL of the crate, as used in SBOMs.
+    pub fn purl(&self) -> String {
+        format!("pkg:cargo/{}@{}", self.name, self.ver)
+    }
+
+    /// Where the node links to with `--link`. Only crates.io crates are on docs.rs and crates.io,
+    /// and git dependencies link to their repository if the manifest does not name one.
+    pub fn url(&self, link: Link) -> Option<String> {
+        let on_crates_io = self.registry.as_deref() == Some("crates-io");
+        match link {
+            Link::DocsRs if on_crates_io => {
+                Some(format!("https://docs.rs/{}/{}", self.name, self.ver))
+            }
+            Link::CratesIo if on_crates_io => Some(format!(
+                "https://crates.io/crates/{}/{}",
+                self.name, self.ver
+            )),
+            Link::Repository => self.repository.clone().or_else(|| {
+                let url = self.source.as_ref()?.strip_prefix("git+")?;
+                Some(url.split(&['?', '#'][..]).next().unwrap_or(url).to_string())
+            }),
+            _ => None,
+        }
+    }
+
+    /// The registry to show on the node, if it is not crates.io and `--show-registry` was passed.
+    /// `--show-source` shows the registry as part of the source instead.
+    pub fn shown_registry(&self, dg: &DepGraph) -> Option<&str> {
+        match &self.registry {
+            Some(registry)
+                if dg.cfg.show_registry && !dg.cfg.show_source && registry != "crates-io" =>
+            {
+                Some(registry)
+            }
+            _ => None,
+        }
+    }
+
+    /// Where the crate comes from, for `--show-source`: `path`, `crates.io`, the name of another
+    /// registry, or the URL of a git repository with the branch or tag and the revision, which is
+    /// shortened unless `full` is set.
+    pub fn source_label(&self, full: bool) -> String {
+        let source = match &self.source {
+            Some(source) => source,
+            None => return "path".into(),
+        };
+        let git = match source.strip_prefix("git+") {
+            Some(git) => git,
+            None => {
+                // Without a known registry name, e.g. with `--lock-only`, the index URL is shown.
+                let index = registry::index_url(source);
+                return match (self.registry.as_deref(), index) {
+                    (Some("crates-io"), _) => "crates.io".into(),
+                    (Some(registry), _) => registry.into(),
+                    (None, Some(index)) if is_crates_io_index(&index) => "crates.io".into(),
+                    (None, Some(index)) => index,
+                    (None, None) => source.clone(),
+                };
+            }
+        };
+
+        // Git sources look like `git+URL?branch=NAME#REVISION`.
+        let (url, rev) = git.split_once('#').unwrap_or((git, ""));
+        let (url, query) = url.split_once('?').unwrap_or((url, ""));
+        let url = if full {
+            url
+        } else {
+            url.split_once("://").map_or(url, |(_, rest)| rest)
+        };
+        let mut label = format!("git: {}", url);
+        for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            if key == "branch" || key == "tag" {
+                label.push_str(&format!(" ({} {})", key, value));
+            }
+        }
+        if !rev.is_empty() {
+            let rev = if full { rev } else { &rev[..rev.len().min(8)] };
+            label.push_str(&format!(" @ {}", rev));
+        }
+        label
+    }
+
     pub fn label<W: Write>(&self, w: &mut W, dg: &DepGraph) -> Result<()> {
-        let name = if self.force_write_ver || dg.cfg.include_vers {
-            format!("{} v{}", self.name, self.ver)
+        let badges = if dg.cfg.badges {
+            self.badges()
         } else {
-            self.name.clone()
+            String::new()
+        };
+        let name = if let Some(format) = &dg.cfg.label_format {
+            format!("\"{}{}\"", badges, self.formatted_label(format))
+        } else if dg.cfg.html_labels {
+            self.html_label(dg, &badges)
+        } else {
+            let mut name = if self.force_write_ver || dg.cfg.include_vers {
+                format!("{}{} v{}", badges, self.name, self.label_ver())
+            } else {
+                format!("{}{}", badges, self.name)
+            };
+            if let Some(count) = self.collapsed {
+                name.push_str(&format!(" (+{} deps)", count));
+            }
+            if let Some(count) = self.transitive.filter(|&count| count > 0) {
+                name.push_str(&format!(" [+{}]", count));
+            }
+            if let Some(DiffStatus::Changed(previous)) = &self.diff {
+                name.push_str(&format!("\\nv{} \u{2192} v{}", previous, self.ver));
+            }
+            if let Some(time) = self.build_time {
+                name.push_str(&format!("\\n{}", format_build_time(time)));
+            }
+            if let Some(size) = self.size().filter(|_| dg.cfg.crate_sizes) {
+                name.push_str(&format!("\\n{}", format_size(size)));
+            }
+            if let Some(registry) = self.shown_registry(dg) {
+                name.push_str(&format!("\\n[{}]", registry));
+            }
+            if dg.cfg.show_source {
+                let source = self.source_label(false);
+                name.push_str(&format!("\\n{}", source.replace('"', "\\\"")));
+            }
+            if let Some(rust_version) = self.shown_rust_version(dg) {
+                name.push_str(&format!("\\nMSRV {}", rust_version));
+            }
+            if self.yanked {
+                name.push_str("\\nyanked");
+            }
+            if let Some(latest) = &self.latest {
+                name.push_str(&format!("\\nlatest: {}", latest));
+            }
+            for advisory in &self.advisories {
+                name.push_str(&format!("\\n{}", advisory.id));
+            }
+            format!("\"{}\"", name)
         };
 
-        let shape = if dg.root_deps_map.contains_key(&self.name) {
-            ", shape=box"
+        let mut attrs = vec![format!("label={}", name)];
+        let kind = self.kind();
+        let is_root = kind == DepKind::Regular && dg.root_deps_map.contains_key(&self.name);
+        if let Some(shape) = dg.cfg.style_shape(&self.name) {
+            attrs.push(format!("shape={}", dot_id(shape)));
+        } else if is_root {
+            attrs.push(format!("shape={}", dot_id(dg.cfg.root_shape())));
+        } else if let Some(shape) = dg.cfg.shapes.get(&kind) {
+            attrs.push(format!("shape={}", dot_id(shape)));
+        }
+        // Regular nodes are drawn in the default color of the graph.
+        if self.license_violation {
+            attrs.push("color=red".into());
+            attrs.push("fontcolor=red".into());
+            attrs.push("penwidth=3".into());
+        } else if self.msrv_violation {
+            attrs.push("color=firebrick".into());
+            attrs.push("fontcolor=firebrick".into());
+            attrs.push("penwidth=3".into());
+        } else if self.heavy {
+            attrs.push("color=darkorange".into());
+            attrs.push("fontcolor=darkorange".into());
+            attrs.push("penwidth=3".into());
+        } else if self.obscure {
+            attrs.push("color=purple".into());
+            attrs.push("fontcolor=purple".into());
+            attrs.push("penwidth=3".into());
+        } else if let Some(color) = dg.cfg.highlight_color(&self.name) {
+            attrs.push(format!("color={}", dot_id(color)));
+            attrs.push(format!("fontcolor={}", dot_id(color)));
+            attrs.push("penwidth=3".into());
+        } else if let Some(color) = dg.cfg.style_color(&self.name) {
+            attrs.push(format!("color={}", dot_id(color)));
+        } else if let Some(color) = dg.cfg.root_color.as_ref().filter(|_| is_root) {
+            attrs.push(format!("color={}", dot_id(color)));
+        } else if kind != DepKind::Regular {
+            attrs.push(format!("color={}", dot_id(dg.cfg.color(kind))));
+        }
+
+        if let Some(scale) = self.scale {
+            attrs.push(format!("width={:.2}", 0.75 * scale));
+            attrs.push(format!("height={:.2}", 0.5 * scale));
+            let font_size = dg.cfg.font_size.unwrap_or(14.0);
+            attrs.push(format!("fontsize={:.1}", font_size * scale));
+        }
+
+        if let Some(url) = dg.cfg.link.and_then(|link| self.url(link)) {
+            attrs.push(format!("URL={}", dot_id(&url)));
+        }
+        let mut tooltip = vec![];
+        if let Some(description) = self.description.as_ref().filter(|_| dg.cfg.tooltips) {
+            // Descriptions often span several lines in the manifest.
+            let description: Vec<&str> = description.split_whitespace().collect();
+            tooltip.push(description.join(" "));
+        }
+        if dg.cfg.show_source {
+            tooltip.push(format!("source: {}", self.source_label(true)));
+        }
+        for advisory in &self.advisories {
+            tooltip.push(format!("{}: {}", advisory.id, advisory.title));
+        }
+        if let Some(downloads) = self.downloads {
+            tooltip.push(format!("downloads: {}", format_count(downloads)));
+        }
+        if dg.cfg.crate_sizes {
+            if let Some(size) = self.download_size {
+                tooltip.push(format!("download: {}", format_size(size)));
+            }
+            if let Some(size) = self.unpacked_size {
+                tooltip.push(format!("unpacked: {}", format_size(size)));
+            }
+        }
+        if !tooltip.is_empty() {
+            attrs.push(format!("tooltip={}", dot_id(&tooltip.join("\n"))));
+        }
+
+        if self.critical_path.is_some() && !attrs.iter().any(|a| a.starts_with("penwidth=")) {
+            attrs.push("penwidth=3".into());
+        }
+
+        if is_root && dg.cfg.root_double_border {
+            attrs.push("peripheries=2".into());
+        }
+
+        if let Some(diff) = &self.diff {
+            attrs.push("style=filled".into());
+            attrs.push(format!("fillcolor={}", diff.fill()));
+        } else if !self.advisories.is_empty() {
+            attrs.push("style=filled".into());
+            attrs.push("fillcolor=salmon".into());
+        } else if self.yanked {
+            attrs.push("style=filled".into());
+            attrs.push("fillcolor=plum".into());
+        } else if self.latest.is_some() {
+            attrs.push("style=filled".into());
+            attrs.push("fillcolor=orange".into());
+        } else if let Some(fill) = dg
+            .cfg
+            .highlight_duplicates
+            .as_ref()
+            .filter(|_| self.duplicate)
+        {
+            attrs.push("style=filled".into());
+            attrs.push(format!("fillcolor={}", dot_id(fill)));
+        } else if let Some(fill) = dg.cfg.root_fill.as_ref().filter(|_| is_root) {
+            attrs.push("style=filled".into());
+            attrs.push(format!("fillcolor={}", dot_id(fill)));
+        } else if self.patched && dg.cfg.highlight_patched {
+            attrs.push("style=filled".into());
+            attrs.push("fillcolor=lightyellow".into());
+        } else if let Some(time) = self.build_time {
+            // From white for crates that compile instantly to red for the slowest one, as an HSV
+            // color with the saturation growing with the time.
+            let max = dg.max_build_time();
+            let share = if max > 0.0 { time / max } else { 0.0 };
+            attrs.push("style=filled".into());
+            attrs.push(format!("fillcolor=\"0.000 {:.3} 1.000\"", share));
+        } else if let Some(size) = self.size().filter(|_| dg.cfg.crate_sizes) {
+            // The same for the sizes, from white to blue.
+            let max = dg.max_crate_size();
+            let share = if max > 0 {
+                size as f64 / max as f64
+            } else {
+                0.0
+            };
+            attrs.push("style=filled".into());
+            attrs.push(format!("fillcolor=\"0.600 {:.3} 1.000\"", share));
+        } else if self.proc_macro && dg.cfg.highlight_proc_macros {
+            attrs.push("style=filled".into());
+            attrs.push("fillcolor=lightblue".into());
+        }
+
+        writeln!(w, " [{}];", attrs.join(", "))
+    }
+
+    /// The `rust-version` that the label shows: with `--msrv`, or with `--check-msrv` if it is
+    /// too new.
+    fn shown_rust_version<'a>(&'a self, dg: &DepGraph) -> Option<&'a str> {
+        if dg.cfg.msrv || self.msrv_violation {
+            self.rust_version.as_deref()
+        } else {
+            None
+        }
+    }
+
+    /// The version that the label shows, which lists every version of a merged node.
+    fn label_ver(&self) -> String {
+        if self.merged_vers.is_empty() {
+            self.ver.clone()
         } else {
-            ""
+            self.merged_vers.join(", ")
+        }
+    }
+
+    /// The size of the crate in bytes: unpacked if it is known, otherwise as downloaded.
+    pub fn size(&self) -> Option<u64> {
+        self.unpacked_size.or(self.download_size)
+    }
+
+    /// The ids of the security advisories of the crate.
+    pub fn advisory_ids(&self) -> Vec<&str> {
+        self.advisories.iter().map(|a| a.id.as_str()).collect()
+    }
+
+    /// The markers of the kind of the crate and of proc-macro crates, each followed by a space.
+    fn badges(&self) -> String {
+        let mut badges = String::new();
+        if let Some(badge) = self.kind().badge() {
+            badges.push_str(badge);
+            badges.push(' ');
+        }
+        if self.proc_macro {
+            badges.push_str(PROC_MACRO_BADGE);
+            badges.push(' ');
+        }
+        badges
+    }
+
+    /// The label of `--label-format`, with the placeholders replaced by the values of the crate.
+    /// The values are escaped, while escape sequences like `\\n` in the format are left to
+    /// graphviz.
+    fn formatted_label(&self, format: &str) -> String {
+        let escape = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"");
+        let source = match &self.source {
+            Some(source) => escape(source),
+            None => "path".into(),
         };
+        format
+            .replace('"', "\\\"")
+            .replace("{name}", &escape(&self.name))
+            .replace("{version}", &escape(&self.label_ver()))
+            .replace("{kind}", self.kind().name())
+            .replace("{source}", &source)
+            .replace("{license}", &escape(self.license.as_deref().unwrap_or("")))
+            .replace(
+                "{registry}",
+                &escape(self.registry.as_deref().unwrap_or("")),
+            )
+            .replace("{advisories}", &self.advisory_ids().join(", "))
+            .replace("{latest}", self.latest.as_deref().unwrap_or(""))
+            .replace(
+                "{msrv}",
+                &escape(self.rust_version.as_deref().unwrap_or("")),
+            )
+            .replace(
+                "{transitive}",
+                &self
+                    .transitive
+                    .map_or(String::new(), |count| count.to_string()),
+            )
+            .replace(
+                "{build_time}",
+                &self.build_time.map_or(String::new(), format_build_time),
+            )
+            .replace("{size}", &self.size().map_or(String::new(), format_size))
+    }
 
-        match self.kind() {
-            DepKind::Regular => writeln!(w, " [label=\"{}\"{}];", name, shape),
-            DepKind::Build => writeln!(w, " [label=\"{}\", color=purple];", name),
-            DepKind::Dev => writeln!(w, " [label=\"{}\", color=blue];", name),
-            DepKind::Optional => writeln!(w, " [label=\"{}\", color=red];", name),
-            _ => writeln!(w, " [label=\"{}\", color=orange];", name),
+    /// A graphviz HTML-like label with the name, version, kind, license, registry, source, MSRV,
+    /// newest version, advisories, number of collapsed dependencies and number of transitive
+    /// dependencies on separate rows. The version is always shown, as it does not make the node
+    /// wider.
+    fn html_label(&self, dg: &DepGraph, badges: &str) -> String {
+        let kind = self.kind();
+        let version = match &self.diff {
+            Some(DiffStatus::Changed(previous)) => format!(
+                "v{} \u{2192} v{}",
+                html_escape(previous),
+                html_escape(&self.ver)
+            ),
+            _ => format!("v{}", html_escape(&self.label_ver())),
+        };
+        let mut rows = vec![
+            format!("{}<b>{}</b>", badges, html_escape(&self.name)),
+            version,
+            format!(
+                "<font color=\"{}\">{} {}</font>",
+                html_escape(dg.cfg.color(kind)),
+                kind.icon(),
+                kind.name()
+            ),
+        ];
+        if self.proc_macro && dg.cfg.highlight_proc_macros {
+            rows.push("proc-macro".into());
+        }
+        if let Some(license) = &self.license {
+            rows.push(format!("<i>{}</i>", html_escape(license)));
+        }
+        if let Some(registry) = self.shown_registry(dg) {
+            rows.push(format!("[{}]", html_escape(registry)));
+        }
+        if dg.cfg.show_source {
+            rows.push(html_escape(&self.source_label(false)));
+        }
+        if let Some(rust_version) = self.shown_rust_version(dg) {
+            rows.push(format!("MSRV {}", html_escape(rust_version)));
         }
+        if self.yanked {
+            rows.push("yanked".into());
+        }
+        if let Some(latest) = &self.latest {
+            rows.push(format!("latest: {}", html_escape(latest)));
+        }
+        if !self.advisories.is_empty() {
+            let ids = self.advisory_ids().join("<br/>");
+            rows.push(format!("<font color=\"red\">{}</font>", ids));
+        }
+        if let Some(count) = self.collapsed {
+            rows.push(format!("+{} deps", count));
+        }
+        if let Some(count) = self.transitive.filter(|&count| count > 0) {
+            rows.push(format!("[+{}]", count));
+        }
+        if let Some(time) = self.build_time {
+            rows.push(format_build_time(time));
+        }
+        if let Some(size) = self.size().filter(|_| dg.cfg.crate_sizes) {
+            rows.push(format_size(size));
+        }
+
+        let rows: String = rows
+            .iter()
+            .map(|row| format!("<tr><td>{}</td></tr>", row))
+            .collect();
+        format!(
+            "<<table border=\"0\" cellborder=\"0\" cellspacing=\"0\">{}</table>>",
+            rows
+        )
+    }
+}
+
+/// A DOT attribute value. Names like `purple` and numerals like `1.5` are left as they are, other
+/// values like `#aa00ff`, `2d` or the keywords of DOT are quoted.
+pub fn dot_id(value: &str) -> String {
+    if is_dot_name(value) || is_dot_numeral(value) {
+        value.into()
+    } else {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+/// Whether a value is a DOT identifier that needs no quotes: letters, digits and underscores, not
+/// starting with a digit. The keywords are quoted, as DOT would read them as keywords otherwise.
+fn is_dot_name(value: &str) -> bool {
+    const KEYWORDS: [&str; 6] = ["node", "edge", "graph", "digraph", "subgraph", "strict"];
+    value
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(value))
+}
+
+/// Whether a value is a DOT numeral like `3`, `-1` or `.5`.
+fn is_dot_numeral(value: &str) -> bool {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    let (int, frac) = match digits.split_once('.') {
+        Some((int, frac)) => (int, Some(frac)),
+        None => (digits, None),
+    };
+    let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    all_digits(int)
+        && frac.is_none_or(all_digits)
+        && !(int.is_empty() && frac.unwrap_or("").is_empty())
+}
+
+fn is_crates_io_index(index: &str) -> bool {
+    index.ends_with("github.com/rust-lang/crates.io-index") || index.ends_with("index.crates.io")
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dot_id_quotes_what_dot_cannot_read_bare() {
+        assert_eq!(dot_id("purple"), "purple");
+        assert_eq!(dot_id("_private"), "_private");
+        assert_eq!(dot_id("1.5"), "1.5");
+        assert_eq!(dot_id("-2"), "-2");
+        assert_eq!(dot_id(".5"), ".5");
+        assert_eq!(dot_id(""), "\"\"");
+        assert_eq!(dot_id("2d"), "\"2d\"");
+        assert_eq!(dot_id("."), "\".\"");
+        assert_eq!(dot_id("1.2.3"), "\"1.2.3\"");
+        assert_eq!(dot_id("#aa00ff"), "\"#aa00ff\"");
+        assert_eq!(dot_id("Graph"), "\"Graph\"");
+        assert_eq!(dot_id("say \"hi\""), "\"say \\\"hi\\\"\"");
     }
 }
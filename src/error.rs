@@ -35,6 +35,12 @@ impl From<io::Error> for CliError {
     }
 }
 
+impl From<serde_json::Error> for CliError {
+    fn from(err: serde_json::Error) -> Self {
+        CliError::Generic(format!("Could not write JSON: {}", err))
+    }
+}
+
 impl From<toml::de::Error> for CliError {
     fn from(err: toml::de::Error) -> Self {
         CliError::Generic(format!("Could not parse input as TOML: {}", err))
@@ -0,0 +1,221 @@
+use crate::project;
+use std::collections::{HashMap, HashSet};
+use toml::Value;
+
+/// The features that are requested for a crate, on the command line or by a crate depending on it.
+#[derive(Clone, Debug, Default)]
+pub struct FeatureRequest {
+    pub features: Vec<String>,
+    pub default_features: bool,
+    pub all_features: bool,
+}
+
+impl FeatureRequest {
+    /// The features that a dependency declaration like `foo = { features = [...] }` requests, on
+    /// top of `features`.
+    pub fn declared(dep: &Value, features: &[String]) -> Self {
+        let mut request = FeatureRequest {
+            features: features.to_vec(),
+            default_features: true,
+            all_features: false,
+        };
+        if let Some(Value::Array(declared)) = dep.get("features") {
+            request
+                .features
+                .extend(declared.iter().filter_map(|f| f.as_str()).map(String::from));
+        }
+        for key in &["default-features", "default_features"] {
+            if let Some(&Value::Boolean(false)) = dep.get(key) {
+                request.default_features = false;
+            }
+        }
+        request
+    }
+}
+
+/// What the requested features of a crate enable.
+#[derive(Debug, Default)]
+pub struct EnabledFeatures {
+    /// The package names of the optional dependencies that are enabled.
+    pub optional_deps: HashSet<String>,
+    /// The features that are enabled on dependencies with `dep/feature`, by package name.
+    pub dep_features: HashMap<String, Vec<String>>,
+}
+
+/// Works out what the requested features enable, following the `[features]` table of the manifest.
+/// A feature can enable other features, optional dependencies (`dep:foo`, or the implicit `foo`
+/// feature) and features of dependencies (`foo/bar`, or `foo?/bar` without enabling `foo`).
+pub fn resolve(manifest_toml: &Value, request: &FeatureRequest) -> EnabledFeatures {
+    let table = manifest_toml.get("features").and_then(|f| f.as_table());
+
+    // Map the keys of optional dependencies, which features refer to, to their package names.
+    let mut dep_tables: Vec<&Value> = manifest_toml.get("dependencies").into_iter().collect();
+    if let Some(platforms) = manifest_toml.get("target").and_then(|t| t.as_table()) {
+        dep_tables.extend(platforms.values().filter_map(|p| p.get("dependencies")));
+    }
+    let mut optional = HashMap::new();
+    let mut packages = HashMap::new();
+    for (key, dep) in dep_tables.iter().filter_map(|t| t.as_table()).flatten() {
+        let package = project::package_name(key, dep);
+        if let Some(&Value::Boolean(true)) = dep.get("optional") {
+            optional.insert(key.as_str(), package.clone());
+        }
+        packages.insert(key.as_str(), package);
+    }
+
+    let mut enabled = EnabledFeatures::default();
+    if request.all_features {
+        enabled.optional_deps.extend(optional.values().cloned());
+    }
+
+    let mut queue = request.features.clone();
+    if request.all_features {
+        queue.extend(table.into_iter().flat_map(|t| t.keys().cloned()));
+    }
+    if request.default_features {
+        queue.push("default".into());
+    }
+
+    let mut visited = HashSet::new();
+    let mut weak = vec![];
+    while let Some(feature) = queue.pop() {
+        if let Some((dep, dep_feature)) = feature.split_once('/') {
+            match dep.strip_suffix('?') {
+                Some(dep) => weak.push((dep.to_string(), dep_feature.to_string())),
+                None => {
+                    if let Some(package) = optional.get(dep) {
+                        enabled.optional_deps.insert(package.clone());
+                    }
+                    if let Some(package) = packages.get(dep) {
+                        enabled
+                            .dep_features
+                            .entry(package.clone())
+                            .or_default()
+                            .push(dep_feature.into());
+                    }
+                }
+            }
+        } else if let Some(dep) = feature.strip_prefix("dep:") {
+            if let Some(package) = optional.get(dep) {
+                enabled.optional_deps.insert(package.clone());
+            }
+        } else if visited.insert(feature.clone()) {
+            match table.and_then(|t| t.get(&feature)) {
+                Some(Value::Array(implied)) => {
+                    queue.extend(implied.iter().filter_map(|f| f.as_str()).map(String::from))
+                }
+                _ => {
+                    if let Some(package) = optional.get(feature.as_str()) {
+                        enabled.optional_deps.insert(package.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    // Weak features only apply to dependencies that something else enabled.
+    for (dep, dep_feature) in weak {
+        if let Some(package) = packages.get(dep.as_str()) {
+            if !optional.contains_key(dep.as_str()) || enabled.optional_deps.contains(package) {
+                enabled
+                    .dep_features
+                    .entry(package.clone())
+                    .or_default()
+                    .push(dep_feature);
+            }
+        }
+    }
+
+    enabled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(features: &[&str], default_features: bool) -> FeatureRequest {
+        FeatureRequest {
+            features: features.iter().map(|&f| f.into()).collect(),
+            default_features,
+            all_features: false,
+        }
+    }
+
+    fn manifest(toml: &str) -> Value {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn default_features_and_dep_prefix() {
+        let manifest = manifest(
+            r#"
+            [dependencies]
+            log = { version = "0.4", optional = true }
+            serde = { version = "1", optional = true }
+
+            [features]
+            default = ["std"]
+            std = ["dep:log"]
+            "#,
+        );
+
+        let enabled = resolve(&manifest, &request(&[], true));
+        assert_eq!(enabled.optional_deps, HashSet::from(["log".to_string()]));
+
+        let enabled = resolve(&manifest, &request(&[], false));
+        assert!(enabled.optional_deps.is_empty());
+
+        // The implicit feature of an optional dependency enables it.
+        let enabled = resolve(&manifest, &request(&["serde"], false));
+        assert_eq!(enabled.optional_deps, HashSet::from(["serde".to_string()]));
+    }
+
+    #[test]
+    fn weak_dep_features_need_the_dep() {
+        let manifest = manifest(
+            r#"
+            [dependencies]
+            serde = { version = "1", optional = true }
+            log = "0.4"
+
+            [features]
+            derive = ["serde?/derive", "log?/std"]
+            "#,
+        );
+
+        let enabled = resolve(&manifest, &request(&["derive"], false));
+        assert!(enabled.optional_deps.is_empty());
+        assert!(!enabled.dep_features.contains_key("serde"));
+        // Dependencies that are not optional are always there.
+        assert_eq!(enabled.dep_features["log"], ["std"]);
+
+        let enabled = resolve(&manifest, &request(&["derive", "serde"], false));
+        assert_eq!(enabled.optional_deps, HashSet::from(["serde".to_string()]));
+        assert_eq!(enabled.dep_features["serde"], ["derive"]);
+    }
+
+    #[test]
+    fn renamed_optional_deps() {
+        let manifest = manifest(
+            r#"
+            [dependencies]
+            json = { package = "serde_json", version = "1", optional = true }
+
+            [target.'cfg(unix)'.dependencies]
+            sys = { package = "libc", version = "0.2", optional = true }
+
+            [features]
+            ordered = ["json/preserve_order"]
+            unix = ["dep:sys"]
+            "#,
+        );
+
+        let enabled = resolve(&manifest, &request(&["ordered", "unix"], false));
+        assert_eq!(
+            enabled.optional_deps,
+            HashSet::from(["serde_json".to_string(), "libc".to_string()])
+        );
+        assert_eq!(enabled.dep_features["serde_json"], ["preserve_order"]);
+        assert!(!enabled.dep_features.contains_key("json"));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::error::{CliError, CliResult};
+use crate::util;
+use serde_json::Value;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Fetches a crate from crates.io given as `NAME` or `NAME@VERSION`, where the version may be any
+/// version requirement. The crate is copied out of the local registry cache into `dir` and locked
+/// there, and the path to its manifest is returned.
+pub fn fetch_crate(spec: &str, dir: &Path, dev_deps: bool) -> CliResult<PathBuf> {
+    let (name, req) = match spec.find('@') {
+        Some(i) => (&spec[..i], &spec[i + 1..]),
+        None => (spec, "*"),
+    };
+
+    // Let cargo pick the version and download the crate by depending on it from a throwaway
+    // project. The empty [workspace] table keeps it out of any workspace `dir` may be in.
+    let wrapper = dir.join("wrapper");
+    fs::create_dir_all(wrapper.join("src"))?;
+    fs::write(wrapper.join("src").join("lib.rs"), "")?;
+    fs::write(
+        wrapper.join("Cargo.toml"),
+        format!(
+            "[package]\n\
+             name = \"cargo-deps-wrapper\"\n\
+             version = \"0.0.0\"\n\
+             \n\
+             [dependencies]\n\
+             {} = {:?}\n\
+             \n\
+             [workspace]\n",
+            name, req
+        ),
+    )?;
+
+    let metadata: Value = serde_json::from_slice(&util::run_cargo([
+        "metadata",
+        "--format-version",
+        "1",
+        "--manifest-path",
+        &wrapper.join("Cargo.toml").to_string_lossy(),
+    ])?)?;
+
+    // The version cargo picked is the only dependency of the wrapper.
+    let root = &metadata["resolve"]["root"];
+    let id = metadata["resolve"]["nodes"]
+        .as_array()
+        .and_then(|nodes| nodes.iter().find(|node| &node["id"] == root))
+        .map(|node| &node["deps"][0]["pkg"]);
+    let manifest = metadata["packages"]
+        .as_array()
+        .and_then(|packages| packages.iter().find(|pkg| Some(&pkg["id"]) == id))
+        .and_then(|pkg| pkg["manifest_path"].as_str())
+        .ok_or_else(|| CliError::Generic(format!("Could not find crate '{}'", spec)))?;
+    let src = Path::new(manifest).parent().unwrap();
+
+    // Lock the crate in a copy, so that the registry cache is left untouched.
+    let krate = dir.join(src.file_name().unwrap());
+    util::copy_dir(src, &krate)?;
+    let manifest_path = krate.join("Cargo.toml");
+
+    // Dev dependencies often include a published version of the crate itself, so leave them out of
+    // the lock file unless they were asked for.
+    if !dev_deps {
+        let mut manifest_toml = util::toml_from_file(&manifest_path)?;
+        if let Some(table) = manifest_toml.as_table_mut() {
+            table.remove("dev-dependencies");
+            if let Some(targets) = table.get_mut("target").and_then(|t| t.as_table_mut()) {
+                for (_, target) in targets.iter_mut() {
+                    if let Some(target) = target.as_table_mut() {
+                        target.remove("dev-dependencies");
+                    }
+                }
+            }
+        }
+        let manifest_toml = toml::to_string(&manifest_toml)
+            .map_err(|e| CliError::Generic(format!("Could not write TOML: {}", e)))?;
+        fs::write(&manifest_path, manifest_toml)?;
+    }
+
+    util::run_cargo([
+        "generate-lockfile",
+        "--manifest-path",
+        &manifest_path.to_string_lossy(),
+    ])?;
+
+    Ok(manifest_path)
+}
+
+/// Writes a file as of a revision of the git repository that contains it to `target`, e.g. the
+/// Cargo.lock of `HEAD~1`.
+pub fn git_show(rev: &str, path: &Path, target: &Path) -> CliResult<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| CliError::Generic(format!("{:?} is not a file", path)))?;
+
+    // A path starting with ./ is relative to the directory git runs in, not to the repository.
+    let object = format!("{}:./{}", rev, file_name.to_string_lossy());
+    let contents = util::run(OsStr::new("git"), Some(dir), ["show", &object])?;
+    fs::write(target, contents)?;
+
+    Ok(())
+}
+
+/// Fetches a git repository into `dir`. Only the requested branch, tag or commit (or the default
+/// branch) is fetched, without its history.
+pub fn fetch_git(url: &str, reference: Option<&str>, dir: &Path) -> CliResult<()> {
+    let git = |args: &[&str]| util::run(OsStr::new("git"), Some(dir), args);
+
+    git(&["init", "--quiet"])?;
+    git(&["remote", "add", "origin", url])?;
+    let reference = reference.unwrap_or("HEAD");
+    if git(&["fetch", "--quiet", "--depth", "1", "origin", reference]).is_err() {
+        // Not every server allows fetching a single commit, so fall back to fetching everything.
+        git(&["fetch", "--quiet", "origin"])?;
+        git(&["checkout", "--quiet", reference])?;
+    } else {
+        git(&["checkout", "--quiet", "FETCH_HEAD"])?;
+    }
+
+    Ok(())
+}
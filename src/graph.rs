@@ -1,39 +1,59 @@
-use crate::config::Config;
-use crate::dep::{DepKind, ResolvedDep};
+use crate::config::{ClusterBy, Config, SizeBy};
+use crate::dep::{self, DepKind, DiffStatus, ResolvedDep, SourceKind};
 use crate::error::{CliError, CliResult};
 use crate::project::RootDepsMap;
-use std::collections::HashMap;
+use crate::registry;
+use crate::util::{self, Pattern};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::io::Write;
 
 pub type Node = usize;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
 pub struct Edge(pub Node, pub Node);
 
 impl Edge {
-    pub fn label<W: Write>(&self, w: &mut W, dg: &DepGraph) -> CliResult<()> {
+    /// The kind of the relationship between parent and child, taking the kinds of both into
+    /// account.
+    pub fn kind(&self, dg: &DepGraph) -> CliResult<DepKind> {
+        Ok(self.kinds(dg)?[0])
+    }
+
+    /// All kinds of the relationship between parent and child, for a child that is declared as
+    /// several kinds of dependency, like both a regular and a dev dependency. The first one is
+    /// the one that `kind` returns.
+    pub fn kinds(&self, dg: &DepGraph) -> CliResult<Vec<DepKind>> {
         use crate::dep::DepKind::{Build, Dev, Optional, Regular, Unknown};
 
-        let parent = dg.get(self.0).unwrap();
-        let child = dg.get(self.1).unwrap();
+        // The kinds are those of the dependency itself, whichever way the edge is drawn.
+        let ed = self.declared(dg);
+        let (parent, child) = (dg.get(ed.0).unwrap(), dg.get(ed.1).unwrap());
 
         // Special case: always color edge from root to root dep by its actual root dependency kind.
         // Otherwise, the root dep could also be a dep of a regular dep which will cause the root ->
         // root dep edge to appear regular, which is misleading as it is not regular in Cargo.toml.
-        let child_kind = if let Some(dep_kinds_map) = &dg.root_deps_map.get(&parent.name) {
-            if let Some(kinds) = dep_kinds_map.get(&child.name) {
-                if kinds.contains(&Regular) {
-                    Regular
-                } else if kinds.contains(&Build) {
-                    Build
-                } else if kinds.contains(&Dev) {
-                    Dev
-                } else if kinds.contains(&Optional) {
-                    Optional
-                } else {
-                    Unknown
+        // The same goes for the dependencies of path dependencies, which have a manifest too.
+        // Registry crates have declared kinds per edge, as there may be several versions of them.
+        let dep_kinds_map = dg
+            .root_deps_map
+            .get(&parent.name)
+            .or_else(|| dg.path_deps_map.get(&parent.name));
+        let declared = match dep_kinds_map {
+            Some(dep_kinds_map) => Some(dep_kinds_map.get(&child.name)),
+            None => dg.edge_kinds.get(&ed).map(Some),
+        };
+        let child_kinds = if let Some(kinds) = declared {
+            if let Some(kinds) = kinds {
+                let mut child_kinds: Vec<DepKind> = [Regular, Build, Dev, Optional]
+                    .iter()
+                    .copied()
+                    .filter(|kind| kinds.contains(kind))
+                    .collect();
+                if child_kinds.is_empty() {
+                    child_kinds.push(Unknown);
                 }
+                child_kinds
             } else {
                 return Err(CliError::Generic(format!(
                     "Crate '{}' is not a dependency of a root crate. \
@@ -42,15 +62,127 @@ impl Edge {
                 )));
             }
         } else {
-            child.kind()
+            vec![child.kind()]
         };
 
-        match (parent.kind(), child_kind) {
-            (Regular, Regular) => writeln!(w, ";")?,
-            (Build, _) | (Regular, Build) => writeln!(w, " [color=purple, style=dashed];")?,
-            (Dev, _) | (Regular, Dev) => writeln!(w, " [color=blue, style=dashed];")?,
-            (Optional, _) | (Regular, Optional) => writeln!(w, " [color=red, style=dashed];")?,
-            _ => writeln!(w, " [color=orange, style=dashed];")?,
+        let mut kinds = vec![];
+        for child_kind in child_kinds {
+            let kind = match (parent.kind(), child_kind) {
+                (Regular, Regular) => Regular,
+                (Build, _) | (Regular, Build) => Build,
+                (Dev, _) | (Regular, Dev) => Dev,
+                (Optional, _) | (Regular, Optional) => Optional,
+                _ => Unknown,
+            };
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        }
+        Ok(kinds)
+    }
+
+    /// The edge as the dependency is declared, from the crate that depends on the other one.
+    pub(crate) fn declared(&self, dg: &DepGraph) -> Edge {
+        if dg.inverted {
+            Edge(self.1, self.0)
+        } else {
+            *self
+        }
+    }
+
+    /// The version requirements that the dependency is declared with, as far as they are known.
+    pub fn reqs<'a>(&self, dg: &'a DepGraph) -> &'a [String] {
+        dg.edge_reqs
+            .get(&self.declared(dg))
+            .map_or(&[], |reqs| reqs.as_slice())
+    }
+
+    /// The first requirement of the dependency that is too loose, like `*`.
+    pub fn loose_req<'a>(&self, dg: &'a DepGraph) -> Option<&'a str> {
+        self.reqs(dg)
+            .iter()
+            .find(|req| util::is_loose_req(req))
+            .map(|req| req.as_str())
+    }
+
+    /// The color of the edge with `--highlight-edges`, if it is incident to a highlighted crate.
+    /// The color of the dependency wins over the one of the crate depending on it.
+    fn highlight_color<'a>(&self, dg: &'a DepGraph) -> Option<&'a str> {
+        if !dg.cfg.highlight_edges {
+            return None;
+        }
+        let declared = self.declared(dg);
+        dg.cfg
+            .highlight_color(&dg.nodes[declared.1].name)
+            .or_else(|| dg.cfg.highlight_color(&dg.nodes[declared.0].name))
+    }
+
+    /// Whether the edge is part of the longest chain of dependencies of `--critical-path`.
+    fn on_critical_path(&self, dg: &DepGraph) -> bool {
+        let declared = self.declared(dg);
+        match (
+            dg.nodes[declared.0].critical_path,
+            dg.nodes[declared.1].critical_path,
+        ) {
+            (Some(parent), Some(child)) => child == parent + 1,
+            _ => false,
+        }
+    }
+
+    /// Writes the attributes of the edge drawn for one of its kinds. The version requirements are
+    /// only shown and flagged on the edge of its first kind. A back edge, which closes a dependency
+    /// cycle, is drawn in bold red and does not affect the layout.
+    pub fn label<W: Write>(
+        &self,
+        w: &mut W,
+        dg: &DepGraph,
+        kind: DepKind,
+        back_edge: bool,
+    ) -> CliResult<()> {
+        let mut attrs = vec![];
+        let highlight = self.highlight_color(dg);
+        if back_edge {
+            attrs.push("color=red".into());
+            attrs.push("style=bold".into());
+            attrs.push("constraint=false".into());
+        } else {
+            if let Some(color) = highlight {
+                attrs.push(format!("color={}", dep::dot_id(color)));
+            } else if kind != DepKind::Regular {
+                attrs.push(format!("color={}", dep::dot_id(dg.cfg.color(kind))));
+            }
+            if let Some(style) = dg.cfg.edge_style(kind) {
+                attrs.push(format!("style={}", dep::dot_id(style)));
+            }
+        }
+        if kind == self.kind(dg)? {
+            let loose_req = self.loose_req(dg).filter(|_| dg.cfg.flag_loose_reqs);
+            let reqs = self.reqs(dg);
+            let label = if dg.cfg.edge_version_reqs && !reqs.is_empty() {
+                Some(reqs.join(", "))
+            } else {
+                loose_req.map(String::from)
+            };
+            if let Some(label) = label {
+                attrs.push(format!("label={}", dep::dot_id(&label)));
+            }
+            if loose_req.is_some() {
+                attrs.push("penwidth=3".into());
+                attrs.push("fontcolor=red".into());
+            } else if highlight.is_some() {
+                attrs.push("penwidth=2".into());
+            }
+        } else if highlight.is_some() {
+            attrs.push("penwidth=2".into());
+        }
+        if self.on_critical_path(dg) && !attrs.iter().any(|a| a.starts_with("penwidth=")) {
+            attrs.push("penwidth=3".into());
+        }
+
+        if attrs.is_empty() {
+            writeln!(w, ";")?;
+        } else {
+            writeln!(w, " [{}];", attrs.join(", "))?;
         }
 
         Ok(())
@@ -60,10 +192,25 @@ impl Edge {
 impl fmt::Display for Edge {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let &Edge(il, ir) = self;
-        write!(f, "n{} -> n{}", il, ir)
+        write!(f, "{} -> {}", DepGraph::node_id(il), DepGraph::node_id(ir))
     }
 }
 
+/// Metrics of the shown part of the graph, for `--stats`.
+#[derive(Debug)]
+pub struct Stats {
+    pub crates: usize,
+    pub edges: usize,
+    /// The longest distance of a crate from a root crate.
+    pub depth: usize,
+    /// The crates with several versions, with their versions.
+    pub duplicates: Vec<(String, Vec<String>)>,
+    /// The crates that pull in the most crates, with the number of crates they pull in.
+    pub largest_subtrees: Vec<(Node, usize)>,
+    /// The crates that the most crates depend on directly, with the number of those crates.
+    pub most_depended_upon: Vec<(Node, usize)>,
+}
+
 #[derive(Debug)]
 pub struct DepGraph {
     /// Vector of nodes containing resolved dependency information as well as the indices of parent
@@ -71,6 +218,17 @@ pub struct DepGraph {
     pub nodes: Vec<ResolvedDep>,
     pub edges: Vec<Edge>,
     pub root_deps_map: RootDepsMap,
+    /// The declared dependencies of path dependencies that are not root crates.
+    pub path_deps_map: RootDepsMap,
+    /// The declared kinds of the edges out of registry crates, as far as they are known.
+    pub edge_kinds: HashMap<Edge, Vec<DepKind>>,
+    /// The version requirements that the edges are declared with, as far as they are known.
+    pub edge_reqs: HashMap<Edge, Vec<String>>,
+    /// Whether the edges point from dependencies to the crates that depend on them.
+    pub inverted: bool,
+    /// Whether the graph was cut down to the subtrees of some crates, which then take the place of
+    /// the root crates.
+    pub subtree: bool,
     pub cfg: Config,
 }
 
@@ -80,18 +238,39 @@ impl DepGraph {
             nodes: vec![],
             edges: vec![],
             root_deps_map: HashMap::new(),
+            path_deps_map: HashMap::new(),
+            edge_kinds: HashMap::new(),
+            edge_reqs: HashMap::new(),
+            inverted: false,
+            subtree: false,
             cfg,
         }
     }
 
-    /// Performs a topological sort on the edges.
+    /// Performs a topological sort on the edges. The edges that close a dependency cycle, like a
+    /// dev-dependency on a crate that depends on the crate itself, cannot be sorted. They are
+    /// reported and put after all the others.
     pub fn topological_sort(&mut self) -> CliResult<()> {
+        let back_edges = self.back_edges();
+        if !back_edges.is_empty() {
+            self.report_cycles(&back_edges);
+        }
+
         let mut graph_nodes = self.nodes.clone();
         let mut l: Vec<Node> = vec![]; // Will contain indices of the nodes in sorted order.
         let mut s: Vec<Node> = vec![]; // Set of nodes with no incoming edges.
 
+        // Leave out the back edges, which leaves a graph without cycles.
+        self.edges.retain(|ed| !back_edges.contains(ed));
+        for (id, node) in graph_nodes.iter_mut().enumerate() {
+            node.children
+                .retain(|&child| !back_edges.contains(&Edge(id, child)));
+            node.parents
+                .retain(|&parent| !back_edges.contains(&Edge(parent, id)));
+        }
+
         // Populate initial list of start nodes which have no incoming edges.
-        for (i, node) in self.nodes.iter().enumerate() {
+        for (i, node) in graph_nodes.iter().enumerate() {
             if node.parents.is_empty() {
                 s.push(i);
             }
@@ -101,8 +280,6 @@ impl DepGraph {
             l.push(n);
 
             while let Some(child) = graph_nodes[n].children.pop() {
-                assert_ne!(n, child);
-
                 // Remove the edge from n -> child.
                 let e_index = self
                     .edges
@@ -128,9 +305,14 @@ impl DepGraph {
             // Add back the edges, this time in topological order.
             for n in l.iter() {
                 for child in self.nodes[*n].children.iter() {
-                    self.edges.push(Edge(*n, *child));
+                    if !back_edges.contains(&Edge(*n, *child)) {
+                        self.edges.push(Edge(*n, *child));
+                    }
                 }
             }
+            let mut back_edges: Vec<Edge> = back_edges.into_iter().collect();
+            back_edges.sort_unstable();
+            self.edges.extend(back_edges);
 
             Ok(())
         } else {
@@ -140,6 +322,97 @@ impl DepGraph {
         }
     }
 
+    /// The edges that close a dependency cycle, found by a depth-first search from the crates
+    /// that nothing depends on, then the root crates, then any crate that is still left: an edge
+    /// back to a crate whose dependencies are still being searched. Without them, the graph has no
+    /// cycles.
+    pub fn back_edges(&self) -> HashSet<Edge> {
+        let mut starts: Vec<Node> = (0..self.nodes.len())
+            .filter(|&id| self.nodes[id].parents.is_empty())
+            .collect();
+        starts.extend(
+            (0..self.nodes.len())
+                .filter(|&id| self.root_deps_map.contains_key(&self.nodes[id].name)),
+        );
+        starts.extend(0..self.nodes.len());
+
+        let mut back_edges = HashSet::new();
+        // Whether the search has reached each node, and whether it is done with it.
+        let mut reached = vec![false; self.nodes.len()];
+        let mut done = vec![false; self.nodes.len()];
+        for start in starts {
+            if reached[start] {
+                continue;
+            }
+            reached[start] = true;
+            // The nodes being searched, with the number of their children searched so far.
+            let mut stack = vec![(start, 0)];
+            while let Some((id, searched)) = stack.last_mut() {
+                let id = *id;
+                match self.nodes[id].children.get(*searched) {
+                    Some(&child) => {
+                        *searched += 1;
+                        if !reached[child] {
+                            reached[child] = true;
+                            stack.push((child, 0));
+                        } else if !done[child] {
+                            back_edges.insert(Edge(id, child));
+                        }
+                    }
+                    None => {
+                        done[id] = true;
+                        stack.pop();
+                    }
+                }
+            }
+        }
+
+        back_edges
+    }
+
+    /// Prints each dependency cycle that one of the back edges closes, from the crate that the back
+    /// edge points to around to itself.
+    fn report_cycles(&self, back_edges: &HashSet<Edge>) {
+        let mut back_edges: Vec<&Edge> = back_edges.iter().collect();
+        back_edges.sort_unstable();
+
+        eprintln!("Found {} dependency cycles:", back_edges.len());
+        for &Edge(from, to) in back_edges {
+            // The shortest path from the end of the back edge to its start.
+            let mut previous: Vec<Option<Node>> = vec![None; self.nodes.len()];
+            let mut queue = VecDeque::from(vec![to]);
+            while let Some(id) = queue.pop_front() {
+                if id == from {
+                    break;
+                }
+                for &child in &self.nodes[id].children {
+                    if child != to && previous[child].is_none() {
+                        previous[child] = Some(id);
+                        queue.push_back(child);
+                    }
+                }
+            }
+
+            let mut path = vec![to];
+            let mut id = from;
+            while id != to {
+                path.push(id);
+                id = match previous[id] {
+                    Some(id) => id,
+                    None => break,
+                };
+            }
+            path[1..].reverse();
+            path.push(to);
+
+            let names: Vec<String> = path
+                .iter()
+                .map(|&id| format!("{} v{}", self.nodes[id].name, self.nodes[id].ver))
+                .collect();
+            eprintln!("  {}", names.join(" -> "));
+        }
+    }
+
     /// Sets the kind of each dependency based on how the dependencies are declared in the manifest.
     pub fn set_resolved_kind(&mut self) -> CliResult<()> {
         // Set regular kind for all root nodes.
@@ -150,6 +423,7 @@ impl DepGraph {
         }
 
         // Iterate over edges in topologically-sorted order to propogate the kinds.
+        let edge_kinds = &self.edge_kinds;
         for ed in self.edges.iter() {
             let (parent_name, parent_regular, parent_build, parent_dev, parent_optional) = {
                 let parent = &self.nodes[ed.0];
@@ -161,7 +435,7 @@ impl DepGraph {
                     parent.is_optional,
                 )
             };
-            let mut child = &mut self.nodes[ed.1];
+            let child = &mut self.nodes[ed.1];
 
             if let Some(dep_kinds_map) = self.root_deps_map.get(&parent_name) {
                 // If this is an edge from the root node,
@@ -183,6 +457,35 @@ impl DepGraph {
                         child.name
                     )));
                 }
+            } else if let Some(kinds) = self
+                .path_deps_map
+                .get(&parent_name)
+                .and_then(|dep_kinds_map| dep_kinds_map.get(&child.name))
+                .or_else(|| edge_kinds.get(ed))
+            {
+                // If this is an edge from a path dependency or a registry crate, its manifest says
+                // how the child is declared. A regular dependency gets the kinds of the parent, any
+                // other kind replaces the regular kind of the parent.
+                for kind in kinds {
+                    let kind_flag = match *kind {
+                        DepKind::Build => &mut child.is_build,
+                        DepKind::Dev => &mut child.is_dev,
+                        DepKind::Optional => &mut child.is_optional,
+                        _ => &mut child.is_regular,
+                    };
+                    if parent_regular {
+                        *kind_flag = true;
+                    }
+                }
+                if parent_build {
+                    child.is_build = true;
+                }
+                if parent_dev {
+                    child.is_dev = true;
+                }
+                if parent_optional {
+                    child.is_optional = true;
+                }
             } else {
                 // If this is an edge from a dependency node, propagate the kind. This is a set
                 // of flags because a dependency can appear several times in the graph, and the
@@ -208,6 +511,22 @@ impl DepGraph {
         Ok(())
     }
 
+    /// Marks the crates that are in the graph in several versions. Orphans only count if they are
+    /// shown.
+    pub fn mark_duplicates(&mut self) {
+        let cfg = &self.cfg;
+        let counted =
+            |dep: &ResolvedDep| dep.kind() != DepKind::Unknown || cfg.keeps_orphan(&dep.name);
+        let mut versions: HashMap<String, usize> = HashMap::new();
+        for dep in self.nodes.iter().filter(|dep| counted(dep)) {
+            *versions.entry(dep.name.clone()).or_insert(0) += 1;
+        }
+
+        for dep in &mut self.nodes {
+            dep.duplicate = counted(dep) && versions.get(&dep.name).is_some_and(|&count| count > 1);
+        }
+    }
+
     /// Forces the version to be displayed on dependencies that have the same name (but a different
     /// version) as another dependency.
     pub fn show_version_on_duplicates(&mut self) {
@@ -266,96 +585,1784 @@ impl DepGraph {
         self.nodes[child].parents.push(parent);
     }
 
-    pub fn get(&self, id: usize) -> Option<&ResolvedDep> {
-        if id < self.nodes.len() {
-            return Some(&self.nodes[id]);
+    /// Whether the node is a root crate.
+    pub fn is_root(&self, id: Node) -> bool {
+        self.root_deps_map.contains_key(&self.nodes[id].name)
+    }
+
+    /// Renumbers the nodes in order of name and version, and sorts the children and parents of each
+    /// node the same way, so that the output does not depend on the order in which the crates were
+    /// found and can be diffed between updates of the dependencies.
+    pub fn sort_nodes(&mut self) {
+        let mut order: Vec<Node> = (0..self.nodes.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (a, b) = (&self.nodes[a], &self.nodes[b]);
+            a.name
+                .cmp(&b.name)
+                .then_with(|| util::cmp_versions(&a.ver, &b.ver))
+        });
+        let mut new_ids = vec![0; self.nodes.len()];
+        for (new_id, &id) in order.iter().enumerate() {
+            new_ids[id] = new_id;
         }
-        None
+
+        let remap = |ids: &[Node]| -> Vec<Node> {
+            let mut ids: Vec<Node> = ids.iter().map(|&id| new_ids[id]).collect();
+            ids.sort_unstable();
+            ids
+        };
+        let mut nodes: Vec<Option<ResolvedDep>> = self.nodes.drain(..).map(Some).collect();
+        self.nodes = order
+            .iter()
+            .map(|&id| {
+                let mut dep = nodes[id].take().unwrap();
+                dep.children = remap(&dep.children);
+                dep.parents = remap(&dep.parents);
+                dep
+            })
+            .collect();
+        let mut edges: Vec<Edge> = self
+            .edges
+            .iter()
+            .map(|ed| Edge(new_ids[ed.0], new_ids[ed.1]))
+            .collect();
+        edges.sort_unstable();
+        self.edges = edges;
+        self.edge_kinds = self
+            .edge_kinds
+            .drain()
+            .map(|(ed, kinds)| (Edge(new_ids[ed.0], new_ids[ed.1]), kinds))
+            .collect();
+        self.edge_reqs = self
+            .edge_reqs
+            .drain()
+            .map(|(ed, reqs)| (Edge(new_ids[ed.0], new_ids[ed.1]), reqs))
+            .collect();
     }
 
-    pub fn find(&self, name: &str, ver: &str) -> Option<usize> {
-        for (i, d) in self.nodes.iter().enumerate() {
-            if d.name == name && d.ver == ver {
-                return Some(i);
+    /// Keeps only the nodes for which `keep` returns true, and the edges between them. Nodes are
+    /// renumbered, but stay in the same order, as do the edges.
+    pub fn retain<F: Fn(Node) -> bool>(&mut self, keep: F) {
+        let mut new_ids = vec![None; self.nodes.len()];
+        let mut next_id = 0;
+        for (id, new_id) in new_ids.iter_mut().enumerate() {
+            if keep(id) {
+                *new_id = Some(next_id);
+                next_id += 1;
             }
         }
-        None
+
+        let remap =
+            |ids: &[Node]| -> Vec<Node> { ids.iter().filter_map(|&id| new_ids[id]).collect() };
+        let nodes = self
+            .nodes
+            .drain(..)
+            .enumerate()
+            .filter(|&(id, _)| new_ids[id].is_some())
+            .map(|(_, mut dep)| {
+                dep.children = remap(&dep.children);
+                dep.parents = remap(&dep.parents);
+                dep
+            })
+            .collect();
+        self.nodes = nodes;
+        self.edges = self
+            .edges
+            .iter()
+            .filter_map(|ed| Some(Edge(new_ids[ed.0]?, new_ids[ed.1]?)))
+            .collect();
+        self.edge_kinds = self
+            .edge_kinds
+            .drain()
+            .filter_map(|(ed, kinds)| Some((Edge(new_ids[ed.0]?, new_ids[ed.1]?), kinds)))
+            .collect();
+        self.edge_reqs = self
+            .edge_reqs
+            .drain()
+            .filter_map(|(ed, reqs)| Some((Edge(new_ids[ed.0]?, new_ids[ed.1]?), reqs)))
+            .collect();
     }
 
-    pub fn find_or_add(&mut self, name: &str, ver: &str) -> usize {
-        if let Some(i) = self.find(name, ver) {
-            return i;
+    /// Which nodes can be reached from a root crate without going through a node for which `skip`
+    /// returns true.
+    pub fn reachable_from_roots<F: Fn(Node) -> bool>(&self, skip: F) -> Vec<bool> {
+        let mut reachable = vec![false; self.nodes.len()];
+        let mut stack: Vec<Node> = (0..self.nodes.len())
+            .filter(|&id| self.is_root(id))
+            .collect();
+        while let Some(id) = stack.pop() {
+            if !reachable[id] && !skip(id) {
+                reachable[id] = true;
+                stack.extend(&self.nodes[id].children);
+            }
         }
-        self.nodes
-            .push(ResolvedDep::new(name.to_owned(), ver.to_owned()));
-        self.nodes.len() - 1
+        reachable
     }
 
-    pub fn render_to<W: Write>(self, output: &mut W) -> CliResult<()> {
-        // Keep track of added nodes.
-        let mut nodes_added = vec![];
+    /// The direct dependencies of the root crates with the number of shown crates that would leave
+    /// the graph if the root crates did not depend on them, most first: the dependency itself and
+    /// the crates that only it pulls in. A dependency that other crates depend on too only counts
+    /// the crates that nothing else pulls in.
+    pub fn heaviest_deps(&self) -> Vec<(Node, usize)> {
+        // Which nodes can be reached from a root crate without the edges from the root crates to
+        // `cut`.
+        let reachable = |cut: Option<Node>| {
+            let mut reachable = vec![false; self.nodes.len()];
+            let mut stack: Vec<Node> = (0..self.nodes.len())
+                .filter(|&id| self.is_root(id))
+                .collect();
+            while let Some(id) = stack.pop() {
+                if !reachable[id] {
+                    reachable[id] = true;
+                    let is_root = self.is_root(id);
+                    stack.extend(
+                        self.nodes[id]
+                            .children
+                            .iter()
+                            .copied()
+                            .filter(|&child| !(is_root && Some(child) == cut)),
+                    );
+                }
+            }
+            reachable
+        };
 
-        writeln!(output, "digraph dependencies {{")?;
+        let reachable_before = reachable(None);
+        let direct_deps: BTreeSet<Node> = (0..self.nodes.len())
+            .filter(|&id| self.is_root(id))
+            .flat_map(|id| self.nodes[id].children.iter().copied())
+            .filter(|&id| !self.is_root(id) && self.is_shown(id))
+            .collect();
+        let mut heaviest: Vec<(Node, usize)> = direct_deps
+            .into_iter()
+            .map(|dep| {
+                let reachable_after = reachable(Some(dep));
+                let count = (0..self.nodes.len())
+                    .filter(|&id| reachable_before[id] && !reachable_after[id] && self.is_shown(id))
+                    .count();
+                (dep, count)
+            })
+            .collect();
+        heaviest.sort_by_key(|&(id, count)| (std::cmp::Reverse(count), id));
 
-        // Output all non-subgraph nodes.
-        for (i, dep) in self.nodes.iter().enumerate() {
-            // Skip subgraph nodes, will be declared in the subgraph.
-            if let Some(sub_deps) = &self.cfg.subgraph {
-                if sub_deps.contains(&dep.name) {
-                    continue;
+        heaviest
+    }
+
+    /// Removes the crates matching the patterns, along with the crates that can then no longer be
+    /// reached from a root crate. Crates that could not be reached before, like orphans, are left
+    /// alone.
+    pub fn exclude(&mut self, patterns: &[Pattern]) {
+        let excluded: Vec<bool> = self
+            .nodes
+            .iter()
+            .map(|dep| util::matches_any(patterns, &dep.name))
+            .collect();
+        let reachable_before = self.reachable_from_roots(|_| false);
+        let reachable = self.reachable_from_roots(|id| excluded[id]);
+
+        self.retain(|id| !excluded[id] && (reachable[id] || !reachable_before[id]));
+    }
+
+    /// Keeps only the crates matching the patterns, the crates that depend on them and the crates
+    /// they depend on, directly or not.
+    pub fn focus(&mut self, patterns: &[Pattern]) -> CliResult<()> {
+        let focused = self.matching(patterns, "--focus")?;
+        let ancestors = self.ancestors(&focused);
+        let descendants = self.descendants(&focused);
+
+        self.retain(|id| ancestors[id] || descendants[id]);
+        Ok(())
+    }
+
+    /// Keeps only the crates on the dependency paths that lead from the root crates, or from the
+    /// crates matching `from`, to the crates matching `targets`.
+    pub fn why(&mut self, targets: &[Pattern], from: Option<&[Pattern]>) -> CliResult<()> {
+        let targets = self.matching(targets, "--why")?;
+        let sources = match from {
+            Some(from) => self.matching(from, "--from")?,
+            None => self.start_nodes(),
+        };
+        let ancestors = self.ancestors(&targets);
+        let descendants = self.descendants(&sources);
+
+        if !(0..self.nodes.len()).any(|id| ancestors[id] && descendants[id]) {
+            return Err(CliError::Generic(
+                "No dependency path leads to the --why crates".into(),
+            ));
+        }
+
+        self.retain(|id| ancestors[id] && descendants[id]);
+        Ok(())
+    }
+
+    /// Keeps only the crates that are in the graph in several versions, and the crates on the paths
+    /// that pull in each version.
+    pub fn duplicates(&mut self) -> CliResult<()> {
+        // Orphans are not part of the build, unless they are shown.
+        let counted =
+            |dep: &ResolvedDep| dep.kind() != DepKind::Unknown || self.cfg.keeps_orphan(&dep.name);
+        let mut versions: HashMap<&str, usize> = HashMap::new();
+        for dep in self.nodes.iter().filter(|dep| counted(dep)) {
+            *versions.entry(&dep.name).or_insert(0) += 1;
+        }
+        let duplicated: Vec<Node> = (0..self.nodes.len())
+            .filter(|&id| counted(&self.nodes[id]) && versions[self.nodes[id].name.as_str()] > 1)
+            .collect();
+        if duplicated.is_empty() {
+            return Err(CliError::Generic(
+                "No crate is in the graph in several versions".into(),
+            ));
+        }
+
+        let ancestors = self.ancestors(&duplicated);
+        self.retain(|id| ancestors[id]);
+        Ok(())
+    }
+
+    /// Makes the crates matching the patterns the root crates, keeping only what they depend on.
+    /// The old root crates become local crates like path dependencies. This must be done before
+    /// the kinds are set, so that they are worked out from the new root crates.
+    pub fn reroot(&mut self, patterns: &[Pattern]) -> CliResult<()> {
+        let roots = self.matching(patterns, "--root")?;
+        let descendants = self.descendants(&roots);
+
+        let old_roots = std::mem::take(&mut self.root_deps_map);
+        self.path_deps_map.extend(old_roots);
+        for &id in &roots {
+            let name = self.nodes[id].name.clone();
+            // Without a manifest, the kinds come from the edges, otherwise they are regular.
+            let dep_kinds_map = match self.path_deps_map.remove(&name) {
+                Some(dep_kinds_map) => dep_kinds_map,
+                None => self.nodes[id]
+                    .children
+                    .iter()
+                    .map(|&child| {
+                        let kinds = self.edge_kinds.get(&Edge(id, child)).cloned();
+                        (
+                            self.nodes[child].name.clone(),
+                            kinds.unwrap_or_else(|| vec![DepKind::Regular]),
+                        )
+                    })
+                    .collect(),
+            };
+            self.root_deps_map.entry(name).or_insert(dep_kinds_map);
+        }
+
+        self.retain(|id| descendants[id]);
+        Ok(())
+    }
+
+    /// Keeps only the crates matching the patterns and the crates they depend on, directly or not,
+    /// leaving out the rest of the project. Unlike with `reroot`, the kinds stay the ones worked
+    /// out from the root crates.
+    pub fn subtree(&mut self, patterns: &[Pattern]) -> CliResult<()> {
+        let subtrees = self.matching(patterns, "--subtree")?;
+        let descendants = self.descendants(&subtrees);
+
+        self.retain(|id| descendants[id]);
+        self.subtree = true;
+        Ok(())
+    }
+
+    /// Keeps only the crates that come from one of the given kinds of sources, and the crates on
+    /// the paths that pull them in. The root crates are always kept, so that the graph shows that
+    /// there are none.
+    pub fn only_sources(&mut self, kinds: &[SourceKind]) {
+        let matching: Vec<Node> = (0..self.nodes.len())
+            .filter(|&id| kinds.contains(&self.nodes[id].source_kind()) || self.is_root(id))
+            .collect();
+        let ancestors = self.ancestors(&matching);
+
+        self.retain(|id| ancestors[id]);
+    }
+
+    /// Keeps only the proc-macro crates, and the crates on the paths that pull them in.
+    pub fn only_proc_macros(&mut self) -> CliResult<()> {
+        let proc_macros: Vec<Node> = (0..self.nodes.len())
+            .filter(|&id| self.nodes[id].proc_macro && self.is_shown(id))
+            .collect();
+        if proc_macros.is_empty() {
+            return Err(CliError::Generic(
+                "No proc-macro crate is in the graph".into(),
+            ));
+        }
+
+        let ancestors = self.ancestors(&proc_macros);
+        self.retain(|id| ancestors[id]);
+        Ok(())
+    }
+
+    /// Keeps only the crates matching the patterns and the crates that depend on them, directly or
+    /// not, and flips the edges so that the graph starts at the matching crates.
+    pub fn invert(&mut self, patterns: &[Pattern]) -> CliResult<()> {
+        let inverted = self.matching(patterns, "--invert")?;
+        let ancestors = self.ancestors(&inverted);
+        self.retain(|id| ancestors[id]);
+
+        for dep in &mut self.nodes {
+            std::mem::swap(&mut dep.parents, &mut dep.children);
+        }
+        // Reversing the edges keeps them in topological order.
+        self.edges.reverse();
+        for ed in &mut self.edges {
+            *ed = Edge(ed.1, ed.0);
+        }
+        self.inverted = true;
+
+        Ok(())
+    }
+
+    /// The nodes of the crates matching the patterns, or an error naming the option they were
+    /// passed to if there are none.
+    fn matching(&self, patterns: &[Pattern], option: &str) -> CliResult<Vec<Node>> {
+        let ids: Vec<Node> = (0..self.nodes.len())
+            .filter(|&id| util::matches_any(patterns, &self.nodes[id].name))
+            .collect();
+        if ids.is_empty() {
+            return Err(CliError::Generic(format!(
+                "No crate in the graph matches {}",
+                option
+            )));
+        }
+        Ok(ids)
+    }
+
+    /// Which nodes depend on one of `ids`, directly or not, including `ids` themselves.
+    fn ancestors(&self, ids: &[Node]) -> Vec<bool> {
+        let mut found = vec![false; self.nodes.len()];
+        let mut stack = ids.to_vec();
+        while let Some(id) = stack.pop() {
+            if !found[id] {
+                found[id] = true;
+                stack.extend(&self.nodes[id].parents);
+            }
+        }
+        found
+    }
+
+    /// Which nodes one of `ids` depends on, directly or not, including `ids` themselves.
+    fn descendants(&self, ids: &[Node]) -> Vec<bool> {
+        let mut found = vec![false; self.nodes.len()];
+        let mut stack = ids.to_vec();
+        while let Some(id) = stack.pop() {
+            if !found[id] {
+                found[id] = true;
+                stack.extend(&self.nodes[id].children);
+            }
+        }
+        found
+    }
+
+    /// Folds the dependencies of the crates matching the patterns into them, keeping count of how
+    /// many there were. Dependencies that are still reachable from a root crate some other way stay
+    /// in the graph.
+    pub fn collapse(&mut self, patterns: &[Pattern]) {
+        let counts: Vec<Option<usize>> = (0..self.nodes.len())
+            .map(|id| {
+                if util::matches_any(patterns, &self.nodes[id].name) {
+                    Some(self.descendants(&[id]).iter().filter(|&&d| d).count() - 1)
+                } else {
+                    None
                 }
+            })
+            .collect();
+
+        let reachable_before = self.reachable_from_roots(|_| false);
+        self.edges.retain(|ed| counts[ed.0].is_none());
+        for (dep, &count) in self.nodes.iter_mut().zip(&counts) {
+            if count.is_some() {
+                dep.collapsed = count;
+                dep.children.clear();
             }
+            dep.parents.retain(|&parent| counts[parent].is_none());
+        }
+        let reachable = self.reachable_from_roots(|_| false);
 
-            // Skip orphan nodes.
-            // Orphan nodes will still be output later if specified in a subgraph.
-            if !self.cfg.include_orphans {
-                if let DepKind::Unknown = dep.kind() {
-                    continue;
+        self.retain(|id| reachable[id] || !reachable_before[id]);
+    }
+
+    /// Removes the nodes that are more than `depth` edges away from every root crate.
+    pub fn limit_depth(&mut self, depth: usize) {
+        let distances = self.distances_from_roots();
+        let removed: Vec<bool> = distances
+            .iter()
+            .map(|distance| distance.is_none_or(|distance| distance > depth))
+            .collect();
+
+        self.mark_truncated(&removed);
+        self.retain(|id| !removed[id]);
+    }
+
+    /// Counts, on each crate that is kept, the removed crates that it leads to through removed
+    /// crates only, including the ones that were already left out below them.
+    fn mark_truncated(&mut self, removed: &[bool]) {
+        for id in 0..self.nodes.len() {
+            if removed[id] || !self.nodes[id].children.iter().any(|&child| removed[child]) {
+                continue;
+            }
+
+            let mut below = HashSet::new();
+            let mut stack: Vec<Node> = self.nodes[id].children.clone();
+            while let Some(child) = stack.pop() {
+                if removed[child] && below.insert(child) {
+                    stack.extend(&self.nodes[child].children);
                 }
             }
+            let count: usize = below
+                .iter()
+                .map(|&child| 1 + self.nodes[child].truncated.unwrap_or(0))
+                .sum();
+            *self.nodes[id].truncated.get_or_insert(0) += count;
+        }
+    }
 
-            write!(output, "\tn{}", i)?;
-            dep.label(output, &self)?;
+    /// Removes crates until at most `max_nodes` are shown, starting with the ones furthest from the
+    /// root crates and, among those, with the ones that have no dependencies. Every crate that is
+    /// left can still be reached from a root crate. Returns the names of the removed crates.
+    pub fn limit_nodes(&mut self, max_nodes: usize) -> Vec<String> {
+        let distances = self.distances_from_roots();
+        let mut shown: Vec<Node> = (0..self.nodes.len())
+            .filter(|&id| self.is_shown(id))
+            .collect();
+        if shown.len() <= max_nodes {
+            return vec![];
+        }
 
-            nodes_added.push(i);
+        // Crates that cannot be reached at all go first. Removing crates by decreasing distance
+        // keeps a path from a root to every crate that is left.
+        shown.sort_by_key(|&id| {
+            (
+                std::cmp::Reverse(distances[id].unwrap_or(usize::MAX)),
+                !self.nodes[id].children.is_empty(),
+                id,
+            )
+        });
+        let mut removed = vec![false; self.nodes.len()];
+        for &id in &shown[..shown.len() - max_nodes] {
+            removed[id] = true;
         }
-        writeln!(output)?;
 
-        // Output any subgraph nodes.
-        if let Some(sub_deps) = &self.cfg.subgraph {
-            writeln!(output, "\tsubgraph cluster_subgraph {{")?;
-            if let Some(sub_name) = &self.cfg.subgraph_name {
-                writeln!(output, "\t\tlabel=\"{}\";", sub_name)?;
-            }
-            writeln!(output, "\t\tcolor=brown;")?;
-            writeln!(output, "\t\tstyle=dashed;")?;
-            writeln!(output)?;
+        let names = (0..self.nodes.len())
+            .filter(|&id| removed[id])
+            .map(|id| self.nodes[id].name.clone())
+            .collect();
+        self.mark_truncated(&removed);
+        self.retain(|id| !removed[id]);
+        names
+    }
 
-            for (i, dep) in self.nodes.iter().enumerate() {
-                if sub_deps.contains(&dep.name) {
-                    write!(output, "\t\tn{}", i)?;
-                    dep.label(output, &self)?;
+    /// The nodes that the graph starts at: the root crates or, in an inverted graph or a subtree,
+    /// the crates without parents.
+    fn start_nodes(&self) -> Vec<Node> {
+        (0..self.nodes.len())
+            .filter(|&id| {
+                if self.inverted || self.subtree {
+                    self.nodes[id].parents.is_empty()
+                } else {
+                    self.is_root(id)
+                }
+            })
+            .collect()
+    }
 
-                    nodes_added.push(i);
+    /// The shortest distance of each node from a root crate, if it can be reached from one. In an
+    /// inverted graph or a subtree, the distances are from the crates that it starts at.
+    fn distances_from_roots(&self) -> Vec<Option<usize>> {
+        let mut distances: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        let mut queue: VecDeque<Node> = self.start_nodes().into();
+        for &id in &queue {
+            distances[id] = Some(0);
+        }
+
+        // Breadth-first search, so that each node gets its shortest distance from a root.
+        while let Some(id) = queue.pop_front() {
+            let distance = distances[id].unwrap() + 1;
+            for &child in &self.nodes[id].children {
+                if distances[child].is_none() {
+                    distances[child] = Some(distance);
+                    queue.push_back(child);
                 }
             }
+        }
 
-            writeln!(output, "\t}}\n")?;
+        distances
+    }
+
+    /// The topological layer of each of the `shown` nodes: crates without dependencies are in layer
+    /// 0, and every other crate is in the layer after the highest one of its dependencies. This is
+    /// the order in which cargo can build the crates, given enough parallelism.
+    fn layers(&self, shown: &HashSet<Node>) -> Vec<Option<usize>> {
+        // The dependencies of a crate are its parents in an inverted graph.
+        let deps = |id: Node| {
+            let deps = if self.inverted {
+                &self.nodes[id].parents
+            } else {
+                &self.nodes[id].children
+            };
+            deps.iter().copied().filter(|dep| shown.contains(dep))
+        };
+
+        let mut layers: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        let mut searched = vec![false; self.nodes.len()];
+        for &start in shown {
+            // Depth-first search, which sets the layer of a node once its dependencies have one.
+            // A dependency that is still being searched is part of a cycle, and is left out.
+            let mut stack = vec![(start, false)];
+            while let Some((id, deps_done)) = stack.pop() {
+                if layers[id].is_some() || (searched[id] && !deps_done) {
+                    continue;
+                }
+                if deps_done {
+                    layers[id] = Some(
+                        deps(id)
+                            .filter_map(|dep| layers[dep])
+                            .map(|layer| layer + 1)
+                            .max()
+                            .unwrap_or(0),
+                    );
+                } else {
+                    searched[id] = true;
+                    stack.push((id, true));
+                    stack.extend(
+                        deps(id)
+                            .filter(|&dep| layers[dep].is_none())
+                            .map(|dep| (dep, false)),
+                    );
+                }
+            }
         }
 
-        // Output edges.
-        for ed in &self.edges {
-            // Only add edges if both nodes exist in the graph.
-            if !(nodes_added.contains(&ed.0) && nodes_added.contains(&ed.1)) {
-                continue;
+        layers
+    }
+
+    /// The longest chain of dependencies among the shown crates, from a crate that depends on the
+    /// others down to a crate without dependencies. Cargo cannot build the crates of the chain at
+    /// the same time, so it bounds how parallel a clean build can be. The chain follows the
+    /// topological layers: each crate is followed by a dependency one layer below it.
+    pub fn critical_path(&self) -> Vec<Node> {
+        let shown: HashSet<Node> = (0..self.nodes.len())
+            .filter(|&id| self.is_shown(id))
+            .collect();
+        let layers = self.layers(&shown);
+        let deps = |id: Node| {
+            if self.inverted {
+                &self.nodes[id].parents
+            } else {
+                &self.nodes[id].children
             }
+        };
 
-            write!(output, "\t{}", ed)?;
-            ed.label(output, &self)?;
+        // The first of the crates in the highest layer starts the chain.
+        let mut path = vec![];
+        let mut next = (0..self.nodes.len())
+            .filter(|&id| layers[id].is_some())
+            .max_by_key(|&id| (layers[id], std::cmp::Reverse(id)));
+        while let Some(id) = next {
+            path.push(id);
+            next = layers[id].and_then(|layer| {
+                deps(id)
+                    .iter()
+                    .copied()
+                    .filter(|&dep| layer > 0 && layers[dep] == Some(layer - 1))
+                    .min()
+            });
         }
 
-        writeln!(output, "}}")?;
+        path
+    }
 
-        Ok(())
+    /// The longest compile time of a crate from `--timings`, in seconds.
+    pub fn max_build_time(&self) -> f64 {
+        self.nodes
+            .iter()
+            .filter_map(|dep| dep.build_time)
+            .fold(0.0, f64::max)
+    }
+
+    /// The size of the largest crate in bytes, as the crates show it.
+    pub fn max_crate_size(&self) -> u64 {
+        self.nodes
+            .iter()
+            .filter_map(|dep| dep.size())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Marks the crates of the longest chain of dependencies with their position on it.
+    pub fn mark_critical_path(&mut self) -> Vec<Node> {
+        let path = self.critical_path();
+        for (i, &id) in path.iter().enumerate() {
+            self.nodes[id].critical_path = Some(i);
+        }
+        path
+    }
+
+    /// Scales the nodes by how many crates depend on them, how many crates they depend on, or how
+    /// many crates they pull in transitively, up to three times the default size. Nodes with a
+    /// count of one or less keep the default size, and the area of the others grows with the count,
+    /// so that the crates with the highest counts stand out without dwarfing the rest.
+    pub fn size_nodes(&mut self, size_by: SizeBy) {
+        let cfg = &self.cfg;
+        let shown =
+            |dep: &ResolvedDep| dep.kind() != DepKind::Unknown || cfg.keeps_orphan(&dep.name);
+
+        // Compile times and sizes are continuous, and scale the nodes by their share of the
+        // largest one.
+        if size_by == SizeBy::BuildTime || size_by == SizeBy::Bytes {
+            let values: Vec<Option<f64>> = self
+                .nodes
+                .iter()
+                .map(|dep| match size_by {
+                    SizeBy::BuildTime => dep.build_time,
+                    _ => dep.size().map(|size| size as f64),
+                })
+                .collect();
+            let max = values.iter().flatten().copied().fold(0.0, f64::max);
+            if max > 0.0 {
+                for (dep, value) in self.nodes.iter_mut().zip(values) {
+                    if let Some(value) = value.filter(|&value| value > 0.0) {
+                        dep.scale = Some(1.0 + 2.0 * (value / max).sqrt());
+                    }
+                }
+            }
+            return;
+        }
+
+        let mut counts = if size_by == SizeBy::Subtree {
+            self.subtree_counts()
+        } else {
+            vec![0; self.nodes.len()]
+        };
+        for ed in &self.edges {
+            let Edge(parent, child) = ed.declared(self);
+            if !(shown(&self.nodes[parent]) && shown(&self.nodes[child])) {
+                continue;
+            }
+            match size_by {
+                SizeBy::FanIn => counts[child] += 1,
+                SizeBy::FanOut => counts[parent] += 1,
+                SizeBy::Subtree | SizeBy::BuildTime | SizeBy::Bytes => {}
+            }
+        }
+
+        let max = counts.iter().copied().max().unwrap_or(0);
+        if max <= 1 {
+            return;
+        }
+        for (dep, &count) in self.nodes.iter_mut().zip(&counts) {
+            if count > 1 {
+                let share = (count - 1) as f64 / (max - 1) as f64;
+                dep.scale = Some(1.0 + 2.0 * share.sqrt());
+            }
+        }
+    }
+
+    /// Computes the metrics of `--stats`, with the `top` crates with the largest subtrees and the
+    /// most dependents.
+    pub fn stats(&self, top: usize) -> Stats {
+        let shown: Vec<Node> = (0..self.nodes.len())
+            .filter(|&id| self.is_shown(id))
+            .collect();
+
+        let mut edges = 0;
+        let mut dependents = vec![0; self.nodes.len()];
+        for ed in &self.edges {
+            let Edge(parent, child) = ed.declared(self);
+            if self.is_shown(parent) && self.is_shown(child) {
+                edges += 1;
+                dependents[child] += 1;
+            }
+        }
+
+        let distances = self.distances_from_roots();
+        let depth = shown
+            .iter()
+            .filter_map(|&id| distances[id])
+            .max()
+            .unwrap_or(0);
+
+        let mut versions: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for &id in &shown {
+            let dep = &self.nodes[id];
+            versions
+                .entry(dep.name.as_str())
+                .or_default()
+                .push(dep.ver.clone());
+        }
+        let duplicates = versions
+            .into_iter()
+            .filter(|(_, versions)| versions.len() > 1)
+            .map(|(name, versions)| (name.to_string(), versions))
+            .collect();
+
+        // Ties are broken by the order of the nodes, which is by name.
+        let top_counts = |counts: &[usize]| -> Vec<(Node, usize)> {
+            let mut counts: Vec<(Node, usize)> = shown
+                .iter()
+                .map(|&id| (id, counts[id]))
+                .filter(|&(_, count)| count > 0)
+                .collect();
+            counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            counts.truncate(top);
+            counts
+        };
+
+        Stats {
+            crates: shown.len(),
+            edges,
+            depth,
+            duplicates,
+            largest_subtrees: top_counts(&self.subtree_counts()),
+            most_depended_upon: top_counts(&dependents),
+        }
+    }
+
+    /// Sets the number of shown crates that each crate depends on, directly or transitively, for
+    /// `--transitive-counts`.
+    pub fn count_transitive_deps(&mut self) {
+        let counts = self.subtree_counts();
+        for (dep, count) in self.nodes.iter_mut().zip(counts) {
+            dep.transitive = Some(count);
+        }
+    }
+
+    /// The number of shown crates below each crate, leaving out orphans unless they are included.
+    fn subtree_counts(&self) -> Vec<usize> {
+        let cfg = &self.cfg;
+        let shown =
+            |dep: &ResolvedDep| dep.kind() != DepKind::Unknown || cfg.keeps_orphan(&dep.name);
+
+        (0..self.nodes.len())
+            .map(|id| {
+                // The dependencies of a crate are its ancestors in an inverted graph.
+                let subtree = if self.inverted {
+                    self.ancestors(&[id])
+                } else {
+                    self.descendants(&[id])
+                };
+                (0..self.nodes.len())
+                    .filter(|&i| i != id && subtree[i] && shown(&self.nodes[i]))
+                    .count()
+            })
+            .collect()
+    }
+
+    /// Merges all versions of each crate into the node of its first version, which lists every
+    /// version. The merged node depends on, and is depended on by, everything any version was.
+    /// The counts of collapsed and left out crates are added up. The transitive count is the
+    /// largest one, as the versions mostly pull in the same crates and these were counted before
+    /// the graph was cut down, so that they cannot be counted again for the merged node. With
+    /// `--diff`, a crate of which one version was added and another removed is shown as changed.
+    pub fn merge_versions(&mut self) {
+        let mut first_ids: HashMap<String, Node> = HashMap::new();
+        let merged_into: Vec<Node> = (0..self.nodes.len())
+            .map(|id| *first_ids.entry(self.nodes[id].name.clone()).or_insert(id))
+            .collect();
+
+        for (id, &first) in merged_into.iter().enumerate() {
+            if first == id {
+                continue;
+            }
+
+            let dep = self.nodes[id].clone();
+            let node = &mut self.nodes[first];
+            if node.merged_vers.is_empty() {
+                node.merged_vers.push(node.ver.clone());
+            }
+            node.merged_vers.push(dep.ver.clone());
+            node.force_write_ver = true;
+            if let Some(count) = dep.collapsed {
+                node.collapsed = Some(node.collapsed.unwrap_or(0) + count);
+            }
+            if let Some(count) = dep.truncated {
+                node.truncated = Some(node.truncated.unwrap_or(0) + count);
+            }
+            node.transitive = node.transitive.max(dep.transitive);
+            node.patched |= dep.patched;
+            node.proc_macro |= dep.proc_macro;
+            node.license_violation |= dep.license_violation;
+            node.yanked |= dep.yanked;
+            node.duplicate |= dep.duplicate;
+            node.diff = match (node.diff.take(), dep.diff) {
+                (Some(DiffStatus::Added), Some(DiffStatus::Removed)) => {
+                    Some(DiffStatus::Changed(dep.ver.clone()))
+                }
+                (Some(DiffStatus::Removed), Some(DiffStatus::Added)) => {
+                    let previous = std::mem::replace(&mut node.ver, dep.ver.clone());
+                    Some(DiffStatus::Changed(previous))
+                }
+                (Some(DiffStatus::Changed(previous)), _)
+                | (_, Some(DiffStatus::Changed(previous))) => Some(DiffStatus::Changed(previous)),
+                (diff, other) => diff.or(other),
+            };
+            if let Some(time) = dep.build_time {
+                node.build_time = Some(node.build_time.unwrap_or(0.0) + time);
+            }
+            if let Some(size) = dep.download_size {
+                node.download_size = Some(node.download_size.unwrap_or(0) + size);
+            }
+            if let Some(size) = dep.unpacked_size {
+                node.unpacked_size = Some(node.unpacked_size.unwrap_or(0) + size);
+            }
+            node.msrv_violation |= dep.msrv_violation;
+            node.obscure |= dep.obscure;
+            if node.downloads.is_none() {
+                node.downloads = dep.downloads;
+            }
+            if node.rust_version.is_none() {
+                node.rust_version = dep.rust_version.clone();
+            }
+            if node.latest.is_none() {
+                node.latest = dep.latest.clone();
+            }
+            for advisory in dep.advisories {
+                if node.advisories.iter().all(|a| a.id != advisory.id) {
+                    node.advisories.push(advisory);
+                }
+            }
+            node.is_regular |= dep.is_regular;
+            node.is_build |= dep.is_build;
+            node.is_dev |= dep.is_dev;
+            node.is_optional |= dep.is_optional;
+            if let Some(license) = dep.license {
+                match &mut node.license {
+                    Some(licenses) if licenses.split(", ").all(|l| l != license) => {
+                        licenses.push_str(", ");
+                        licenses.push_str(&license);
+                    }
+                    Some(_) => (),
+                    None => node.license = Some(license),
+                }
+            }
+        }
+
+        let mut edges = vec![];
+        let mut seen: HashSet<Edge> = HashSet::new();
+        let mut edge_kinds: HashMap<Edge, Vec<DepKind>> = HashMap::new();
+        let mut edge_reqs: HashMap<Edge, Vec<String>> = HashMap::new();
+        for ed in &self.edges {
+            let merged = Edge(merged_into[ed.0], merged_into[ed.1]);
+            if merged.0 == merged.1 {
+                continue;
+            }
+            if seen.insert(merged) {
+                edges.push(merged);
+            }
+            if let Some(kinds) = self.edge_kinds.get(ed) {
+                let merged_kinds = edge_kinds.entry(merged).or_default();
+                for &kind in kinds {
+                    if !merged_kinds.contains(&kind) {
+                        merged_kinds.push(kind);
+                    }
+                }
+            }
+            if let Some(reqs) = self.edge_reqs.get(ed) {
+                let merged_reqs = edge_reqs.entry(merged).or_default();
+                for req in reqs {
+                    if !merged_reqs.contains(req) {
+                        merged_reqs.push(req.clone());
+                    }
+                }
+            }
+        }
+
+        for dep in &mut self.nodes {
+            dep.children.clear();
+            dep.parents.clear();
+        }
+        for ed in &edges {
+            self.nodes[ed.0].children.push(ed.1);
+            self.nodes[ed.1].parents.push(ed.0);
+        }
+        self.edges = edges;
+        self.edge_kinds = edge_kinds;
+        self.edge_reqs = edge_reqs;
+
+        self.retain(|id| merged_into[id] == id);
+    }
+
+    /// The licenses of the shown crates with the number of crates under each, most common first.
+    /// Crates whose license is not known count as `None`.
+    pub fn license_counts(&self) -> Vec<(Option<&str>, usize)> {
+        let mut counts: HashMap<Option<&str>, usize> = HashMap::new();
+        for (id, dep) in self.nodes.iter().enumerate() {
+            if self.is_shown(id) {
+                *counts.entry(dep.license.as_deref()).or_default() += 1;
+            }
+        }
+
+        let mut counts: Vec<(Option<&str>, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then(a.0.is_none().cmp(&b.0.is_none()))
+                .then(a.0.cmp(&b.0))
+        });
+        counts
+    }
+
+    /// The shown edges whose version requirement is too loose, as the names and versions of the
+    /// crates that declare and that match the requirement, along with the requirement.
+    pub fn loose_reqs(&self) -> Vec<(String, String, &str)> {
+        self.edges
+            .iter()
+            .filter(|ed| self.is_shown(ed.0) && self.is_shown(ed.1))
+            .filter_map(|ed| {
+                let req = ed.loose_req(self)?;
+                let ed = ed.declared(self);
+                let crate_id =
+                    |id: Node| format!("{} v{}", self.nodes[id].name, self.nodes[id].ver);
+                Some((crate_id(ed.0), crate_id(ed.1), req))
+            })
+            .collect()
+    }
+
+    /// Removes the edges that are implied by longer paths: if A depends on B, B on C and A on C,
+    /// the edge from A to C is dropped. Which crates can be reached from which stays the same.
+    /// Back edges of dependency cycles are kept, and the rest of the graph is reduced without them.
+    pub fn transitive_reduction(&mut self) {
+        let back_edges = self.back_edges();
+        let children: Vec<Vec<Node>> = (0..self.nodes.len())
+            .map(|id| {
+                self.nodes[id]
+                    .children
+                    .iter()
+                    .copied()
+                    .filter(|&child| !back_edges.contains(&Edge(id, child)))
+                    .collect()
+            })
+            .collect();
+
+        let mut implied = HashSet::new();
+        for (id, dep_children) in children.iter().enumerate() {
+            // In an acyclic graph, a child that can be reached from a grandchild can be reached
+            // through another child.
+            let mut indirect = vec![false; self.nodes.len()];
+            let mut stack: Vec<Node> = dep_children
+                .iter()
+                .flat_map(|&child| children[child].iter().copied())
+                .collect();
+            while let Some(id) = stack.pop() {
+                if !indirect[id] {
+                    indirect[id] = true;
+                    stack.extend(&children[id]);
+                }
+            }
+            for &child in dep_children {
+                if indirect[child] {
+                    implied.insert(Edge(id, child));
+                }
+            }
+        }
+
+        self.remove_edges(&implied);
+    }
+
+    /// Removes the edges, along with the crates that can then no longer be reached from a root
+    /// crate. Crates that could not be reached before are left alone.
+    pub fn remove_edges(&mut self, edges: &HashSet<Edge>) {
+        let reachable_before = self.reachable_from_roots(|_| false);
+        self.edges.retain(|ed| !edges.contains(ed));
+        for (id, dep) in self.nodes.iter_mut().enumerate() {
+            dep.children
+                .retain(|&child| !edges.contains(&Edge(id, child)));
+            dep.parents
+                .retain(|&parent| !edges.contains(&Edge(parent, id)));
+        }
+        let reachable = self.reachable_from_roots(|_| false);
+
+        self.retain(|id| reachable[id] || !reachable_before[id]);
+    }
+
+    /// Adds the nodes and edges of the graph of another project. Crates with the same name and
+    /// version in both graphs become a single node. This must be done before sorting the graph.
+    pub fn merge(&mut self, other: DepGraph) {
+        let ids: Vec<Node> = other
+            .nodes
+            .iter()
+            .map(|dep| {
+                let id = self.find_or_add(&dep.name, &dep.ver);
+                let node = &mut self.nodes[id];
+                node.patched |= dep.patched;
+                node.proc_macro |= dep.proc_macro;
+                if node.source.is_none() {
+                    node.source = dep.source.clone();
+                }
+                if node.registry.is_none() {
+                    node.registry = dep.registry.clone();
+                }
+                if node.license.is_none() {
+                    node.license = dep.license.clone();
+                }
+                if node.repository.is_none() {
+                    node.repository = dep.repository.clone();
+                }
+                if node.description.is_none() {
+                    node.description = dep.description.clone();
+                }
+                if node.rust_version.is_none() {
+                    node.rust_version = dep.rust_version.clone();
+                }
+                id
+            })
+            .collect();
+
+        for ed in &other.edges {
+            let (parent, child) = (ids[ed.0], ids[ed.1]);
+            if !self.nodes[parent].children.contains(&child) {
+                let (name, ver) = (
+                    self.nodes[child].name.clone(),
+                    self.nodes[child].ver.clone(),
+                );
+                self.add_child(parent, &name, &ver);
+            }
+        }
+        for (ed, kinds) in other.edge_kinds {
+            let merged_kinds = self
+                .edge_kinds
+                .entry(Edge(ids[ed.0], ids[ed.1]))
+                .or_default();
+            for kind in kinds {
+                if !merged_kinds.contains(&kind) {
+                    merged_kinds.push(kind);
+                }
+            }
+        }
+        for (ed, reqs) in other.edge_reqs {
+            let merged_reqs = self
+                .edge_reqs
+                .entry(Edge(ids[ed.0], ids[ed.1]))
+                .or_default();
+            for req in reqs {
+                if !merged_reqs.contains(&req) {
+                    merged_reqs.push(req);
+                }
+            }
+        }
+
+        for (deps_map, other_deps_map) in [
+            (&mut self.root_deps_map, other.root_deps_map),
+            (&mut self.path_deps_map, other.path_deps_map),
+        ] {
+            for (name, dep_kinds_map) in other_deps_map {
+                let merged = deps_map.entry(name).or_default();
+                for (dep_name, kinds) in dep_kinds_map {
+                    let merged_kinds = merged.entry(dep_name).or_default();
+                    for kind in kinds {
+                        if !merged_kinds.contains(&kind) {
+                            merged_kinds.push(kind);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, id: usize) -> Option<&ResolvedDep> {
+        if id < self.nodes.len() {
+            return Some(&self.nodes[id]);
+        }
+        None
+    }
+
+    /// Identifier of the node in the output. All output formats that need node identifiers use
+    /// this, so that they can be cross-referenced.
+    pub fn node_id(id: Node) -> String {
+        format!("n{}", id)
+    }
+
+    /// Whether the node ends up in the output. Orphan nodes are only shown if requested or if they
+    /// are part of the subgraph.
+    pub fn is_shown(&self, id: Node) -> bool {
+        let dep = &self.nodes[id];
+        if dep.kind() != DepKind::Unknown || self.cfg.keeps_orphan(&dep.name) {
+            return true;
+        }
+
+        self.cfg.in_subgraph(&self.nodes[id].name)
+    }
+
+    pub fn find(&self, name: &str, ver: &str) -> Option<usize> {
+        for (i, d) in self.nodes.iter().enumerate() {
+            if d.name == name && d.ver == ver {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    pub fn find_or_add(&mut self, name: &str, ver: &str) -> usize {
+        if let Some(i) = self.find(name, ver) {
+            return i;
+        }
+        self.nodes
+            .push(ResolvedDep::new(name.to_owned(), ver.to_owned()));
+        self.nodes.len() - 1
+    }
+
+    /// Groups each workspace member with the path dependencies that it pulls in, directly or
+    /// through other path dependencies. A path dependency shared by several members is grouped with
+    /// the first of them. Returns nothing if there is only one root crate.
+    pub fn member_clusters(&self) -> Vec<(Node, Vec<Node>)> {
+        let is_root = |id: Node| self.root_deps_map.contains_key(&self.nodes[id].name);
+        let in_subgraph = |id: Node| self.cfg.in_subgraph(&self.nodes[id].name);
+
+        let members: Vec<Node> = (0..self.nodes.len())
+            .filter(|&i| is_root(i) && self.is_shown(i) && !in_subgraph(i))
+            .collect();
+        if members.len() < 2 {
+            return vec![];
+        }
+
+        let mut assigned = HashSet::new();
+        members
+            .into_iter()
+            .map(|member| {
+                let mut cluster = vec![member];
+                let mut i = 0;
+                while i < cluster.len() {
+                    for &child in &self.nodes[cluster[i]].children {
+                        if self.nodes[child].source.is_none()
+                            && !is_root(child)
+                            && self.is_shown(child)
+                            && !in_subgraph(child)
+                            && !assigned.contains(&child)
+                            && !cluster.contains(&child)
+                        {
+                            cluster.push(child);
+                        }
+                    }
+                    i += 1;
+                }
+
+                assigned.extend(cluster.iter().cloned());
+                (member, cluster)
+            })
+            .collect()
+    }
+
+    /// Writes the default attributes of the graph, nodes and edges that differ from the graphviz
+    /// ones: the layout engine, direction and options, the font, the colors of the theme and the
+    /// color of regular dependencies, followed by the attributes passed with `--graph-attr`,
+    /// `--node-attr` and `--edge-attr`, which override them.
+    fn write_defaults<W: Write>(&self, output: &mut W) -> CliResult<()> {
+        let theme = self.cfg.theme;
+        let regular = self.cfg.color(DepKind::Regular);
+        let mut font: Vec<String> = (theme.font != "black")
+            .then(|| format!("fontcolor={}", dep::dot_id(theme.font)))
+            .into_iter()
+            .collect();
+        let color = (regular != "black").then(|| format!("color={}", dep::dot_id(regular)));
+        if let Some(name) = &self.cfg.font {
+            font.push(format!("fontname={}", dep::dot_id(name)));
+        }
+        if let Some(size) = self.cfg.font_size {
+            font.push(format!("fontsize={}", size));
+        }
+
+        let mut graph_attrs: Vec<String> = font.clone();
+        if let Some(rankdir) = &self.cfg.rankdir {
+            graph_attrs.push(format!("rankdir={}", rankdir));
+        }
+        if let Some(layout) = &self.cfg.layout {
+            graph_attrs.push(format!("layout={}", layout));
+        }
+        if let Some(splines) = &self.cfg.splines {
+            graph_attrs.push(format!("splines={}", splines));
+        }
+        if let Some(overlap) = &self.cfg.overlap {
+            graph_attrs.push(format!("overlap={}", dep::dot_id(overlap)));
+        }
+        if let Some(background) = theme.background {
+            graph_attrs.push(format!("bgcolor={}", dep::dot_id(background)));
+        }
+        let mut node_attrs: Vec<String> = font.iter().chain(&color).cloned().collect();
+        if let Some(fill) = theme.fill {
+            node_attrs.push("style=filled".into());
+            node_attrs.push(format!("fillcolor={}", dep::dot_id(fill)));
+        }
+        let edge_attrs: Vec<String> = font.iter().chain(&color).cloned().collect();
+
+        for (element, mut attrs, extra) in [
+            ("graph", graph_attrs, &self.cfg.graph_attrs),
+            ("node", node_attrs, &self.cfg.node_attrs),
+            ("edge", edge_attrs, &self.cfg.edge_attrs),
+        ] {
+            attrs.extend(
+                extra
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, dep::dot_id(value))),
+            );
+            if !attrs.is_empty() {
+                writeln!(output, "\t{} [{}];", element, attrs.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Groups the crates by where they come from: local crates, crates.io, each other registry and
+    /// git repositories, in that order. Empty groups are left out.
+    pub fn source_clusters(&self) -> Vec<(String, String, Vec<Node>)> {
+        let in_subgraph = |id: Node| self.cfg.in_subgraph(&self.nodes[id].name);
+
+        let mut path = vec![];
+        let mut crates_io = vec![];
+        let mut registries: BTreeMap<String, Vec<Node>> = BTreeMap::new();
+        let mut git = vec![];
+        for id in (0..self.nodes.len()).filter(|&i| self.is_shown(i) && !in_subgraph(i)) {
+            let dep = &self.nodes[id];
+            match (dep.source_kind(), dep.registry.as_deref()) {
+                (SourceKind::Path, _) => path.push(id),
+                (SourceKind::Git, _) => git.push(id),
+                (SourceKind::Registry, Some("crates-io")) => crates_io.push(id),
+                // Registries that were not looked up by name are labeled with their index.
+                (SourceKind::Registry, registry) => {
+                    let registry = registry
+                        .map(String::from)
+                        .or_else(|| dep.source.as_deref().and_then(registry::index_url));
+                    registries
+                        .entry(registry.unwrap_or_else(|| "registry".into()))
+                        .or_default()
+                        .push(id)
+                }
+            }
+        }
+
+        let mut clusters = vec![
+            ("source_path".to_string(), "path".to_string(), path),
+            ("source_crates_io".into(), "crates.io".into(), crates_io),
+        ];
+        for (i, (registry, ids)) in registries.into_iter().enumerate() {
+            clusters.push((format!("source_registry_{}", i), registry, ids));
+        }
+        clusters.push(("source_git".into(), "git".into(), git));
+        clusters.retain(|(_, _, ids)| !ids.is_empty());
+        clusters
+    }
+
+    pub fn render_to<W: Write>(self, output: &mut W) -> CliResult<()> {
+        // Keep track of added nodes.
+        let mut nodes_added = vec![];
+
+        // Each cluster has an id, a label and its nodes.
+        let clusters: Vec<(String, String, Vec<Node>)> =
+            if self.cfg.cluster_by == Some(ClusterBy::Source) {
+                self.source_clusters()
+            } else if self.cfg.member_clusters {
+                self.member_clusters()
+                    .into_iter()
+                    .map(|(member, cluster)| {
+                        let name = self.nodes[member].name.clone();
+                        (format!("member_{}", member), name, cluster)
+                    })
+                    .collect()
+            } else {
+                vec![]
+            };
+        let clustered: HashSet<Node> = clusters
+            .iter()
+            .flat_map(|(_, _, cluster)| cluster.iter().cloned())
+            .collect();
+
+        writeln!(output, "digraph dependencies {{")?;
+        self.write_defaults(output)?;
+
+        // Output all non-subgraph nodes.
+        for (i, dep) in self.nodes.iter().enumerate() {
+            // Skip subgraph nodes, will be declared in the subgraph.
+            if self.cfg.in_subgraph(&dep.name) {
+                continue;
+            }
+
+            // Skip nodes of clusters, will be declared in the clusters.
+            if clustered.contains(&i) {
+                continue;
+            }
+
+            // Skip orphan nodes.
+            // Orphan nodes will still be output later if specified in a subgraph.
+            if dep.kind() == DepKind::Unknown && !self.cfg.keeps_orphan(&dep.name) {
+                continue;
+            }
+
+            write!(output, "\t{}", Self::node_id(i))?;
+            dep.label(output, &self)?;
+
+            nodes_added.push(i);
+        }
+        writeln!(output)?;
+
+        // Output a cluster for each workspace member or source.
+        for (id, label, cluster) in &clusters {
+            writeln!(output, "\tsubgraph cluster_{} {{", id)?;
+            writeln!(output, "\t\tlabel={};", dep::dot_id(label))?;
+            writeln!(output, "\t\tcolor=gray;")?;
+            writeln!(output)?;
+
+            for &i in cluster {
+                write!(output, "\t\t{}", Self::node_id(i))?;
+                self.nodes[i].label(output, &self)?;
+
+                nodes_added.push(i);
+            }
+
+            writeln!(output, "\t}}\n")?;
+        }
+
+        // Output any subgraph nodes. A crate matching several subgraphs is in the first of them.
+        for (s, subgraph) in self.cfg.subgraphs.iter().enumerate() {
+            writeln!(output, "\tsubgraph cluster_subgraph_{} {{", s)?;
+            if let Some(sub_name) = &subgraph.name {
+                writeln!(output, "\t\tlabel={};", dep::dot_id(sub_name))?;
+            }
+            let color = subgraph.color.as_deref().unwrap_or("brown");
+            writeln!(output, "\t\tcolor={};", dep::dot_id(color))?;
+            writeln!(output, "\t\tstyle=dashed;")?;
+            writeln!(output)?;
+
+            for (i, dep) in self.nodes.iter().enumerate() {
+                if self.cfg.subgraph_of(&dep.name) == Some(s) {
+                    write!(output, "\t\t{}", Self::node_id(i))?;
+                    dep.label(output, &self)?;
+
+                    nodes_added.push(i);
+                }
+            }
+
+            writeln!(output, "\t}}\n")?;
+        }
+
+        // Put the crates at the same distance from the root crates, or in the same layer, on the
+        // same rank.
+        let distances = if self.cfg.rank_by_depth {
+            Some(self.distances_from_roots())
+        } else if self.cfg.rank_by_layer {
+            let shown: HashSet<Node> = nodes_added.iter().copied().collect();
+            Some(self.layers(&shown))
+        } else {
+            None
+        };
+        if let Some(distances) = distances {
+            let mut ranks: Vec<Vec<Node>> = vec![];
+            for &i in &nodes_added {
+                if let Some(distance) = distances[i] {
+                    if ranks.len() <= distance {
+                        ranks.resize(distance + 1, vec![]);
+                    }
+                    ranks[distance].push(i);
+                }
+            }
+            for rank in ranks.iter_mut().filter(|rank| !rank.is_empty()) {
+                rank.sort_unstable();
+                let ids: Vec<String> = rank.iter().map(|&i| Self::node_id(i)).collect();
+                writeln!(output, "\t{{rank=same; {};}}", ids.join("; "))?;
+            }
+            writeln!(output)?;
+        }
+
+        // Output edges, sorted by their nodes rather than in topological order, so that they stay
+        // in place when dependencies are added or removed.
+        let mut edges = self.edges.clone();
+        edges.sort_unstable();
+        let back_edges = self.back_edges();
+        for ed in &edges {
+            // Only add edges if both nodes exist in the graph.
+            if !(nodes_added.contains(&ed.0) && nodes_added.contains(&ed.1)) {
+                continue;
+            }
+
+            // A dependency of several kinds gets an edge for each of them.
+            for kind in ed.kinds(&self)? {
+                write!(output, "\t{}", ed)?;
+                ed.label(output, &self, kind, back_edges.contains(ed))?;
+            }
+        }
+
+        // Show how many crates were left out below each crate that was cut off.
+        for &i in &nodes_added {
+            if let Some(count) = self.nodes[i].truncated {
+                let id = Self::node_id(i);
+                writeln!(
+                    output,
+                    "\t{}_more [label=\"… {} more\", shape=plaintext, fontcolor=gray];",
+                    id, count
+                )?;
+                writeln!(
+                    output,
+                    "\t{} -> {}_more [color=gray, style=dotted];",
+                    id, id
+                )?;
+            }
+        }
+
+        if self.cfg.legend {
+            self.write_legend(output, !back_edges.is_empty())?;
+        }
+
+        writeln!(output, "}}")?;
+
+        Ok(())
+    }
+
+    /// Writes a cluster that explains the colors, shapes and edge styles of the graph: a root crate
+    /// with an edge to a crate of each kind that is shown, followed by the highlights in use.
+    fn write_legend<W: Write>(&self, output: &mut W, cycles: bool) -> CliResult<()> {
+        writeln!(output, "\tsubgraph cluster_legend {{")?;
+        writeln!(output, "\t\tlabel=\"Legend\";")?;
+        writeln!(output, "\t\tcolor=gray;")?;
+        writeln!(output)?;
+
+        let mut root_attrs = vec![
+            "label=\"root crate\"".to_string(),
+            format!("shape={}", dep::dot_id(self.cfg.root_shape())),
+        ];
+        if let Some(color) = &self.cfg.root_color {
+            root_attrs.push(format!("color={}", dep::dot_id(color)));
+        }
+        if self.cfg.root_double_border {
+            root_attrs.push("peripheries=2".into());
+        }
+        if let Some(fill) = &self.cfg.root_fill {
+            root_attrs.push("style=filled".into());
+            root_attrs.push(format!("fillcolor={}", dep::dot_id(fill)));
+        }
+        writeln!(output, "\t\tlegend_root [{}];", root_attrs.join(", "))?;
+        let shown = [
+            self.cfg.regular_deps,
+            self.cfg.build_deps || !self.cfg.build_deps_for.is_empty(),
+            self.cfg.dev_deps || !self.cfg.dev_deps_for.is_empty(),
+            self.cfg.optional_deps || !self.cfg.optional_deps_for.is_empty(),
+            self.cfg.include_orphans.is_some(),
+        ];
+        for (&kind, _) in DepKind::ALL.iter().zip(&shown).filter(|(_, &shown)| shown) {
+            let badge = match kind.badge().filter(|_| self.cfg.badges) {
+                Some(badge) => format!("{} ", badge),
+                None => String::new(),
+            };
+            let mut node_attrs = vec![format!("label=\"{}{} dependency\"", badge, kind.name())];
+            let mut edge_attrs = vec![];
+            if kind != DepKind::Regular {
+                let color = format!("color={}", dep::dot_id(self.cfg.color(kind)));
+                node_attrs.push(color.clone());
+                edge_attrs.push(color);
+            }
+            if let Some(shape) = self.cfg.shapes.get(&kind) {
+                node_attrs.push(format!("shape={}", dep::dot_id(shape)));
+            }
+            if let Some(style) = self.cfg.edge_style(kind) {
+                edge_attrs.push(format!("style={}", dep::dot_id(style)));
+            }
+
+            writeln!(
+                output,
+                "\t\tlegend_{} [{}];",
+                kind.name(),
+                node_attrs.join(", ")
+            )?;
+            write!(output, "\t\tlegend_root -> legend_{}", kind.name())?;
+            if edge_attrs.is_empty() {
+                writeln!(output, ";")?;
+            } else {
+                writeln!(output, " [{}];", edge_attrs.join(", "))?;
+            }
+        }
+
+        if cycles {
+            writeln!(output, "\t\tlegend_cycle [label=\"crate in a cycle\"];")?;
+            writeln!(
+                output,
+                "\t\tlegend_cycle -> legend_root [color=red, style=bold, constraint=false];"
+            )?;
+        }
+
+        // Duplicated crates show their version, unless all crates do.
+        if let Some(fill) = &self.cfg.highlight_duplicates {
+            writeln!(
+                output,
+                "\t\tlegend_duplicate [label=\"crate v1.0.0\\n(in several versions)\", \
+                 style=filled, fillcolor={}];",
+                dep::dot_id(fill)
+            )?;
+        } else if !self.cfg.include_vers {
+            writeln!(
+                output,
+                "\t\tlegend_duplicate [label=\"crate v1.0.0\\n(in several versions)\"];"
+            )?;
+        }
+        if self.cfg.highlight_patched {
+            writeln!(
+                output,
+                "\t\tlegend_patched [label=\"patched crate\", style=filled, fillcolor=lightyellow];"
+            )?;
+        }
+        for (i, highlight) in self.cfg.highlight.iter().enumerate() {
+            let color = dep::dot_id(&highlight.color);
+            writeln!(
+                output,
+                "\t\tlegend_highlight_{} [label={}, color={}, fontcolor={}, penwidth=3];",
+                i,
+                dep::dot_id(&highlight.name),
+                color,
+                color
+            )?;
+        }
+        if self.cfg.timings.is_some() {
+            writeln!(
+                output,
+                "\t\tlegend_fast [label=\"fast to compile\", style=filled, \
+                 fillcolor=\"0.000 0.000 1.000\"];"
+            )?;
+            writeln!(
+                output,
+                "\t\tlegend_slow [label=\"slowest to compile\", style=filled, \
+                 fillcolor=\"0.000 1.000 1.000\"];"
+            )?;
+        }
+        if self.cfg.crate_sizes {
+            writeln!(
+                output,
+                "\t\tlegend_small [label=\"small crate\", style=filled, \
+                 fillcolor=\"0.600 0.000 1.000\"];"
+            )?;
+            writeln!(
+                output,
+                "\t\tlegend_large [label=\"largest crate\", style=filled, \
+                 fillcolor=\"0.600 1.000 1.000\"];"
+            )?;
+        }
+        if self.cfg.highlight_heaviest {
+            writeln!(
+                output,
+                "\t\tlegend_heavy [label=\"heaviest dependency\", color=darkorange, \
+                 fontcolor=darkorange, penwidth=3];"
+            )?;
+        }
+        if let Some(downloads) = self.cfg.obscure_below {
+            writeln!(
+                output,
+                "\t\tlegend_obscure [label=\"fewer than {} downloads\", color=purple, \
+                 fontcolor=purple, penwidth=3];",
+                dep::format_count(downloads)
+            )?;
+        }
+        if self.cfg.critical_path {
+            writeln!(
+                output,
+                "\t\tlegend_critical_path [label=\"critical path\", penwidth=3];"
+            )?;
+            writeln!(
+                output,
+                "\t\tlegend_root -> legend_critical_path [penwidth=3];"
+            )?;
+        }
+        if self.cfg.diff.is_some() {
+            for diff in &[
+                DiffStatus::Added,
+                DiffStatus::Removed,
+                DiffStatus::Changed(String::new()),
+            ] {
+                writeln!(
+                    output,
+                    "\t\tlegend_{} [label=\"{} crate\", style=filled, fillcolor={}];",
+                    diff.name(),
+                    diff.name(),
+                    diff.fill()
+                )?;
+            }
+        }
+        if let Some(toolchain) = &self.cfg.check_msrv {
+            writeln!(
+                output,
+                "\t\tlegend_msrv [label={}, color=firebrick, fontcolor=firebrick, penwidth=3];",
+                dep::dot_id(&format!("needs Rust > {}", toolchain))
+            )?;
+        }
+        if self.cfg.yanked {
+            writeln!(
+                output,
+                "\t\tlegend_yanked [label=\"yanked crate\", style=filled, fillcolor=plum];"
+            )?;
+        }
+        if self.cfg.outdated {
+            writeln!(
+                output,
+                "\t\tlegend_outdated [label=\"outdated crate\", style=filled, fillcolor=orange];"
+            )?;
+        }
+        if self.cfg.advisories {
+            writeln!(
+                output,
+                "\t\tlegend_advisory [label=\"security advisory\", style=filled, \
+                 fillcolor=salmon];"
+            )?;
+        }
+        if !self.cfg.license_policy.is_empty() {
+            writeln!(
+                output,
+                "\t\tlegend_license_violation [label=\"license violation\", color=red, \
+                 fontcolor=red, penwidth=3];"
+            )?;
+        }
+        if self.cfg.highlight_proc_macros || self.cfg.badges {
+            let mut attrs = vec![if self.cfg.badges {
+                format!("label=\"{} proc-macro crate\"", dep::PROC_MACRO_BADGE)
+            } else {
+                "label=\"proc-macro crate\"".to_string()
+            }];
+            if self.cfg.highlight_proc_macros {
+                attrs.push("style=filled".into());
+                attrs.push("fillcolor=lightblue".into());
+            }
+            writeln!(output, "\t\tlegend_proc_macro [{}];", attrs.join(", "))?;
+        }
+
+        writeln!(output, "\t}}")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::DepKindsMap;
+
+    /// A graph of the given dependencies between crates of version 1.0.0, with `app` as the root
+    /// crate.
+    fn graph(deps: &[(&str, &str)]) -> DepGraph {
+        let m = crate::app().get_matches_from(["cargo", "deps"]);
+        let cfg = Config::from_matches(m.subcommand_matches("deps").unwrap()).unwrap();
+        let mut dg = DepGraph::new(cfg);
+        dg.root_deps_map.insert("app".into(), DepKindsMap::new());
+        for &(parent, child) in deps {
+            let parent = dg.find_or_add(parent, "1.0.0");
+            dg.add_child(parent, child, "1.0.0");
+        }
+        dg
+    }
+
+    fn edges(dg: &DepGraph) -> Vec<String> {
+        let mut edges: Vec<String> = dg
+            .edges
+            .iter()
+            .map(|ed| format!("{} -> {}", dg.nodes[ed.0].name, dg.nodes[ed.1].name))
+            .collect();
+        edges.sort();
+        edges
+    }
+
+    fn names(dg: &DepGraph) -> Vec<&str> {
+        let mut names: Vec<&str> = dg.nodes.iter().map(|dep| dep.name.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    fn patterns(names: &[&str]) -> Vec<Pattern> {
+        names
+            .iter()
+            .map(|name| Pattern::new(name).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn transitive_reduction_removes_implied_edges() {
+        let mut dg = graph(&[
+            ("app", "a"),
+            ("app", "b"),
+            ("app", "c"),
+            ("a", "c"),
+            ("b", "c"),
+        ]);
+        dg.transitive_reduction();
+
+        assert_eq!(edges(&dg), ["a -> c", "app -> a", "app -> b", "b -> c"]);
+    }
+
+    #[test]
+    fn transitive_reduction_keeps_back_edges_of_cycles() {
+        // `b` depends back on `a`, like a dev-dependency of `a` that depends on it.
+        let mut dg = graph(&[("app", "a"), ("app", "b"), ("a", "b"), ("b", "a")]);
+        dg.transitive_reduction();
+
+        assert_eq!(edges(&dg), ["a -> b", "app -> a", "b -> a"]);
+        assert_eq!(names(&dg), ["a", "app", "b"]);
+    }
+
+    #[test]
+    fn why_keeps_the_paths_to_the_target() {
+        let mut dg = graph(&[("app", "a"), ("app", "b"), ("a", "c"), ("b", "d")]);
+        dg.why(&patterns(&["c"]), None).unwrap();
+
+        assert_eq!(edges(&dg), ["a -> c", "app -> a"]);
+        assert!(dg.why(&patterns(&["missing"]), None).is_err());
+    }
+
+    #[test]
+    fn focus_keeps_ancestors_and_descendants() {
+        let mut dg = graph(&[("app", "a"), ("app", "b"), ("a", "c"), ("b", "d")]);
+        dg.focus(&patterns(&["a"])).unwrap();
+
+        assert_eq!(edges(&dg), ["a -> c", "app -> a"]);
+    }
+
+    #[test]
+    fn subtree_keeps_the_crate_and_its_dependencies() {
+        let mut dg = graph(&[("app", "a"), ("app", "b"), ("a", "c"), ("b", "c")]);
+        dg.subtree(&patterns(&["a"])).unwrap();
+
+        assert_eq!(edges(&dg), ["a -> c"]);
+        assert!(dg.subtree);
+        assert!(dg.root_deps_map.contains_key("app"));
+    }
+
+    #[test]
+    fn reroot_makes_the_crate_the_root() {
+        let mut dg = graph(&[("app", "a"), ("app", "b"), ("a", "c")]);
+        dg.reroot(&patterns(&["a"])).unwrap();
+
+        assert_eq!(edges(&dg), ["a -> c"]);
+        let roots: Vec<&String> = dg.root_deps_map.keys().collect();
+        assert_eq!(roots, ["a"]);
+        assert_eq!(dg.root_deps_map["a"]["c"], [DepKind::Regular]);
+        assert!(dg.path_deps_map.contains_key("app"));
+    }
+
+    #[test]
+    fn merge_versions_merges_nodes_and_edges() {
+        let mut dg = graph(&[("app", "bar")]);
+        let app = dg.find("app", "1.0.0").unwrap();
+        let bar = dg.find("bar", "1.0.0").unwrap();
+        dg.add_child(app, "foo", "1.0.0");
+        dg.add_child(app, "foo", "2.0.0");
+        dg.add_child(bar, "foo", "2.0.0");
+        dg.merge_versions();
+
+        assert_eq!(names(&dg), ["app", "bar", "foo"]);
+        assert_eq!(edges(&dg), ["app -> bar", "app -> foo", "bar -> foo"]);
+        let foo = &dg.nodes[dg.find("foo", "1.0.0").unwrap()];
+        assert_eq!(foo.merged_vers, ["1.0.0", "2.0.0"]);
+        assert_eq!(foo.parents.len(), 2);
+    }
+
+    #[test]
+    fn merge_versions_combines_diff_status() {
+        let mut dg = graph(&[("app", "foo")]);
+        let app = dg.find("app", "1.0.0").unwrap();
+        dg.add_child(app, "foo", "2.0.0");
+        dg.add_child(app, "bar", "1.0.0");
+        dg.add_child(app, "bar", "2.0.0");
+        for (name, ver, diff) in [
+            ("foo", "1.0.0", DiffStatus::Removed),
+            ("foo", "2.0.0", DiffStatus::Added),
+            ("bar", "2.0.0", DiffStatus::Added),
+        ] {
+            let id = dg.find(name, ver).unwrap();
+            dg.nodes[id].diff = Some(diff);
+        }
+        dg.merge_versions();
+
+        // The merged node of a changed crate is at the added version.
+        let foo = &dg.nodes[dg.find("foo", "2.0.0").unwrap()];
+        assert_eq!(foo.diff, Some(DiffStatus::Changed("1.0.0".into())));
+        let bar = &dg.nodes[dg.find("bar", "1.0.0").unwrap()];
+        assert_eq!(bar.diff, Some(DiffStatus::Added));
     }
 }
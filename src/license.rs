@@ -0,0 +1,232 @@
+/// The licenses that `--allow-license` and `--deny-license` accept and reject. License ids are
+/// compared case-insensitively, and an id like `Apache-2.0` also covers `Apache-2.0 WITH
+/// LLVM-exception`.
+#[derive(Clone, Debug, Default)]
+pub struct LicensePolicy {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl LicensePolicy {
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Whether a crate may be used under an SPDX license expression: one of the alternatives of an
+    /// `OR` has to be acceptable, and all the licenses of an `AND`. Expressions that cannot be
+    /// parsed, like `see LICENSE` for custom license files, are only accepted without an allow
+    /// list, as are unknown licenses.
+    pub fn accepts(&self, expr: Option<&str>) -> bool {
+        let tokens = match expr {
+            Some(expr) => tokenize(expr),
+            None => return self.allow.is_empty(),
+        };
+
+        let mut parser = Parser {
+            policy: self,
+            tokens: &tokens,
+            pos: 0,
+        };
+        match parser.or_expr() {
+            Some(accepted) if parser.pos == tokens.len() => accepted,
+            _ => self.allow.is_empty(),
+        }
+    }
+
+    /// Whether a single license, with its exception if any, is acceptable.
+    fn accepts_license(&self, id: &str, exception: Option<&str>) -> bool {
+        let id = id.trim_end_matches('+');
+        let matches = |list: &[String]| {
+            list.iter().any(|entry| {
+                let mut parts = entry.split_whitespace();
+                let entry_id = parts.next().unwrap_or("");
+                let entry_exception = match (parts.next(), parts.next()) {
+                    (Some(with), Some(exception)) if with.eq_ignore_ascii_case("WITH") => {
+                        Some(exception)
+                    }
+                    _ => None,
+                };
+                entry_id.eq_ignore_ascii_case(id)
+                    && entry_exception
+                        .is_none_or(|e| exception.is_some_and(|x| x.eq_ignore_ascii_case(e)))
+            })
+        };
+
+        !matches(&self.deny) && (self.allow.is_empty() || matches(&self.allow))
+    }
+}
+
+/// Splits an expression into license ids, operators and parentheses. The `/` of old manifests,
+/// like `MIT/Apache-2.0`, means `OR`.
+fn tokenize(expr: &str) -> Vec<String> {
+    expr.replace('(', " ( ")
+        .replace(')', " ) ")
+        .replace('/', " OR ")
+        .split_whitespace()
+        .map(String::from)
+        .collect()
+}
+
+/// A recursive descent parser that evaluates the expression against the policy as it goes.
+/// `AND` binds tighter than `OR`, and `WITH` tighter than both.
+struct Parser<'a> {
+    policy: &'a LicensePolicy,
+    tokens: &'a [String],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn or_expr(&mut self) -> Option<bool> {
+        let mut accepted = self.and_expr()?;
+        while self.eat("OR") {
+            accepted |= self.and_expr()?;
+        }
+        Some(accepted)
+    }
+
+    fn and_expr(&mut self) -> Option<bool> {
+        let mut accepted = self.license()?;
+        while self.eat("AND") {
+            accepted &= self.license()?;
+        }
+        Some(accepted)
+    }
+
+    fn license(&mut self) -> Option<bool> {
+        if self.eat("(") {
+            let accepted = self.or_expr()?;
+            return if self.eat(")") { Some(accepted) } else { None };
+        }
+
+        let id = self.next()?;
+        if ["(", ")", "OR", "AND", "WITH"]
+            .iter()
+            .any(|op| id.eq_ignore_ascii_case(op))
+        {
+            return None;
+        }
+        let exception = if self.eat("WITH") {
+            Some(self.next()?)
+        } else {
+            None
+        };
+        Some(self.policy.accepts_license(id, exception))
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.tokens.get(self.pos)?;
+        self.pos += 1;
+        Some(token)
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        match self.tokens.get(self.pos) {
+            Some(t) if t.eq_ignore_ascii_case(token) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(allow: &[&str], deny: &[&str]) -> LicensePolicy {
+        LicensePolicy {
+            allow: allow.iter().map(|&id| id.into()).collect(),
+            deny: deny.iter().map(|&id| id.into()).collect(),
+        }
+    }
+
+    #[test]
+    fn single_license() {
+        let policy = policy(&["MIT", "Apache-2.0"], &[]);
+        assert!(policy.accepts(Some("MIT")));
+        assert!(policy.accepts(Some("mit")));
+        assert!(!policy.accepts(Some("GPL-3.0")));
+    }
+
+    #[test]
+    fn or() {
+        let allow = policy(&["MIT"], &[]);
+        assert!(allow.accepts(Some("MIT OR Apache-2.0")));
+        assert!(allow.accepts(Some("GPL-3.0 OR MIT")));
+        assert!(!allow.accepts(Some("GPL-3.0 OR LGPL-2.1")));
+
+        // One acceptable alternative is enough, even if the others are denied.
+        let deny = policy(&[], &["GPL-3.0"]);
+        assert!(deny.accepts(Some("GPL-3.0 OR MIT")));
+        assert!(!deny.accepts(Some("GPL-3.0 or GPL-3.0")));
+    }
+
+    #[test]
+    fn and() {
+        let allow = policy(&["MIT", "Apache-2.0"], &[]);
+        assert!(allow.accepts(Some("MIT AND Apache-2.0")));
+        assert!(!allow.accepts(Some("MIT AND GPL-3.0")));
+
+        let deny = policy(&[], &["GPL-3.0"]);
+        assert!(!deny.accepts(Some("MIT AND GPL-3.0")));
+    }
+
+    #[test]
+    fn precedence_and_parentheses() {
+        let policy = policy(&["MIT", "Unicode-DFS-2016"], &[]);
+        assert!(policy.accepts(Some("(MIT OR Apache-2.0) AND Unicode-DFS-2016")));
+        assert!(!policy.accepts(Some("(MIT OR Apache-2.0) AND BSD-3-Clause")));
+        // AND binds tighter than OR.
+        assert!(policy.accepts(Some("MIT OR Apache-2.0 AND BSD-3-Clause")));
+        assert!(!policy.accepts(Some("(MIT OR Apache-2.0) AND (BSD-3-Clause)")));
+    }
+
+    #[test]
+    fn with_exception() {
+        // An id covers the license with any exception.
+        let id = policy(&["Apache-2.0"], &[]);
+        assert!(id.accepts(Some("Apache-2.0 WITH LLVM-exception")));
+
+        // An entry with an exception only covers the license with that exception.
+        let exception = policy(&["Apache-2.0 WITH LLVM-exception"], &[]);
+        assert!(exception.accepts(Some("Apache-2.0 WITH LLVM-exception")));
+        assert!(!exception.accepts(Some("Apache-2.0")));
+        assert!(!exception.accepts(Some("Apache-2.0 WITH Classpath-exception-2.0")));
+
+        let deny = policy(&[], &["GPL-2.0 WITH Classpath-exception-2.0"]);
+        assert!(!deny.accepts(Some("GPL-2.0 WITH Classpath-exception-2.0")));
+        assert!(deny.accepts(Some("GPL-2.0")));
+    }
+
+    #[test]
+    fn or_later() {
+        let allow = policy(&["GPL-2.0"], &[]);
+        assert!(allow.accepts(Some("GPL-2.0+")));
+
+        let deny = policy(&[], &["GPL-2.0"]);
+        assert!(!deny.accepts(Some("GPL-2.0+")));
+    }
+
+    #[test]
+    fn slash() {
+        // The `/` of old manifests means OR.
+        let policy = policy(&["Apache-2.0"], &[]);
+        assert!(policy.accepts(Some("MIT/Apache-2.0")));
+        assert!(!policy.accepts(Some("MIT/BSD-3-Clause")));
+    }
+
+    #[test]
+    fn unparseable_and_unknown() {
+        let allow = policy(&["MIT"], &[]);
+        assert!(!allow.accepts(Some("see LICENSE")));
+        assert!(!allow.accepts(Some("MIT OR")));
+        assert!(!allow.accepts(Some("(MIT")));
+        assert!(!allow.accepts(None));
+
+        let deny = policy(&[], &["GPL-3.0"]);
+        assert!(deny.accepts(Some("see LICENSE")));
+        assert!(deny.accepts(Some("MIT AND")));
+        assert!(deny.accepts(None));
+    }
+}
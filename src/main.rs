@@ -10,24 +10,43 @@
 
 #[macro_use]
 extern crate clap;
+extern crate regex;
+extern crate serde_json;
 extern crate toml;
 
+mod advisory;
 mod config;
 mod dep;
 mod error;
+mod features;
+mod fetch;
 mod graph;
+mod license;
+mod metadata;
 mod project;
+mod registry;
+mod render;
+mod target;
+mod theme;
+mod timings;
 mod util;
 
-use crate::config::Config;
+use crate::config::{Backend, ClusterBy, Config, Link, SizeBy};
 use crate::error::{CliError, CliResult};
 use crate::project::Project;
+use crate::render::Format;
+use crate::theme::Theme;
+use crate::util::TempDir;
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
-use std::fs::File;
-use std::io::{self, BufWriter};
-use std::path::Path;
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
 
-fn parse_cli<'a>() -> ArgMatches<'a> {
+fn app<'a, 'b>() -> App<'a, 'b> {
     App::new("cargo-deps")
         .version(crate_version!())
         .bin_name("cargo")
@@ -39,67 +58,648 @@ fn parse_cli<'a>() -> ArgMatches<'a> {
                 .args_from_usage(
                     "
                     -o  --dot-file [PATH] 'Output file [default: stdout]'
-                        --filter [DEPNAMES] ... 'Only display provided deps'
-                        --include-orphans 'Don't purge orphan nodes (yellow). \
-                        This is useful in some workspaces'
+                        --filter [DEPNAMES] ... 'Only display provided deps. Names may be glob \
+                        (tokio-*) or regex (^serde) patterns'
+                        --exclude [DEPNAMES] ... 'Hide provided deps, and the deps that are only \
+                        reachable through them. Accepts patterns like --filter'
+                        --hide [DEPNAMES] ... 'Hide provided deps like --exclude, on top of \
+                        --hide-common'
+                        --hide-common 'Hide crates that nearly every graph contains, like syn, \
+                        quote and cfg-if'
+                        --collapse [DEPNAMES] ... 'Fold the deps of provided deps into them, \
+                        showing only how many there are. Accepts patterns like --filter'
+                        --root [CRATE] ... 'Graph the given crate as the root crate, showing only \
+                        what it depends on. Accepts patterns like --filter'
+                        --subtree [CRATE] ... 'Only display the given crate and the deps it \
+                        depends on, without the rest of the project. Accepts patterns like --filter'
+                        --focus [CRATE] ... 'Only display the given crate, the deps that depend \
+                        on it and the deps it depends on. Accepts patterns like --filter'
+                        --invert [CRATE] ... 'Show the reverse dependencies of the given crate, \
+                        with the edges pointing from each crate to the crates that depend on it'
+                        --duplicates 'Only display deps that are in several versions, and the \
+                        deps that pull in each version'
+                        --merge-versions 'Show all versions of a crate as a single node, labeled \
+                        with every version'
+                        --transitive-reduction 'Leave out the edges that are implied by longer \
+                        paths, which declutters dense graphs'
                     -I, --include-versions 'Include the dependency version on nodes'
-                        --subgraph [DEPNAMES] ... 'Group provided deps in their own subgraph'
+                        --generate-lockfile 'Run cargo generate-lockfile if there is no \
+                        Cargo.lock'
+                        --highlight-patched 'Fill the nodes of crates overridden by [patch] or \
+                        [replace] sections'
+                        --highlight-proc-macros 'Fill the nodes of proc-macro crates'
+                        --highlight-duplicates 'Fill the nodes of crates that are in the graph \
+                        in several versions'
+                        --duplicate-fill [COLOR] 'Fill color of --highlight-duplicates \
+                        [default: orange]. Implies --highlight-duplicates'
+                        --badges 'Put markers for the kind of dependency and for proc-macro \
+                        crates in front of the labels, for output without colors'
+                        --highlight [CRATE]... 'Draw the given crates in a bold color, given \
+                        after a colon like serde:blue [default: red]. Accepts patterns like \
+                        --filter'
+                        --highlight-edges 'Also draw the edges of the crates of --highlight in \
+                        their color'
+                        --edge-version-reqs 'Label the edges with the version requirements that \
+                        the deps are declared with'
+                        --flag-loose-reqs 'Highlight the edges of deps declared with a wildcard \
+                        requirement like * or 0.*, and list them on stderr'
+                        --only-git 'Only display git deps, and the deps that pull them in'
+                        --only-path 'Only display path deps, and the deps that pull them in'
+                        --only-registry 'Only display registry deps, and the deps that pull them \
+                        in'
+                        --only-proc-macros 'Only display proc-macro crates, and the deps that \
+                        pull them in'
+                        --html-labels 'Show the name, version, kind and license (if the crate is \
+                        in the local registry cache) of each node on separate rows'
+                        --license-summary 'Print the licenses of the shown crates with the \
+                        number of crates under each on stderr'
+                        --allow-license [LICENSE]... 'Only allow crates under the given SPDX \
+                        licenses, like MIT. Crates that violate the policy are drawn in red, and \
+                        the exit code is non-zero'
+                        --deny-license [LICENSE]... 'Reject crates under the given SPDX licenses, \
+                        like GPL-3.0'
+                        --advisories 'Fill the nodes of crates with RustSec security advisories \
+                        and label them with the advisory ids. The advisory database is cloned \
+                        into ~/.cargo/advisory-db, or updated there, with git'
+                        --advisory-db [PATH] 'Read the advisories from the given checkout of the \
+                        advisory database, as it is. Implies --advisories'
+                        --audit 'Exit with a non-zero code if a shown crate has a security \
+                        advisory. Implies --advisories'
+                        --outdated 'Fill the nodes of crates from crates.io that have a newer \
+                        version, and show the newest version in their labels'
+                        --msrv 'Show the rust-version of the crates in their labels'
+                        --check-msrv [VERSION] 'Draw the crates whose rust-version is newer than \
+                        the given Rust version, like 1.70, in a bold color and list them'
+                        --yanked 'Fill the nodes of crates whose version is yanked from crates.io'
+                        --deny-yanked 'Exit with a non-zero code if a shown crate is yanked. \
+                        Implies --yanked'
+                        --index-path [PATH] 'Read the versions of crates from a local clone of \
+                        the crates.io index instead of the sparse index'
+                        --stats 'Print the number of crates and edges, the depth, the crates with \
+                        several versions, the largest subtrees and the most depended upon crates \
+                        on stderr'
+                        --stats-file [PATH] 'Write the metrics of --stats to a file as JSON'
+                        --critical-path 'Draw the longest chain of dependencies in bold and \
+                        print it on stderr. It bounds how parallel a clean build can be'
+                        --crate-sizes 'Show the sizes of the crates from the local registry \
+                        cache or crates.io in their labels, and fill the nodes the bluer the \
+                        larger the crate is'
+                        --downloads 'Show how often the crates from crates.io have been \
+                        downloaded in their tooltips, asking crates.io with curl'
+                        --transitive-counts 'Show the number of crates that each crate depends \
+                        on, directly or not, in its label, like serde [+3]'
+                        --show-source 'Show where each crate comes from on its node: a path, \
+                        crates.io, another registry or a git repository and revision'
+                        --show-registry 'Show the registry of crates that do not come from \
+                        crates.io'
+                        --ascii 'Use ASCII instead of Unicode line drawing in tree output'
+                        --subgraph [DEPNAMES] ... 'Group provided deps in their own subgraph. \
+                        Pass it several times for several subgraphs. Accepts patterns like \
+                        --filter'
+                        --font [NAME] 'Font of the labels, as a font name or family that graphviz \
+                        can find, like \"Noto Sans CJK JP\"'
+                        --font-size [POINTS] 'Size of the labels [default: 14]'
+                        --tooltips 'Show the description of each crate when hovering its node \
+                        in SVG output'
+                        --root-shape [SHAPE] 'Node shape of the root crates, as a graphviz shape \
+                        name [default: box]'
+                        --root-color [COLOR] 'Color of the root crates [default: the color of \
+                        regular dependencies]'
+                        --root-fill [COLOR] 'Fill the nodes of the root crates with a color'
+                        --root-double-border 'Draw the nodes of the root crates with a double \
+                        border'
+                        --legend 'Add a legend that explains the colors, shapes and edge styles \
+                        of the graph'
+                        --rank-by-depth 'Lay out the graph in rings, with the crates at the same \
+                        distance from the root crates on the same rank'
+                        --no-member-clusters 'Don't group each workspace member and its path \
+                        dependencies in its own cluster'
+                        --workspace-member [NAMES] ... 'Only graph the given workspace members \
+                        and their dependencies'
 
                         --all-deps 'Include all dependencies in the graph. \
                         Can be used with --no-regular-deps'
                         --no-regular-deps 'Exclude regular dependencies from the graph'
                         --build-deps 'Include build dependencies in the graph (purple)'
                         --dev-deps 'Include dev dependencies in the graph (blue)'
+                        --build-deps-for [CRATE]... 'Include the build dependencies of the given \
+                        workspace members only'
+                        --dev-deps-for [CRATE]... 'Include the dev dependencies of the given \
+                        workspace members only'
+                        --all-features 'Activate all features of the root crates'
+                        --no-default-features 'Do not activate the default feature of the root \
+                        crates'
                         --optional-deps 'Include optional dependencies in the graph (red)'
+                        --optional-deps-for [CRATE]... 'Include the optional dependencies of the \
+                        given workspace members only'
+                        --color-regular [COLOR] 'Color of regular dependencies, as a graphviz \
+                        color name or like #aa00ff [default: from the theme]'
+                        --color-build [COLOR] 'Color of build dependencies [default: from the \
+                        theme]'
+                        --color-dev [COLOR] 'Color of dev dependencies [default: from the theme]'
+                        --color-optional [COLOR] 'Color of optional dependencies [default: from \
+                        the theme]'
+                        --color-orphan [COLOR] 'Color of orphan dependencies [default: from the \
+                        theme]'
+                        --shape-regular [SHAPE] 'Node shape of regular dependencies, as a \
+                        graphviz shape name like diamond [default: ellipse]'
+                        --shape-build [SHAPE] 'Node shape of build dependencies [default: ellipse]'
+                        --shape-dev [SHAPE] 'Node shape of dev dependencies [default: ellipse]'
+                        --shape-optional [SHAPE] 'Node shape of optional dependencies [default: \
+                        ellipse]'
+                        --shape-orphan [SHAPE] 'Node shape of orphan dependencies [default: \
+                        ellipse]'
+                        --edge-style-regular [STYLE] 'Style of the edges of regular dependencies, \
+                        as a graphviz style like bold [default: solid]'
+                        --edge-style-build [STYLE] 'Style of the edges of build dependencies \
+                        [default: dotted]'
+                        --edge-style-dev [STYLE] 'Style of the edges of dev dependencies \
+                        [default: dashed]'
+                        --edge-style-optional [STYLE] 'Style of the edges of optional dependencies \
+                        [default: dashed]'
+                        --edge-style-orphan [STYLE] 'Style of the edges of orphan dependencies \
+                        [default: dashed]'
                     ",
                 )
                 .args(&[
-                    Arg::from_usage("--manifest-path [PATH] 'Specify location of manifest file'")
-                        .default_value("Cargo.toml"),
-                    Arg::from_usage("--subgraph-name [NAME] 'Optional name of subgraph'")
-                        .requires("subgraph"),
+                    Arg::from_usage(
+                        "--manifest-path [PATH]... 'Specify location of manifest file, or - to \
+                         read it from stdin. Pass several times to merge several projects into \
+                         one graph'",
+                    )
+                    .number_of_values(1)
+                    .default_value("Cargo.toml"),
+                    Arg::from_usage(
+                        "--lockfile-path [PATH] 'Specify location of lock file [default: next to \
+                         the manifest file or in a parent directory]'",
+                    )
+                    .conflicts_with("lock-only"),
+                    Arg::from_usage(
+                        "--depth [N] 'Only show dependencies up to N edges away from the root \
+                         crates'",
+                    ),
+                    Arg::from_usage(
+                        "--features [FEATURES] ... 'Features of the root crates to activate. Only \
+                         the optional deps that the features enable are shown as regular deps'",
+                    )
+                    .number_of_values(1),
+                    Arg::from_usage(
+                        "--target [TRIPLE] 'Only show the dependencies that apply to the given \
+                         target, e.g. thumbv7em-none-eabihf [default: all targets]'",
+                    ),
+                    Arg::from_usage(
+                        "--include-orphans [CRATE]... 'Don't purge orphan nodes (yellow), or only \
+                         the given ones. This is useful in some workspaces'",
+                    )
+                    .min_values(0),
+                    Arg::from_usage(
+                        "--max-nodes [N] 'Leave out the crates furthest from the root crates \
+                         until at most N are shown, listing them on stderr'",
+                    ),
+                    Arg::from_usage(
+                        "--heaviest [N] 'List the N direct dependencies that pull in the most \
+                         crates that nothing else pulls in on stderr, i.e. the ones to cut for the \
+                         biggest reduction of the graph'",
+                    ),
+                    Arg::from_usage(
+                        "--highlight-heaviest 'Draw the dependencies of --heaviest in a bold \
+                         color'",
+                    )
+                    .requires("heaviest"),
+                    Arg::from_usage(
+                        "--subgraph-name [NAME]... 'Optional name of subgraph. Pass it once per \
+                         --subgraph to name several subgraphs, in the same order'",
+                    )
+                    .number_of_values(1)
+                    .requires("subgraph"),
+                    Arg::from_usage(
+                        "--subgraph-color [COLOR]... 'Color of the box of the subgraph [default: \
+                         brown]. Pass it once per --subgraph like --subgraph-name'",
+                    )
+                    .number_of_values(1)
+                    .requires("subgraph"),
+                    Arg::from_usage(
+                        "--why [CRATE] ... 'Only display the dependency paths that lead from the \
+                         root crates to the given crate. Accepts patterns like --filter'",
+                    ),
+                    Arg::from_usage(
+                        "--from [CRATE] ... 'Start the --why paths at the given crates instead \
+                         of the root crates'",
+                    )
+                    .requires("why"),
+                    Arg::from_usage(
+                        "--format [FORMAT] 'Output format [default: guessed from the output \
+                         file extension, otherwise dot]. Image formats require graphviz'",
+                    )
+                    .possible_values(Format::NAMES),
+                    Arg::from_usage(
+                        "--backend [BACKEND] 'How to resolve the dependencies: parse Cargo.lock \
+                         directly, or run cargo metadata [default: lockfile]'",
+                    )
+                    .possible_values(Backend::NAMES),
+                    Arg::from_usage(
+                        "--theme [THEME] 'Color scheme of the graph, including the background \
+                         [default: light]'",
+                    )
+                    .possible_values(Theme::NAMES),
+                    Arg::from_usage(
+                        "--template [PATH] 'Render the graph with a Handlebars template instead \
+                         of an output format'",
+                    )
+                    .conflicts_with("format"),
+                    Arg::from_usage(
+                        "--label-format [FORMAT] 'Label the nodes with the given format, e.g. \
+                         \"{name}\\n{version} ({kind})\". The placeholders are {name}, {version}, \
+                         {kind}, {source}, {license}, {registry}, {advisories}, {latest}, {msrv}, \
+                         {transitive}, {build_time} and {size}'",
+                    )
+                    .conflicts_with("html-labels"),
+                    Arg::from_usage(
+                        "--link [TARGET] 'Link each node to the docs.rs or crates.io page of the \
+                         crate, or to its repository, which makes SVG output clickable'",
+                    )
+                    .possible_values(Link::NAMES),
+                    Arg::from_usage(
+                        "--rankdir [DIR] 'Direction of the layout: top to bottom, left to right, \
+                         bottom to top or right to left [default: TB]'",
+                    )
+                    .possible_values(&["TB", "LR", "BT", "RL"]),
+                    Arg::from_usage(
+                        "--rank-by-layer 'Put the crates in the same topological layer on the same \
+                         rank, which follows the order in which they are built'",
+                    )
+                    .conflicts_with("rank-by-depth"),
+                    Arg::from_usage(
+                        "--layout [ENGINE] 'Graphviz layout engine. neato, fdp and sfdp lay out \
+                         large graphs more compactly than dot, without ranks [default: dot]'",
+                    )
+                    .possible_values(&["dot", "neato", "fdp", "sfdp", "circo", "twopi", "osage"]),
+                    Arg::from_usage(
+                        "--splines [SPLINES] 'How edges are drawn [default: spline]'",
+                    )
+                    .possible_values(&["spline", "ortho", "polyline", "curved", "line", "none"]),
+                    Arg::from_usage(
+                        "--overlap [MODE] 'How the layout engines other than dot remove node \
+                         overlaps, like false, scale or prism [default: true]'",
+                    ),
+                    Arg::from_usage(
+                        "--graph-attr [KEY=VALUE]... 'Set a graphviz attribute of the graph, like \
+                         splines=ortho'",
+                    )
+                    .number_of_values(1),
+                    Arg::from_usage(
+                        "--node-attr [KEY=VALUE]... 'Set a default graphviz attribute of the \
+                         nodes, like fontname=Helvetica'",
+                    )
+                    .number_of_values(1),
+                    Arg::from_usage(
+                        "--edge-attr [KEY=VALUE]... 'Set a default graphviz attribute of the \
+                         edges, like arrowsize=0.5'",
+                    )
+                    .number_of_values(1),
+                    Arg::from_usage(
+                        "--cluster-by [GROUPING] 'Group the crates in labeled clusters by where \
+                         they come from: path, crates.io, another registry or git. This replaces \
+                         the workspace member clusters'",
+                    )
+                    .possible_values(ClusterBy::NAMES),
+                    Arg::from_usage(
+                        "--size-by [MEASURE] 'Scale the nodes by how many crates depend on them \
+                         (fan-in), how many crates they depend on (fan-out), how many crates \
+                         they pull in transitively (subtree), their compile time from \
+                         --timings (build-time) or their size, unpacked or downloaded (bytes)'",
+                    )
+                    .possible_values(SizeBy::NAMES),
+                    Arg::from_usage(
+                        "--timings [PATH] 'Read the compile times of the crates from the output \
+                         of cargo build --timings=json, fill the nodes the redder the longer the \
+                         crate takes to compile and show the time in the labels'",
+                    ),
+                    Arg::from_usage(
+                        "--obscure-below [DOWNLOADS] 'Draw the crates from crates.io that have \
+                         been downloaded fewer times than DOWNLOADS in a bold color and list them \
+                         on stderr, as candidates for a closer review'",
+                    ),
+                    Arg::from_usage(
+                        "--styles [PATH] 'Read the colors, shapes and clusters of the crates \
+                         matching patterns from the [[style]] tables of a TOML file'",
+                    ),
+                    Arg::from_usage(
+                        "--no-manifest-options 'Ignore the default options in the \
+                         [package.metadata.deps] table of the manifest'",
+                    ),
+                    Arg::from_usage(
+                        "--lock-only [PATH] 'Graph a Cargo.lock on its own, without its \
+                         Cargo.toml. All dependencies are treated as regular dependencies'",
+                    )
+                    .conflicts_with("backend"),
+                    Arg::with_name("diff")
+                        .long("diff")
+                        .value_names(&["OLD", "NEW"])
+                        .help(
+                            "Graph the changes from one Cargo.lock to another, both read on their \
+                             own like with --lock-only. Added crates are filled in green, removed \
+                             ones in red and the ones whose version changed in yellow",
+                        )
+                        .conflicts_with_all(&["backend", "lock-only"]),
+                    Arg::from_usage(
+                        "--diff-rev [REV] 'Graph the changes to the Cargo.lock of the project \
+                         since a git revision, like HEAD~1, the same way as --diff'",
+                    )
+                    .conflicts_with_all(&["backend", "lock-only", "diff"]),
+                    Arg::from_usage(
+                        "--crate [NAME@VERSION] 'Graph a crate from crates.io instead of the local \
+                         project. The version may be any version requirement [default: latest]'",
+                    )
+                    .conflicts_with_all(&["lock-only", "diff", "diff-rev"]),
+                    Arg::from_usage(
+                        "--git [URL] 'Graph the project in a git repository. --manifest-path is \
+                         relative to the root of the repository'",
+                    )
+                    .conflicts_with_all(&["lock-only", "crate", "diff", "diff-rev"]),
+                    Arg::from_usage(
+                        "--watch 'Write the output file again whenever Cargo.toml, Cargo.lock or \
+                         the --styles file change, until interrupted'",
+                    )
+                    .requires("dot-file")
+                    .conflicts_with_all(&["crate", "git", "diff-rev"]),
+                    Arg::from_usage("--branch [BRANCH] 'Branch or tag of the git repository'")
+                        .requires("git"),
+                    Arg::from_usage("--rev [REV] 'Commit of the git repository'")
+                        .requires("git")
+                        .conflicts_with("branch"),
                 ]),
         )
-        .get_matches()
+}
+
+/// Parses the command line, followed by the options of `[package.metadata.deps]` in the manifest
+/// that are not given on the command line.
+fn parse_cli<'a>() -> CliResult<ArgMatches<'a>> {
+    let mut args: Vec<OsString> = env::args_os().collect();
+    let m = app().get_matches_from(&args);
+
+    let manifest_args = match m.subcommand_matches("deps") {
+        Some(m) => config::manifest_args(m)?,
+        None => None,
+    };
+    match manifest_args {
+        Some((manifest_path, manifest_args)) if !manifest_args.is_empty() => {
+            args.extend(manifest_args);
+            app().get_matches_from_safe(&args).map_err(|e| {
+                CliError::Generic(format!(
+                    "Invalid options in [package.metadata.deps] of {}: {}",
+                    manifest_path.display(),
+                    e.message
+                        .lines()
+                        .next()
+                        .unwrap_or("")
+                        .trim_start_matches("error: ")
+                ))
+            })
+        }
+        _ => Ok(m),
+    }
 }
 
 fn main() {
-    let m = parse_cli();
+    let m = parse_cli().unwrap_or_else(|e| e.exit());
 
     if let Some(m) = m.subcommand_matches("deps") {
-        let cfg = Config::from_matches(&m).unwrap_or_else(|e| e.exit());
-        execute(cfg).map_err(|e| e.exit()).unwrap();
+        let cfg = Config::from_matches(m).unwrap_or_else(|e| e.exit());
+        if cfg.watch {
+            watch(m).map_err(|e| e.exit()).unwrap();
+        } else {
+            execute(cfg).map_err(|e| e.exit()).unwrap();
+        }
     }
 }
 
 fn execute(cfg: Config) -> CliResult<()> {
-    // Search through parent dirs for Cargo.toml.
-    is_cargo_toml(&cfg.manifest_path)?;
-    let manifest_path = util::find_manifest_file(&cfg.manifest_path)?;
-
-    // Cargo.lock must be in the same directory as Cargo.toml or in a parent directory.
-    let manifest = manifest_path.to_str().unwrap();
-    let lock_file = format!("{}.lock", &manifest[0..manifest.len() - 5]);
-    let lock_path = util::find_manifest_file(&lock_file)?;
+    let dot_file = cfg.dot_file.clone();
+    let format = cfg.format;
+    if cfg.lockfile_path.is_some() && cfg.manifest_paths.len() > 1 {
+        return Err(CliError::Generic(
+            "--lockfile-path cannot be used with several manifests".into(),
+        ));
+    }
 
     // Graph the project.
-    let dot_file = cfg.dot_file.clone();
-    let project = Project::with_config(cfg)?;
-    let graph = project.graph(manifest_path, lock_path)?;
+    let graph = if let Some(lock_path) = cfg.lock_only.clone() {
+        Project::with_config(cfg)?.lock_graph(PathBuf::from(lock_path))?
+    } else if let Some((old_path, new_path)) = cfg.diff.clone() {
+        Project::with_config(cfg)?.diff_graph(PathBuf::from(old_path), PathBuf::from(new_path))?
+    } else if let Some(rev) = cfg.diff_rev.clone() {
+        if cfg.manifest_paths.len() > 1 {
+            return Err(CliError::Generic(
+                "--diff-rev cannot be used with several manifests".into(),
+            ));
+        }
+        let lock_path = lock_file_path(&cfg, &cfg.manifest_paths[0])?;
+
+        // The old lock file is written to a temporary directory that is removed once it has been
+        // graphed.
+        let tmp_dir = TempDir::new("rev")?;
+        let old_path = tmp_dir.path().join("Cargo.lock");
+        fetch::git_show(&rev, &lock_path, &old_path)?;
+
+        Project::with_config(cfg)?.diff_graph(old_path, lock_path)?
+    } else if let Some(spec) = cfg.crate_spec.clone() {
+        // The crate is locked in a temporary directory that is removed once it has been graphed.
+        let tmp_dir = TempDir::new("crate")?;
+        let manifest_path = fetch::fetch_crate(&spec, tmp_dir.path(), cfg.dev_deps)?;
+
+        Project::with_config(cfg)?.graph(vec![manifest_path])?
+    } else if let Some(url) = cfg.git.clone() {
+        // The repository is fetched into a temporary directory that is removed once it has been
+        // graphed.
+        let tmp_dir = TempDir::new("git")?;
+        fetch::fetch_git(&url, cfg.git_ref.as_deref(), tmp_dir.path())?;
+
+        let mut manifest_paths = vec![];
+        for manifest in &cfg.manifest_paths {
+            is_cargo_toml(manifest)?;
+            let manifest_path = tmp_dir.path().join(manifest);
+            if !manifest_path.is_file() {
+                return Err(CliError::Generic(format!(
+                    "Could not find {:?} in the git repository",
+                    manifest
+                )));
+            }
+            manifest_paths.push(manifest_path);
+        }
+
+        Project::with_config(cfg)?.graph(manifest_paths)?
+    } else if cfg.manifest_paths.iter().any(|manifest| manifest == "-") {
+        if cfg.manifest_paths.len() > 1 {
+            return Err(CliError::Generic(
+                "The manifest read from stdin cannot be merged with other manifests".into(),
+            ));
+        }
+        if cfg.lockfile_path.is_none() {
+            return Err(CliError::Generic(
+                "--lockfile-path is required when reading the manifest from stdin".into(),
+            ));
+        }
+        if cfg.backend == Backend::Metadata {
+            return Err(CliError::Generic(
+                "The metadata backend cannot read the manifest from stdin".into(),
+            ));
+        }
+
+        let mut manifest = String::new();
+        io::stdin().read_to_string(&mut manifest)?;
+        let manifest_toml = toml::from_str(&manifest)?;
+
+        // Paths in the manifest, e.g. of workspace members, are relative to the current directory.
+        let manifest_path = env::current_dir()?.join("Cargo.toml");
+
+        Project::with_config(cfg)?.graph_manifests(vec![(manifest_path, manifest_toml)])?
+    } else {
+        // Search through parent dirs for Cargo.toml.
+        let mut manifest_paths = vec![];
+        for manifest in &cfg.manifest_paths {
+            is_cargo_toml(manifest)?;
+            manifest_paths.push(util::find_manifest_file(manifest)?);
+        }
+
+        Project::with_config(cfg)?.graph(manifest_paths)?
+    };
+
+    // Render the graph. This is done in memory first so that a failing graphviz run does not
+    // leave an empty output file behind.
+    let violations = (0..graph.nodes.len())
+        .filter(|&id| graph.nodes[id].license_violation && graph.is_shown(id))
+        .count();
+    let vulnerable = (0..graph.nodes.len())
+        .filter(|&id| !graph.nodes[id].advisories.is_empty() && graph.is_shown(id))
+        .count();
+    let yanked = (0..graph.nodes.len())
+        .filter(|&id| graph.nodes[id].yanked && graph.is_shown(id))
+        .count();
+    let audit = graph.cfg.audit;
+    let deny_yanked = graph.cfg.deny_yanked;
+    let mut output = vec![];
+    render::render(graph, format, &mut output)?;
 
-    // Render the dot file.
     match dot_file {
         None => {
             let o = io::stdout();
             let mut bw = BufWriter::new(o.lock());
-            graph.render_to(&mut bw)
+            bw.write_all(&output)?;
         }
         Some(file) => {
-            let o = File::create(&Path::new(&file)).expect("Failed to create file");
+            let o = File::create(Path::new(&file))?;
             let mut bw = BufWriter::new(o);
-            graph.render_to(&mut bw)
+            bw.write_all(&output)?;
+        }
+    }
+
+    // The graph is still written, so that it shows where the violations come from.
+    let mut failures = vec![];
+    if violations > 0 {
+        failures.push(format!("{} crates violate the license policy", violations));
+    }
+    if audit && vulnerable > 0 {
+        failures.push(format!("{} crates have security advisories", vulnerable));
+    }
+    if deny_yanked && yanked > 0 {
+        failures.push(format!("{} crates are yanked", yanked));
+    }
+    if !failures.is_empty() {
+        return Err(CliError::Generic(failures.join(" and ")));
+    }
+
+    Ok(())
+}
+
+/// The lock file of a manifest: the one given with `--lockfile-path`, or Cargo.lock next to the
+/// manifest or in a parent directory.
+fn lock_file_path(cfg: &Config, manifest: &str) -> CliResult<PathBuf> {
+    match &cfg.lockfile_path {
+        Some(lock_path) => Ok(PathBuf::from(lock_path)),
+        None => {
+            is_cargo_toml(manifest)?;
+            util::find_manifest_file(&format!("{}.lock", &manifest[..manifest.len() - 5]))
+        }
+    }
+}
+
+/// Writes the output again whenever the manifests, the lock files or the styles file change, until
+/// the process is interrupted. The files are polled, as there is no portable way to be notified of
+/// changes. Errors are reported without stopping, as the next change often fixes them.
+fn watch(m: &ArgMatches) -> CliResult<()> {
+    let cfg = Config::from_matches(m)?;
+    let output = cfg.dot_file.clone().unwrap_or_default();
+    let modified = |files: &[PathBuf]| -> Vec<Option<SystemTime>> {
+        files
+            .iter()
+            .map(|file| fs::metadata(file).and_then(|m| m.modified()).ok())
+            .collect()
+    };
+
+    let mut files = watched_files(m, &cfg)?;
+    let mut last_modified = None;
+    loop {
+        // Changes made while the graph is written are picked up by the next round.
+        if last_modified.as_ref() != Some(&modified(&files)) {
+            if last_modified.is_some() {
+                eprintln!("Change detected, writing {} again.", output);
+            }
+            // The styles are read again too.
+            match Config::from_matches(m).and_then(execute) {
+                Ok(()) => eprintln!("Wrote {}, watching for changes.", output),
+                Err(e) => eprintln!("error: {}", e),
+            }
+            // Members and path dependencies may have been added or removed.
+            if let Ok(new_files) = watched_files(m, &cfg) {
+                files = new_files;
+            }
+            last_modified = Some(modified(&files));
+        }
+
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
+/// The files that `--watch` polls: the manifests with those of their workspace members and path
+/// dependencies, the lock files and the styles file.
+fn watched_files(m: &ArgMatches, cfg: &Config) -> CliResult<Vec<PathBuf>> {
+    let mut files = vec![];
+    if let Some(lock_path) = &cfg.lock_only {
+        files.push(PathBuf::from(lock_path));
+    } else if let Some((old_path, new_path)) = &cfg.diff {
+        files.push(PathBuf::from(old_path));
+        files.push(PathBuf::from(new_path));
+    } else {
+        for manifest in cfg
+            .manifest_paths
+            .iter()
+            .filter(|&manifest| manifest != "-")
+        {
+            is_cargo_toml(manifest)?;
+            let manifest_path = util::find_manifest_file(manifest)?;
+            // A manifest that cannot be read is watched on its own until it is fixed.
+            let manifests = util::toml_from_file(&manifest_path).and_then(|manifest_toml| {
+                let project = Project::with_config(cfg.clone())?;
+                Ok(project
+                    .parse_root_deps(&manifest_path, &manifest_toml)?
+                    .manifests)
+            });
+            match manifests {
+                Ok(manifests) => files.extend(manifests),
+                Err(_) => files.push(manifest_path),
+            }
+            // The lock file may only be generated by the first run.
+            if let Ok(lock_path) = lock_file_path(cfg, manifest) {
+                files.push(lock_path);
+            }
         }
     }
+    if let Some(styles) = m.value_of("styles") {
+        files.push(PathBuf::from(styles));
+    }
+
+    Ok(files)
 }
 
 // Check that the manifest file name is "Cargo.toml".
@@ -0,0 +1,215 @@
+use crate::config::Config;
+use crate::dep::DepKind;
+use crate::error::{CliError, CliResult};
+use crate::graph::{DepGraph, Edge};
+use crate::project::{self, DepKindsMap};
+use crate::util;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// Builds the graph from the output of `cargo metadata`, which resolves workspaces, renames,
+/// features and target-specific dependencies the same way cargo does.
+pub fn parse(cfg: &Config, manifest_path: &Path) -> CliResult<DepGraph> {
+    let mut args: Vec<&OsStr> = ["metadata", "--format-version", "1", "--manifest-path"]
+        .iter()
+        .map(OsStr::new)
+        .collect();
+    args.push(manifest_path.as_os_str());
+    if let Some(target) = &cfg.target {
+        args.push(OsStr::new("--filter-platform"));
+        args.push(OsStr::new(target));
+    }
+    // Optional dependencies are only resolved if their features are enabled. With feature options,
+    // cargo works out which of them are.
+    let feature_request = cfg.feature_request();
+    let features = cfg.features.join(",");
+    if feature_request.is_some() {
+        if !features.is_empty() {
+            args.push(OsStr::new("--features"));
+            args.push(OsStr::new(&features));
+        }
+        if cfg.all_features {
+            args.push(OsStr::new("--all-features"));
+        }
+        if cfg.no_default_features {
+            args.push(OsStr::new("--no-default-features"));
+        }
+    } else if cfg.optional_deps || !cfg.optional_deps_for.is_empty() {
+        args.push(OsStr::new("--all-features"));
+    }
+
+    let metadata: Value = serde_json::from_slice(&util::run_cargo(args)?)?;
+    let invalid = || CliError::Generic("Unexpected output from cargo metadata".into());
+
+    // Index the packages by id.
+    let mut packages = HashMap::new();
+    for pkg in metadata["packages"].as_array().ok_or_else(invalid)? {
+        packages.insert(pkg["id"].as_str().ok_or_else(invalid)?, pkg);
+    }
+
+    let mut dg = DepGraph::new(cfg.clone());
+
+    // The kinds selected for a root crate include the ones selected for it alone.
+    let declared_deps = |pkg: &Value, root: Option<&str>| -> CliResult<DepKindsMap> {
+        let includes = |kind| cfg.includes_kind(kind, root);
+        let mut dep_kinds_map = DepKindsMap::new();
+        for dep in pkg["dependencies"].as_array().ok_or_else(invalid)? {
+            let dep_name = dep["name"].as_str().ok_or_else(invalid)?.to_string();
+            let kind = match (dep["kind"].as_str(), dep["optional"].as_bool()) {
+                (Some("build"), _) if includes(DepKind::Build) => DepKind::Build,
+                (Some("dev"), _) if includes(DepKind::Dev) => DepKind::Dev,
+                // Optional dependencies in the resolve graph are enabled by the requested features.
+                (None, Some(true)) if feature_request.is_some() && cfg.regular_deps => {
+                    DepKind::Regular
+                }
+                (None, Some(true)) if includes(DepKind::Optional) => DepKind::Optional,
+                (None, Some(false)) if cfg.regular_deps => DepKind::Regular,
+                _ => continue,
+            };
+            project::add_kind(&mut dep_kinds_map, dep_name, kind);
+        }
+        Ok(dep_kinds_map)
+    };
+
+    // The workspace members are the root crates.
+    for id in metadata["workspace_members"]
+        .as_array()
+        .ok_or_else(invalid)?
+    {
+        let pkg = id
+            .as_str()
+            .and_then(|id| packages.get(id))
+            .ok_or_else(invalid)?;
+        let name = pkg["name"].as_str().ok_or_else(invalid)?;
+
+        if let Some(members) = &cfg.workspace_members {
+            if !members.iter().any(|member| member == name) {
+                continue;
+            }
+        }
+
+        dg.root_deps_map
+            .insert(name.to_string(), declared_deps(pkg, Some(name))?);
+    }
+    for member in cfg.per_member_kinds() {
+        if !dg.root_deps_map.contains_key(member) {
+            return Err(CliError::Generic(format!(
+                "'{}' is not a selected member of the workspace",
+                member
+            )));
+        }
+    }
+
+    // Any other package without a source is a path dependency.
+    for pkg in packages.values() {
+        let name = pkg["name"].as_str().ok_or_else(invalid)?;
+        if pkg["source"].is_null() && !dg.root_deps_map.contains_key(name) {
+            dg.path_deps_map
+                .insert(name.to_string(), declared_deps(pkg, None)?);
+        }
+    }
+
+    let nodes = metadata["resolve"]["nodes"]
+        .as_array()
+        .ok_or_else(invalid)?;
+    for node in nodes {
+        let pkg = node["id"]
+            .as_str()
+            .and_then(|id| packages.get(id))
+            .ok_or_else(invalid)?;
+        let name = pkg["name"].as_str().ok_or_else(invalid)?.to_string();
+        let ver = pkg["version"].as_str().ok_or_else(invalid)?;
+
+        if let Some(filter_deps) = &cfg.filter {
+            if !util::matches_any(filter_deps, &name) {
+                continue;
+            }
+        }
+
+        let id = dg.find_or_add(&name, ver);
+        dg.nodes[id].source = pkg["source"].as_str().map(|source| source.into());
+        dg.nodes[id].license = pkg["license"]
+            .as_str()
+            .map(|license| license.into())
+            .or_else(|| {
+                let file = pkg["license_file"].as_str()?;
+                Some(format!("see {}", file))
+            });
+        dg.nodes[id].repository = pkg["repository"].as_str().map(|url| url.into());
+        dg.nodes[id].description = pkg["description"].as_str().map(|d| d.into());
+        dg.nodes[id].rust_version = pkg["rust_version"].as_str().map(|r| r.into());
+        dg.nodes[id].proc_macro = pkg["targets"].as_array().is_some_and(|targets| {
+            targets.iter().any(|target| {
+                target["kind"]
+                    .as_array()
+                    .is_some_and(|kinds| kinds.iter().any(|kind| kind == "proc-macro"))
+            })
+        });
+
+        for dep in node["deps"].as_array().ok_or_else(invalid)? {
+            let child = dep["pkg"]
+                .as_str()
+                .and_then(|id| packages.get(id))
+                .ok_or_else(invalid)?;
+            let dep_name = child["name"].as_str().ok_or_else(invalid)?.to_string();
+            let dep_ver = child["version"].as_str().ok_or_else(invalid)?;
+            let mut reqs: Vec<String> = vec![];
+            for declared in pkg["dependencies"].as_array().ok_or_else(invalid)? {
+                if let (Some(name), Some(req)) =
+                    (declared["name"].as_str(), declared["req"].as_str())
+                {
+                    if name == dep_name && !reqs.iter().any(|r| r == req) {
+                        reqs.push(req.to_string());
+                    }
+                }
+            }
+
+            if let Some(filter_deps) = &cfg.filter {
+                if !util::matches_any(filter_deps, &dep_name) {
+                    continue;
+                }
+            }
+
+            if let Some(dep_kinds_map) = dg
+                .root_deps_map
+                .get(&name)
+                .or_else(|| dg.path_deps_map.get(&name))
+            {
+                if dep_kinds_map.get(&dep_name).is_none() {
+                    // This dep was filtered out when adding root dependencies.
+                    continue;
+                }
+                dg.add_child(id, &dep_name, dep_ver);
+                let child = dg.find_or_add(&dep_name, dep_ver);
+                dg.edge_reqs.insert(Edge(id, child), reqs);
+                continue;
+            }
+
+            // The edges out of registry crates are classified by the kinds cargo reports for them.
+            let mut kinds = vec![];
+            for dep_kind in dep["dep_kinds"].as_array().ok_or_else(invalid)? {
+                let kind = match dep_kind["kind"].as_str() {
+                    Some("build") if cfg.build_deps => DepKind::Build,
+                    Some("dev") if cfg.dev_deps => DepKind::Dev,
+                    None if cfg.regular_deps => DepKind::Regular,
+                    _ => continue,
+                };
+                if !kinds.contains(&kind) {
+                    kinds.push(kind);
+                }
+            }
+            if kinds.is_empty() {
+                continue;
+            }
+
+            dg.add_child(id, &dep_name, dep_ver);
+            let child = dg.find_or_add(&dep_name, dep_ver);
+            dg.edge_kinds.insert(Edge(id, child), kinds);
+            dg.edge_reqs.insert(Edge(id, child), reqs);
+        }
+    }
+
+    Ok(dg)
+}
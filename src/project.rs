@@ -1,160 +1,1274 @@
-use crate::config::Config;
-use crate::dep::{DepKind, RootCrate};
+use crate::advisory::{self, Advisory};
+use crate::config::{Backend, Config, Link};
+use crate::dep::{self, DepKind, DiffStatus, ResolvedDep, RootCrate};
 use crate::error::{CliError, CliResult};
-use crate::graph::DepGraph;
+use crate::features::{self, EnabledFeatures, FeatureRequest};
+use crate::graph::{DepGraph, Edge, Node, Stats};
+use crate::metadata;
+use crate::registry;
+use crate::target::{self, Target};
+use crate::timings;
 use crate::util;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
 use toml::Value;
 
+/// The latest lock file format version that is known to be parsed correctly.
+const MAX_LOCK_VERSION: i64 = 4;
+
 // Map of dep names to their kinds.
 pub type DepKindsMap = HashMap<String, Vec<DepKind>>;
 // Map of root names to dep kinds maps.
 pub type RootDepsMap = HashMap<String, DepKindsMap>;
+// Map of dep names to the version requirements they are declared with.
+pub type DepReqsMap = HashMap<String, Vec<String>>;
+
+/// What the manifests declare about the dependencies of the root crates.
+#[derive(Debug, Default)]
+pub struct ManifestDeps {
+    pub root_crates: Vec<RootCrate>,
+    pub root_deps_map: RootDepsMap,
+    /// The declared dependencies of path dependencies that are not root crates.
+    pub path_deps_map: RootDepsMap,
+    /// Map of dep names to the alternate registry they are declared to come from.
+    pub registries: HashMap<String, String>,
+    /// The names of the root crates and path dependencies that are proc-macro crates.
+    pub proc_macros: HashSet<String>,
+    /// The version requirements declared by the root crates and path dependencies, by crate name.
+    pub reqs: HashMap<String, DepReqsMap>,
+    /// The descriptions of the root crates and path dependencies, by crate name.
+    pub descriptions: HashMap<String, String>,
+    /// The licenses of the root crates and path dependencies, by crate name.
+    pub licenses: HashMap<String, String>,
+    /// The `rust-version` of the root crates and path dependencies, by crate name.
+    pub rust_versions: HashMap<String, String>,
+    /// The manifests that were read: the given one, those of the workspace members and those of
+    /// the path dependencies.
+    pub manifests: Vec<PathBuf>,
+}
 
 #[derive(Debug)]
 pub struct Project {
     cfg: Config,
+    /// The target of `--target`. cargo metadata evaluates the platforms itself.
+    target: Option<Target>,
 }
 
 impl Project {
     pub fn with_config(cfg: Config) -> CliResult<Self> {
-        Ok(Project { cfg })
+        let target = match (&cfg.target, cfg.backend) {
+            (Some(triple), Backend::Lockfile) => Some(Target::new(triple)?),
+            _ => None,
+        };
+
+        Ok(Project { cfg, target })
     }
 
-    pub fn graph(self, manifest_path: PathBuf, lock_path: PathBuf) -> CliResult<DepGraph> {
-        let (root_crates, root_deps_map) = self.parse_root_deps(&manifest_path)?;
+    /// Builds the graph of one or more projects. The graphs of several projects are merged into one
+    /// graph with the root crates of all of them.
+    pub fn graph(self, manifest_paths: Vec<PathBuf>) -> CliResult<DepGraph> {
+        let mut manifests = vec![];
+        for manifest_path in manifest_paths {
+            let manifest_toml = util::toml_from_file(&manifest_path)?;
+            manifests.push((manifest_path, manifest_toml));
+        }
+
+        self.graph_manifests(manifests)
+    }
+
+    /// Builds the graph from already parsed manifests. Paths in a manifest are relative to its
+    /// path, which does not need to exist.
+    pub fn graph_manifests(self, manifests: Vec<(PathBuf, Value)>) -> CliResult<DepGraph> {
+        let mut dg = DepGraph::new(self.cfg.clone());
+        let mut vendor_dirs = HashMap::new();
+        for (manifest_path, manifest_toml) in manifests {
+            let (project_dg, project_vendor_dirs) =
+                self.parse_manifest(manifest_path, manifest_toml)?;
+            dg.merge(project_dg);
+            vendor_dirs.extend(project_vendor_dirs);
+        }
+
+        self.finish(dg, &vendor_dirs)
+    }
+
+    /// Builds the unsorted graph of a single project, and returns it with the directories of its
+    /// vendored sources.
+    fn parse_manifest(
+        &self,
+        manifest_path: PathBuf,
+        manifest_toml: Value,
+    ) -> CliResult<(DepGraph, HashMap<String, PathBuf>)> {
+        let (mut dg, registries) = match self.cfg.backend {
+            Backend::Lockfile => {
+                let manifest_deps = self.parse_root_deps(&manifest_path, &manifest_toml)?;
 
-        let mut dg = self.parse_lock_file(lock_path, &dbg!(root_crates), dbg!(root_deps_map))?;
+                // Cargo.lock must be in the same directory as Cargo.toml or in a parent directory,
+                // unless it was given explicitly.
+                let manifest = manifest_path.to_str().unwrap();
+                let lock_file = format!("{}.lock", &manifest[0..manifest.len() - 5]);
+                let lock_path = match self.cfg.lockfile_path.as_ref().map(PathBuf::from) {
+                    Some(lock_path) => Ok(lock_path),
+                    None => util::find_manifest_file(&lock_file),
+                };
+                let lock_path = match lock_path {
+                    Ok(lock_path) => lock_path,
+                    Err(_) if self.cfg.generate_lockfile => {
+                        eprintln!("Generating a lock file for {:?}.", manifest);
+                        util::run_cargo(["generate-lockfile", "--manifest-path", manifest])?;
+                        util::find_manifest_file(&lock_file)?
+                    }
+                    Err(CliError::Generic(e)) => {
+                        return Err(CliError::Generic(format!(
+                            "{}. Pass --generate-lockfile to create one",
+                            e
+                        )))
+                    }
+                    Err(e) => return Err(e),
+                };
+                let lock_toml = util::toml_from_file(lock_path)?;
+
+                let mut dg = self.parse_lock_file(
+                    &lock_toml,
+                    &manifest_deps.root_crates,
+                    manifest_deps.root_deps_map,
+                    manifest_deps.path_deps_map,
+                )?;
+                for dep in dg.nodes.iter_mut().filter(|dep| dep.source.is_none()) {
+                    if manifest_deps.proc_macros.contains(&dep.name) {
+                        dep.proc_macro = true;
+                    }
+                    dep.description = manifest_deps.descriptions.get(&dep.name).cloned();
+                    dep.license = manifest_deps.licenses.get(&dep.name).cloned();
+                    dep.rust_version = manifest_deps.rust_versions.get(&dep.name).cloned();
+                }
+                let reqs = &manifest_deps.reqs;
+                let edge_reqs: Vec<(Edge, Vec<String>)> = dg
+                    .edges
+                    .iter()
+                    .filter_map(|&ed| {
+                        let (parent, child) = (&dg.nodes[ed.0], &dg.nodes[ed.1]);
+                        let reqs = reqs.get(&parent.name)?.get(&child.name)?;
+                        Some((ed, reqs.clone()))
+                    })
+                    .collect();
+                dg.edge_reqs.extend(edge_reqs);
+                (dg, manifest_deps.registries)
+            }
+            Backend::Metadata => (metadata::parse(&self.cfg, &manifest_path)?, HashMap::new()),
+        };
+
+        // Name the registries that the crates come from. The lock file only has the index URL,
+        // which is looked up in the cargo configuration. Failing that, the name the crate is
+        // declared with in a manifest is used, and finally the URL itself. Registries replaced by
+        // another source, e.g. vendored sources, are named after the replacement.
+        let manifest_dir = manifest_path.parent().unwrap();
+        let names = registry::registry_names(manifest_dir);
+        let replacements = registry::source_replacements(manifest_dir);
+        for dep in dg.nodes.iter_mut() {
+            let index = match dep.source.as_ref().and_then(|s| registry::index_url(s)) {
+                Some(index) => index,
+                None => continue,
+            };
+            let name = if source_is_registry(&dep.source, "crates-io") {
+                "crates-io".into()
+            } else if let Some(name) = names.get(&index) {
+                name.clone()
+            } else if let Some(name) = registries.get(&dep.name) {
+                name.clone()
+            } else {
+                index
+            };
+            dep.registry = Some(match replacements.get(&name) {
+                Some(replacement) => replacement.name.clone(),
+                None => name,
+            });
+        }
+
+        // Mark the crates that were patched. The lock file already has the patched source.
+        for patch in parse_patches(&manifest_path, &manifest_toml)? {
+            for dep in dg.nodes.iter_mut() {
+                if dep.name == patch.name && !source_is_registry(&dep.source, &patch.registry) {
+                    dep.patched = true;
+                }
+            }
+        }
 
+        // Vendored crates are looked up in their directory rather than the registry cache.
+        let vendor_dirs: HashMap<String, PathBuf> = replacements
+            .into_values()
+            .filter_map(|replacement| Some((replacement.name, replacement.directory?)))
+            .collect();
+
+        Ok((dg, vendor_dirs))
+    }
+
+    /// Builds the graph from a lock file alone, without its manifest. The root crates are the
+    /// packages that no other package depends on. The lock file does not record the kind of a
+    /// dependency, so all of them are regular dependencies.
+    pub fn lock_graph(self, lock_path: PathBuf) -> CliResult<DepGraph> {
+        let dg = self.lock_file_graph(lock_path)?;
+
+        self.finish(dg, &HashMap::new())
+    }
+
+    /// Builds the graph of the changes from one lock file to another, both read on their own like
+    /// with `lock_graph`. The graph has the crates of both, marked as added, removed or changed in
+    /// version. A crate whose version changed is a single node at its new version. Each version of
+    /// a crate that is only in the old lock file is paired with one that is only in the new one,
+    /// in order, and the rest are added or removed.
+    pub fn diff_graph(self, old_path: PathBuf, new_path: PathBuf) -> CliResult<DepGraph> {
+        let mut old = self.lock_file_graph(old_path)?;
+        let mut dg = self.lock_file_graph(new_path)?;
+
+        let versions = |dg: &DepGraph| {
+            let mut versions: HashMap<String, Vec<String>> = HashMap::new();
+            for dep in &dg.nodes {
+                versions
+                    .entry(dep.name.clone())
+                    .or_default()
+                    .push(dep.ver.clone());
+            }
+            for versions in versions.values_mut() {
+                versions.sort_by_key(|ver| semver::Version::parse(ver).ok());
+            }
+            versions
+        };
+        let old_versions = versions(&old);
+        let new_versions = versions(&dg);
+
+        // Maps the old versions of the crates whose version changed to their new version, and back.
+        let mut renamed: HashMap<(String, String), String> = HashMap::new();
+        let mut previous: HashMap<(String, String), String> = HashMap::new();
+        for (name, old_vers) in &old_versions {
+            let new_vers = match new_versions.get(name) {
+                Some(new_vers) => new_vers,
+                None => continue,
+            };
+            let gone = old_vers.iter().filter(|ver| !new_vers.contains(ver));
+            let came = new_vers.iter().filter(|ver| !old_vers.contains(ver));
+            for (old_ver, new_ver) in gone.zip(came) {
+                renamed.insert((name.clone(), old_ver.clone()), new_ver.clone());
+                previous.insert((name.clone(), new_ver.clone()), old_ver.clone());
+            }
+        }
+        for dep in old.nodes.iter_mut() {
+            if let Some(new_ver) = renamed.get(&(dep.name.clone(), dep.ver.clone())) {
+                dep.ver = new_ver.clone();
+            }
+        }
+
+        dg.merge(old);
+        for dep in dg.nodes.iter_mut() {
+            let key = (dep.name.clone(), dep.ver.clone());
+            let in_old = old_versions
+                .get(&dep.name)
+                .is_some_and(|vers| vers.contains(&dep.ver));
+            let in_new = new_versions
+                .get(&dep.name)
+                .is_some_and(|vers| vers.contains(&dep.ver));
+            dep.diff = if let Some(old_ver) = previous.get(&key) {
+                Some(DiffStatus::Changed(old_ver.clone()))
+            } else if !in_new {
+                Some(DiffStatus::Removed)
+            } else if !in_old {
+                Some(DiffStatus::Added)
+            } else {
+                None
+            };
+        }
+
+        let dg = self.finish(dg, &HashMap::new())?;
+        print_diff(&dg);
+
+        Ok(dg)
+    }
+
+    /// Builds the graph of a lock file alone, before it is sorted.
+    fn lock_file_graph(&self, lock_path: PathBuf) -> CliResult<DepGraph> {
+        let lock_toml = util::toml_from_file(lock_path)?;
+        let packages = lock_packages(&lock_toml);
+
+        let dependencies = |pkg: &Value| -> Vec<String> {
+            match pkg.get("dependencies") {
+                Some(Value::Array(deps)) => deps
+                    .iter()
+                    .map(|dep| parse_dep_string(dep.as_str().unwrap_or("")).0.into())
+                    .collect(),
+                _ => vec![],
+            }
+        };
+        // Dependencies are only named with their version if the crate is in the lock file in
+        // several versions, so a crate is depended on if it is named either way.
+        let depended_on: HashSet<(&str, Option<&str>)> = packages
+            .iter()
+            .filter_map(|pkg| pkg.get("dependencies")?.as_array())
+            .flatten()
+            .filter_map(|dep| dep.as_str())
+            .map(|dep| {
+                let (name, ver, _) = parse_dep_string(dep);
+                (name, ver)
+            })
+            .collect();
+
+        let mut root_crates = vec![];
+        let mut root_deps_map = RootDepsMap::new();
+        for pkg in &packages {
+            if let (Some(name), Some(ver)) = (
+                pkg.get("name").and_then(|n| n.as_str()),
+                pkg.get("version").and_then(|v| v.as_str()),
+            ) {
+                if depended_on.contains(&(name, None)) || depended_on.contains(&(name, Some(ver))) {
+                    continue;
+                }
+                if let Some(members) = &self.cfg.workspace_members {
+                    if !members.iter().any(|member| member == name) {
+                        continue;
+                    }
+                }
+
+                let mut dep_kinds_map = DepKindsMap::new();
+                if self.cfg.regular_deps {
+                    for dep_name in dependencies(pkg) {
+                        add_kind(&mut dep_kinds_map, dep_name, DepKind::Regular);
+                    }
+                }
+
+                let (name, ver) = (name.to_string(), ver.to_string());
+                root_deps_map.insert(name.clone(), dep_kinds_map);
+                root_crates.push(RootCrate { name, ver });
+            }
+        }
+
+        if root_crates.is_empty() {
+            return Err(CliError::Toml("No root crates found in lock file".into()));
+        }
+
+        self.parse_lock_file(&lock_toml, &root_crates, root_deps_map, RootDepsMap::new())
+    }
+
+    /// Sorts the graph and works out the kind of each dependency. `vendor_dirs` maps the names of
+    /// directory sources to their directory.
+    fn finish(
+        &self,
+        mut dg: DepGraph,
+        vendor_dirs: &HashMap<String, PathBuf>,
+    ) -> CliResult<DepGraph> {
         // Sort the graph.
+        dg.sort_nodes();
         dg.topological_sort()?;
 
-        dbg!(&dg);
+        // cargo metadata already reports the kinds of all dependencies.
+        if self.cfg.backend == Backend::Lockfile {
+            self.classify_registry_deps(&mut dg, vendor_dirs);
+        }
+
+        // Excluded crates are removed first, so that they do not affect the kinds of the rest.
+        // Hidden crates are excluded too.
+        let mut exclude = self.cfg.exclude.clone().unwrap_or_default();
+        exclude.extend(self.cfg.hide.iter().cloned());
+        if !exclude.is_empty() {
+            dg.exclude(&exclude);
+        }
+
+        if let Some(collapse) = &self.cfg.collapse {
+            dg.collapse(collapse);
+        }
+
+        if let Some(root) = &self.cfg.root {
+            dg.reroot(root)?;
+        }
 
         // Set the kind of dependency on each dep.
         dg.set_resolved_kind()?;
 
+        // cargo metadata already reports which packages are proc-macro crates.
+        if (self.cfg.highlight_proc_macros || self.cfg.only_proc_macros || self.cfg.badges)
+            && self.cfg.backend == Backend::Lockfile
+        {
+            for dep in dg.nodes.iter_mut().filter(|dep| dep.source.is_some()) {
+                let vendor_dir = dep
+                    .registry
+                    .as_ref()
+                    .and_then(|registry| vendor_dirs.get(registry));
+                dep.proc_macro =
+                    registry::find_manifest(&dep.name, &dep.ver, vendor_dir.map(|d| d.as_path()))
+                        .and_then(|path| util::toml_from_file(path).ok())
+                        .is_some_and(|manifest| is_proc_macro(&manifest));
+            }
+        }
+        if self.cfg.only_proc_macros {
+            dg.only_proc_macros()?;
+        }
+
+        if !self.cfg.only_sources.is_empty() {
+            dg.only_sources(&self.cfg.only_sources);
+        }
+
+        if let Some(focus) = &self.cfg.focus {
+            dg.focus(focus)?;
+        }
+
+        if let Some(subtree) = &self.cfg.subtree {
+            dg.subtree(subtree)?;
+        }
+
+        if self.cfg.duplicates {
+            dg.duplicates()?;
+        }
+
+        // The counts are taken before the graph is cut down, as they are meant to show what each
+        // crate costs.
+        if self.cfg.transitive_counts {
+            dg.count_transitive_deps();
+        }
+
+        if let Some(why) = &self.cfg.why {
+            dg.why(why, self.cfg.why_from.as_deref())?;
+        }
+
+        if let Some(depth) = self.cfg.depth {
+            dg.limit_depth(depth);
+        }
+
+        if let Some(max_nodes) = self.cfg.max_nodes {
+            let removed = dg.limit_nodes(max_nodes);
+            if !removed.is_empty() {
+                eprintln!(
+                    "Left out {} crates to stay within {} nodes: {}",
+                    removed.len(),
+                    max_nodes,
+                    removed.join(", ")
+                );
+            }
+        }
+
+        if self.cfg.transitive_reduction {
+            dg.transitive_reduction();
+        }
+
+        if let Some(count) = self.cfg.heaviest {
+            let heaviest: Vec<(Node, usize)> = dg.heaviest_deps().into_iter().take(count).collect();
+            eprintln!(
+                "Direct dependencies that pull in the most crates of their own ({}):",
+                heaviest.len()
+            );
+            for &(id, crates) in &heaviest {
+                eprintln!(
+                    "  {:>5}  {} v{}",
+                    crates, dg.nodes[id].name, dg.nodes[id].ver
+                );
+                dg.nodes[id].heavy = self.cfg.highlight_heaviest;
+            }
+        }
+
+        // The graph is inverted last, as everything before follows the dependencies downwards.
+        if let Some(invert) = &self.cfg.invert {
+            dg.invert(invert)?;
+        }
+
         if !self.cfg.include_vers {
             dg.show_version_on_duplicates();
         }
+        if self.cfg.highlight_duplicates.is_some() {
+            dg.mark_duplicates();
+        }
+
+        // The passes below share the lookups, as several of them need the same index entries.
+        let mut crates_io = registry::CratesIo::default();
+
+        // cargo metadata already reports the licenses of all packages.
+        if self.cfg.shows_licenses() && self.cfg.backend == Backend::Lockfile {
+            for dep in dg.nodes.iter_mut().filter(|dep| dep.source.is_some()) {
+                let vendor_dir = dep
+                    .registry
+                    .as_ref()
+                    .and_then(|registry| vendor_dirs.get(registry));
+                dep.license =
+                    registry::license(&dep.name, &dep.ver, vendor_dir.map(|d| d.as_path()))
+                        .or_else(|| match dep.registry.as_deref() {
+                            Some("crates-io") => crates_io.license(&dep.name, &dep.ver),
+                            _ => None,
+                        });
+            }
+        }
+        if !self.cfg.license_policy.is_empty() {
+            let mut violations = vec![];
+            for id in 0..dg.nodes.len() {
+                let dep = &dg.nodes[id];
+                // Root crates and path dependencies often have no license, as they are not
+                // published.
+                if !dg.is_shown(id) || (dep.source.is_none() && dep.license.is_none()) {
+                    continue;
+                }
+                if !self.cfg.license_policy.accepts(dep.license.as_deref()) {
+                    violations.push(id);
+                }
+            }
+
+            if !violations.is_empty() {
+                eprintln!(
+                    "Found {} crates that violate the license policy:",
+                    violations.len()
+                );
+            }
+            for id in violations {
+                let dep = &mut dg.nodes[id];
+                dep.license_violation = true;
+                eprintln!(
+                    "  {} v{} ({})",
+                    dep.name,
+                    dep.ver,
+                    dep.license.as_deref().unwrap_or("unknown license")
+                );
+            }
+        }
+        // The advisory database only has crates from crates.io.
+        if self.cfg.advisories {
+            let db = advisory::find_db(self.cfg.advisory_db.as_deref())?;
+            let advisories = advisory::load(&db)?;
+            let mut affected = vec![];
+            for id in 0..dg.nodes.len() {
+                let dep = &dg.nodes[id];
+                if !dg.is_shown(id) || !source_is_registry(&dep.source, "crates-io") {
+                    continue;
+                }
+                let dep_advisories: Vec<Advisory> = advisories
+                    .get(&dep.name)
+                    .into_iter()
+                    .flatten()
+                    .filter(|advisory| advisory.affects(&dep.ver))
+                    .cloned()
+                    .collect();
+                if !dep_advisories.is_empty() {
+                    affected.push((id, dep_advisories));
+                }
+            }
 
-        Ok(dbg!(dg))
+            if !affected.is_empty() {
+                eprintln!("Found {} crates with security advisories:", affected.len());
+            }
+            for (id, dep_advisories) in affected {
+                let dep = &mut dg.nodes[id];
+                for advisory in &dep_advisories {
+                    eprintln!(
+                        "  {} v{}: {} {}",
+                        dep.name, dep.ver, advisory.id, advisory.title
+                    );
+                }
+                dep.advisories = dep_advisories;
+            }
+        }
+        if self.cfg.outdated || self.cfg.yanked {
+            let index_path = self.cfg.index_path.as_ref().map(Path::new);
+            let mut outdated = vec![];
+            let mut yanked = vec![];
+            let mut not_found = 0;
+            for id in 0..dg.nodes.len() {
+                let dep = &dg.nodes[id];
+                if !dg.is_shown(id) || !source_is_registry(&dep.source, "crates-io") {
+                    continue;
+                }
+                let versions = match crates_io.index_versions(&dep.name, index_path) {
+                    Some(versions) => versions,
+                    None => {
+                        not_found += 1;
+                        continue;
+                    }
+                };
+                if self.cfg.yanked && versions.iter().any(|v| v.yanked && v.version == dep.ver) {
+                    yanked.push(id);
+                }
+                if self.cfg.outdated {
+                    let latest = registry::latest_version(&versions);
+                    match (latest, semver::Version::parse(&dep.ver)) {
+                        (Some(latest), Ok(ver)) if latest > ver => outdated.push((id, latest)),
+                        _ => (),
+                    }
+                }
+            }
+
+            if not_found > 0 {
+                eprintln!(
+                    "Could not find the versions of {} crates in the crates.io index.",
+                    not_found
+                );
+            }
+            if !outdated.is_empty() {
+                eprintln!("Found {} outdated crates:", outdated.len());
+            }
+            for (id, latest) in outdated {
+                let dep = &mut dg.nodes[id];
+                eprintln!("  {} v{} -> {}", dep.name, dep.ver, latest);
+                dep.latest = Some(latest.to_string());
+            }
+            if !yanked.is_empty() {
+                eprintln!("Found {} yanked crates:", yanked.len());
+            }
+            for id in yanked {
+                let dep = &mut dg.nodes[id];
+                eprintln!("  {} v{}", dep.name, dep.ver);
+                dep.yanked = true;
+            }
+        }
+        if self.cfg.shows_rust_versions() && self.cfg.backend == Backend::Lockfile {
+            let index_path = self.cfg.index_path.as_ref().map(Path::new);
+            for dep in dg.nodes.iter_mut().filter(|dep| dep.source.is_some()) {
+                let vendor_dir = dep
+                    .registry
+                    .as_ref()
+                    .and_then(|registry| vendor_dirs.get(registry));
+                dep.rust_version =
+                    registry::rust_version(&dep.name, &dep.ver, vendor_dir.map(|d| d.as_path()))
+                        .or_else(|| match dep.registry.as_deref() {
+                            Some("crates-io") => {
+                                crates_io
+                                    .index_versions(&dep.name, index_path)?
+                                    .into_iter()
+                                    .find(|v| v.version == dep.ver)?
+                                    .rust_version
+                            }
+                            _ => None,
+                        });
+            }
+        }
+        if let Some(toolchain) = &self.cfg.check_msrv {
+            let toolchain = util::parse_rust_version(toolchain).unwrap();
+            let mut blocking = vec![];
+            for id in 0..dg.nodes.len() {
+                let rust_version = dg.nodes[id]
+                    .rust_version
+                    .as_deref()
+                    .and_then(util::parse_rust_version);
+                if dg.is_shown(id) && rust_version.is_some_and(|ver| ver > toolchain) {
+                    blocking.push(id);
+                }
+            }
+
+            if !blocking.is_empty() {
+                eprintln!(
+                    "Found {} crates that need a newer Rust than {}:",
+                    blocking.len(),
+                    toolchain
+                );
+            }
+            for id in blocking {
+                let dep = &mut dg.nodes[id];
+                dep.msrv_violation = true;
+                eprintln!(
+                    "  {} v{} (rust-version {})",
+                    dep.name,
+                    dep.ver,
+                    dep.rust_version.as_deref().unwrap_or("")
+                );
+            }
+        }
+        if self.cfg.tooltips && self.cfg.backend == Backend::Lockfile {
+            let mut not_found = 0;
+            for dep in dg.nodes.iter_mut().filter(|dep| dep.source.is_some()) {
+                let vendor_dir = dep
+                    .registry
+                    .as_ref()
+                    .and_then(|registry| vendor_dirs.get(registry));
+                dep.description =
+                    registry::description(&dep.name, &dep.ver, vendor_dir.map(|d| d.as_path()))
+                        .or_else(|| match dep.registry.as_deref() {
+                            Some("crates-io") => crates_io.description(&dep.name),
+                            _ => None,
+                        });
+                if dep.description.is_none() {
+                    not_found += 1;
+                }
+            }
+            if not_found > 0 {
+                eprintln!(
+                    "Could not find the description of {} crates, which have no tooltip.",
+                    not_found
+                );
+            }
+        }
+        if self.cfg.link == Some(Link::Repository) && self.cfg.backend == Backend::Lockfile {
+            for dep in dg.nodes.iter_mut().filter(|dep| dep.source.is_some()) {
+                let vendor_dir = dep
+                    .registry
+                    .as_ref()
+                    .and_then(|registry| vendor_dirs.get(registry));
+                dep.repository =
+                    registry::repository(&dep.name, &dep.ver, vendor_dir.map(|d| d.as_path()));
+            }
+        }
+
+        if self.cfg.shows_crate_sizes() {
+            let mut not_found = 0;
+            for dep in dg.nodes.iter_mut().filter(|dep| dep.source.is_some()) {
+                let vendor_dir = dep
+                    .registry
+                    .as_ref()
+                    .and_then(|registry| vendor_dirs.get(registry));
+                dep.unpacked_size =
+                    registry::unpacked_size(&dep.name, &dep.ver, vendor_dir.map(|d| d.as_path()));
+                dep.download_size = registry::download_size(&dep.name, &dep.ver).or_else(|| {
+                    match dep.registry.as_deref() {
+                        Some("crates-io") => crates_io.download_size(&dep.name, &dep.ver),
+                        _ => None,
+                    }
+                });
+                if dep.size().is_none() {
+                    not_found += 1;
+                }
+            }
+            if not_found > 0 {
+                eprintln!("Could not find the size of {} crates.", not_found);
+            }
+        }
+
+        if self.cfg.shows_downloads() {
+            let mut not_found = 0;
+            for id in 0..dg.nodes.len() {
+                if !dg.is_shown(id) || dg.nodes[id].registry.as_deref() != Some("crates-io") {
+                    continue;
+                }
+                let dep = &mut dg.nodes[id];
+                dep.downloads = crates_io.downloads(&dep.name);
+                if dep.downloads.is_none() {
+                    not_found += 1;
+                }
+            }
+            if not_found > 0 {
+                eprintln!("Could not find the download count of {} crates.", not_found);
+            }
+        }
+        if let Some(threshold) = self.cfg.obscure_below {
+            for dep in dg.nodes.iter_mut() {
+                dep.obscure = dep.downloads.is_some_and(|downloads| downloads < threshold);
+            }
+            let obscure: Vec<&ResolvedDep> = dg.nodes.iter().filter(|dep| dep.obscure).collect();
+            if !obscure.is_empty() {
+                eprintln!(
+                    "Found {} crates with fewer than {} downloads:",
+                    obscure.len(),
+                    dep::format_count(threshold)
+                );
+            }
+            for dep in obscure {
+                let downloads = dep.downloads.unwrap_or(0);
+                eprintln!(
+                    "  {} v{} ({} downloads)",
+                    dep.name,
+                    dep.ver,
+                    dep::format_count(downloads)
+                );
+            }
+        }
+
+        if let Some(path) = &self.cfg.timings {
+            let times = timings::load(path)?;
+            for dep in dg.nodes.iter_mut() {
+                dep.build_time = times.get(&(dep.name.clone(), dep.ver.clone())).copied();
+            }
+        }
+
+        // Versions are merged last, as everything before looks crates up by name and version.
+        if self.cfg.merge_versions {
+            dg.merge_versions();
+        }
+
+        if let Some(size_by) = self.cfg.size_by {
+            dg.size_nodes(size_by);
+        }
+
+        if self.cfg.critical_path {
+            let path = dg.mark_critical_path();
+            let names: Vec<String> = path
+                .iter()
+                .map(|&id| format!("{} v{}", dg.nodes[id].name, dg.nodes[id].ver))
+                .collect();
+            eprintln!("Critical path of {} crates:", path.len());
+            eprintln!("  {}", names.join(" -> "));
+        }
+
+        if self.cfg.license_summary {
+            let licenses = dg.license_counts();
+            let total: usize = licenses.iter().map(|(_, count)| count).sum();
+            eprintln!("Licenses of {} crates:", total);
+            for (license, count) in licenses {
+                eprintln!("  {:>5}  {}", count, license.unwrap_or("unknown"));
+            }
+        }
+
+        if self.cfg.stats || self.cfg.stats_file.is_some() {
+            let stats = dg.stats(10);
+            if self.cfg.stats {
+                print_stats(&dg, &stats);
+            }
+            if let Some(path) = &self.cfg.stats_file {
+                let json = serde_json::to_string_pretty(&stats_json(&dg, &stats))?;
+                fs::write(path, json + "\n").map_err(|e| {
+                    CliError::Generic(format!("Could not write the stats to {}: {}", path, e))
+                })?;
+            }
+        }
+
+        if self.cfg.flag_loose_reqs {
+            let loose_reqs = dg.loose_reqs();
+            if loose_reqs.is_empty() {
+                eprintln!("No loose version requirements found.");
+            } else {
+                eprintln!("Found {} loose version requirements:", loose_reqs.len());
+                for (parent, child, req) in loose_reqs {
+                    eprintln!("  {} -> {} = \"{}\"", parent, child, req);
+                }
+            }
+        }
+
+        Ok(dg)
     }
 
-    /// Builds a list of the dependencies declared in the manifest file.
-    pub fn parse_root_deps(
-        &self,
-        manifest_path: &PathBuf,
-    ) -> CliResult<(Vec<RootCrate>, RootDepsMap)> {
-        let manifest_toml = util::toml_from_file(manifest_path)?;
+    /// Classifies the dependencies of registry crates by how their manifests declare them, and
+    /// removes the ones of kinds that were not selected or that do not apply to the target, along
+    /// with the crates that are then no longer needed. Crates that are not in the local registry
+    /// cache keep all their dependencies, unclassified. The dependencies of root crates and path
+    /// dependencies were already classified based on their manifests.
+    fn classify_registry_deps(&self, dg: &mut DepGraph, vendor_dirs: &HashMap<String, PathBuf>) {
+        let mut removed = HashSet::new();
+        let mut edge_kinds = HashMap::new();
+        let mut edge_reqs = HashMap::new();
+        let mut not_cached = 0;
+        for (id, dep) in dg.nodes.iter().enumerate() {
+            if dep.source.is_none() {
+                continue;
+            }
 
-        // Get the name and version of the root project.
-        let root_crates_tomls = {
-            if let Some(table) = manifest_toml.get("package") {
-                if let Some(table) = table.as_table() {
-                    if let (Some(&Value::String(ref name)), Some(&Value::String(ref ver))) =
-                        (table.get("name"), table.get("version"))
-                    {
-                        let (name, ver) = (name.to_string(), ver.to_string());
-                        vec![(RootCrate { name, ver }, manifest_toml)]
-                    } else {
-                        return Err(CliError::Toml(
-                            "No 'name' or 'version' fields in [package] table".into(),
-                        ));
+            let vendor_dir = dep
+                .registry
+                .as_ref()
+                .and_then(|registry| vendor_dirs.get(registry));
+            let manifest =
+                match registry::find_manifest(&dep.name, &dep.ver, vendor_dir.map(|d| d.as_path()))
+                    .and_then(|path| util::toml_from_file(path).ok())
+                {
+                    Some(manifest) => manifest,
+                    None => {
+                        not_cached += 1;
+                        continue;
+                    }
+                };
+
+            // Published manifests do not inherit anything from a workspace. Optional dependencies
+            // are only in the lock file if a feature enables them, so they count as regular.
+            let mut declared: DepKindsMap = HashMap::new();
+            let mut declared_reqs = DepReqsMap::new();
+            for (key, kind) in &[
+                ("dependencies", DepKind::Regular),
+                ("build-dependencies", DepKind::Build),
+                ("dev-dependencies", DepKind::Dev),
+            ] {
+                if let Ok(deps) = dependencies(&manifest, key, None, None, self.target.as_ref()) {
+                    for (dep_name, dep) in deps {
+                        add_req(&mut declared_reqs, dep_name.clone(), &dep);
+                        add_kind(&mut declared, dep_name, *kind);
                     }
+                }
+            }
+
+            for &child in &dep.children {
+                let selected: Vec<DepKind> = declared
+                    .get(&dg.nodes[child].name)
+                    .into_iter()
+                    .flatten()
+                    .copied()
+                    .filter(|kind| match kind {
+                        DepKind::Regular => self.cfg.regular_deps,
+                        DepKind::Build => self.cfg.build_deps,
+                        DepKind::Dev => self.cfg.dev_deps,
+                        _ => false,
+                    })
+                    .collect();
+                if selected.is_empty() {
+                    removed.insert(Edge(id, child));
                 } else {
-                    return Err(CliError::Toml(
-                        "Could not parse [package] as a table".into(),
-                    ));
+                    edge_kinds.insert(Edge(id, child), selected);
+                    if let Some(reqs) = declared_reqs.get(&dg.nodes[child].name) {
+                        edge_reqs.insert(Edge(id, child), reqs.clone());
+                    }
                 }
+            }
+        }
+
+        if not_cached > 0 && self.target.is_some() {
+            eprintln!(
+                "{} crates are not in the local registry cache, so their dependencies for all \
+                 targets are kept.",
+                not_cached
+            );
+        }
+        dg.edge_kinds.extend(edge_kinds);
+        dg.edge_reqs.extend(edge_reqs);
+        dg.remove_edges(&removed);
+    }
+
+    /// Builds a list of the dependencies declared in the manifest file. If the manifest is the root
+    /// of a workspace, all workspace members are root crates.
+    pub fn parse_root_deps(
+        &self,
+        manifest_path: &Path,
+        manifest_toml: &Value,
+    ) -> CliResult<ManifestDeps> {
+        let manifest_dir = manifest_path.parent().unwrap();
+        let mut manifests = vec![manifest_path.to_path_buf()];
+
+        // The [workspace] table that inherited fields are resolved against. If the manifest is not
+        // the workspace root itself, it may be a member of a workspace further up.
+        let workspace_root = if manifest_toml.get("workspace").is_some() {
+            Some((manifest_dir.to_path_buf(), manifest_toml.clone()))
+        } else {
+            find_workspace_root(manifest_path)?
+        };
+        let workspace = workspace_root
+            .as_ref()
+            .and_then(|(_, t)| t.get("workspace"));
+        let workspace_dir = workspace_root.as_ref().map(|(dir, _)| dir.as_path());
+
+        // Get the name and version of the root project. A virtual manifest has no package of its
+        // own, only workspace members.
+        let mut root_crates_tomls = {
+            if let Some(table) = manifest_toml.get("package") {
+                vec![(
+                    parse_root_crate(table, workspace)?,
+                    manifest_toml.clone(),
+                    manifest_dir.to_path_buf(),
+                )]
+            } else if manifest_toml.get("workspace").is_some() {
+                vec![]
             } else {
-                // TODO: Check for workspace here.
-                return Err(CliError::Toml("No [package] table found".into()));
+                return Err(CliError::Toml(
+                    "No [package] or [workspace] table found".into(),
+                ));
             }
         };
 
-        let mut root_deps_map = HashMap::new();
+        // Add the workspace members.
+        if let Some(workspace_table) = manifest_toml.get("workspace") {
+            for member_path in workspace_members(manifest_dir, workspace_table)? {
+                let member_toml = util::toml_from_file(&member_path)?;
+                manifests.push(member_path.clone());
 
-        for (root_crate, manifest_toml) in root_crates_tomls.iter() {
-            let root_name = &root_crate.name;
-            let mut dep_kinds_map = HashMap::new();
-
-            if let Some(table) = manifest_toml.get("dependencies") {
-                if let Some(table) = table.as_table() {
-                    for (dep_name, dep_table) in table.iter() {
-                        if let Some(&Value::Boolean(true)) = dep_table.get("optional") {
-                            if self.cfg.optional_deps {
-                                add_kind(
-                                    &mut dep_kinds_map,
-                                    dep_name.to_string(),
-                                    DepKind::Optional,
-                                );
-                            }
-                        } else if self.cfg.regular_deps {
-                            add_kind(&mut dep_kinds_map, dep_name.to_string(), DepKind::Regular);
-                        }
-                    }
+                if let Some(table) = member_toml.get("package") {
+                    root_crates_tomls.push((
+                        parse_root_crate(table, workspace)?,
+                        member_toml,
+                        member_path.parent().unwrap().to_path_buf(),
+                    ));
+                } else {
+                    return Err(CliError::Toml(format!(
+                        "No [package] table found in workspace member {}",
+                        member_path.display()
+                    )));
                 }
             }
+        }
 
-            if self.cfg.build_deps {
-                if let Some(table) = manifest_toml.get("build-dependencies") {
-                    if let Some(table) = table.as_table() {
-                        for (dep_name, _) in table.iter() {
-                            add_kind(&mut dep_kinds_map, dep_name.to_string(), DepKind::Build);
-                        }
-                    }
+        // Keep only the selected workspace members. Unselected members can still show up as
+        // dependencies of the selected ones.
+        if let Some(members) = &self.cfg.workspace_members {
+            for member in members {
+                if !root_crates_tomls
+                    .iter()
+                    .any(|(root_crate, _, _)| &root_crate.name == member)
+                {
+                    return Err(CliError::Generic(format!(
+                        "'{}' is not a member of the workspace",
+                        member
+                    )));
                 }
             }
 
-            if self.cfg.dev_deps {
-                if let Some(table) = manifest_toml.get("dev-dependencies") {
-                    if let Some(table) = table.as_table() {
-                        for (dep_name, _) in table.iter() {
-                            add_kind(&mut dep_kinds_map, dep_name.to_string(), DepKind::Dev);
+            root_crates_tomls.retain(|(root_crate, _, _)| members.contains(&root_crate.name));
+        }
+
+        for member in self.cfg.per_member_kinds() {
+            if !root_crates_tomls
+                .iter()
+                .any(|(root_crate, _, _)| &root_crate.name == member)
+            {
+                return Err(CliError::Generic(format!(
+                    "'{}' is not a selected member of the workspace",
+                    member
+                )));
+            }
+        }
+
+        if root_crates_tomls.is_empty() {
+            return Err(CliError::Toml(
+                "The virtual manifest does not have any workspace members".into(),
+            ));
+        }
+
+        // The requested features apply to all root crates.
+        let feature_request = self.cfg.feature_request();
+        let mut root_deps_map = HashMap::new();
+        let mut proc_macros = HashSet::new();
+        let mut reqs = HashMap::new();
+        let mut descriptions = HashMap::new();
+        let mut licenses = HashMap::new();
+        let mut rust_versions = HashMap::new();
+        let mut queue: Vec<(PathBuf, Value, Option<EnabledFeatures>)> = vec![];
+
+        for (root_crate, manifest_toml, dir) in root_crates_tomls.iter() {
+            let enabled = feature_request
+                .as_ref()
+                .map(|request| features::resolve(manifest_toml, request));
+            let dep_kinds_map = self.declared_deps(
+                manifest_toml,
+                workspace,
+                workspace_dir,
+                enabled.as_ref(),
+                Some(&root_crate.name),
+            )?;
+            root_deps_map.insert(root_crate.name.to_string(), dep_kinds_map);
+            reqs.insert(
+                root_crate.name.to_string(),
+                self.declared_reqs(manifest_toml, workspace, workspace_dir)?,
+            );
+            if is_proc_macro(manifest_toml) {
+                proc_macros.insert(root_crate.name.to_string());
+            }
+            if let Some(description) = package_description(manifest_toml) {
+                descriptions.insert(root_crate.name.to_string(), description);
+            }
+            if let Some(license) = package_license(manifest_toml, workspace) {
+                licenses.insert(root_crate.name.to_string(), license);
+            }
+            if let Some(ver) = package_field(manifest_toml, workspace, "rust-version") {
+                rust_versions.insert(root_crate.name.to_string(), ver.to_string());
+            }
+            queue.push((dir.clone(), manifest_toml.clone(), enabled));
+        }
+
+        // Follow the path dependencies, so that their own dependencies are classified by how they
+        // are declared in their manifests too, and collect the registries of all dependencies.
+        // This is done on a best effort basis: a path dependency whose manifest cannot be read is
+        // classified from the lock file alone, and the features of a path dependency are the ones
+        // requested by the first crate found to depend on it.
+        let mut path_deps_map = HashMap::new();
+        let mut registries = HashMap::new();
+
+        while let Some((dir, manifest_toml, enabled)) = queue.pop() {
+            for key in &["dependencies", "build-dependencies", "dev-dependencies"] {
+                for (dep_name, dep) in dependencies(
+                    &manifest_toml,
+                    key,
+                    workspace,
+                    workspace_dir,
+                    self.target.as_ref(),
+                )? {
+                    if let Some(registry) = dep.get("registry").and_then(|r| r.as_str()) {
+                        registries.insert(dep_name, registry.to_string());
+                    }
+
+                    let path = match dep.get("path").and_then(|path| path.as_str()) {
+                        Some(path) => dir.join(path),
+                        None => continue,
+                    };
+                    let dep_toml = match util::toml_from_file(path.join("Cargo.toml")) {
+                        Ok(dep_toml) => dep_toml,
+                        Err(_) => continue,
+                    };
+                    let name = match dep_toml
+                        .get("package")
+                        .and_then(|package| package.get("name"))
+                        .and_then(|name| name.as_str())
+                    {
+                        Some(name) => name.to_string(),
+                        None => continue,
+                    };
+
+                    if root_deps_map.contains_key(&name) || path_deps_map.contains_key(&name) {
+                        continue;
+                    }
+                    let dep_enabled = enabled.as_ref().map(|enabled| {
+                        let dep_features = enabled.dep_features.get(&name);
+                        let request =
+                            FeatureRequest::declared(&dep, dep_features.map_or(&[], |f| f));
+                        features::resolve(&dep_toml, &request)
+                    });
+                    if let Ok(dep_kinds_map) = self.declared_deps(
+                        &dep_toml,
+                        workspace,
+                        workspace_dir,
+                        dep_enabled.as_ref(),
+                        None,
+                    ) {
+                        if is_proc_macro(&dep_toml) {
+                            proc_macros.insert(name.clone());
+                        }
+                        if let Some(description) = package_description(&dep_toml) {
+                            descriptions.insert(name.clone(), description);
+                        }
+                        if let Some(license) = package_license(&dep_toml, workspace) {
+                            licenses.insert(name.clone(), license);
+                        }
+                        if let Some(ver) = package_field(&dep_toml, workspace, "rust-version") {
+                            rust_versions.insert(name.clone(), ver.to_string());
+                        }
+                        if let Ok(dep_reqs) =
+                            self.declared_reqs(&dep_toml, workspace, workspace_dir)
+                        {
+                            reqs.insert(name.clone(), dep_reqs);
                         }
+                        path_deps_map.insert(name, dep_kinds_map);
+                        manifests.push(path.join("Cargo.toml"));
+                        queue.push((path, dep_toml, dep_enabled));
                     }
                 }
             }
-
-            root_deps_map.insert(root_name.to_string(), dep_kinds_map);
         }
 
-        Ok((
-            root_crates_tomls
+        Ok(ManifestDeps {
+            root_crates: root_crates_tomls
                 .iter()
-                .map(|(root_crate, _)| root_crate.clone())
+                .map(|(root_crate, _, _)| root_crate.clone())
                 .collect(),
             root_deps_map,
-        ))
+            path_deps_map,
+            registries,
+            proc_macros,
+            reqs,
+            descriptions,
+            licenses,
+            rust_versions,
+            manifests,
+        })
+    }
+
+    /// The version requirements of the dependencies declared in a manifest, of all kinds.
+    fn declared_reqs(
+        &self,
+        manifest_toml: &Value,
+        workspace: Option<&Value>,
+        workspace_dir: Option<&Path>,
+    ) -> CliResult<DepReqsMap> {
+        let mut reqs = DepReqsMap::new();
+        for key in &["dependencies", "build-dependencies", "dev-dependencies"] {
+            for (dep_name, dep) in dependencies(
+                manifest_toml,
+                key,
+                workspace,
+                workspace_dir,
+                self.target.as_ref(),
+            )? {
+                add_req(&mut reqs, dep_name, &dep);
+            }
+        }
+        Ok(reqs)
+    }
+
+    /// Classifies the dependencies declared in a manifest, keeping only the selected kinds. With
+    /// `enabled` features, the optional dependencies that they enable are regular dependencies.
+    /// The kinds selected for a `root` crate include the ones selected for it alone.
+    fn declared_deps(
+        &self,
+        manifest_toml: &Value,
+        workspace: Option<&Value>,
+        workspace_dir: Option<&Path>,
+        enabled: Option<&EnabledFeatures>,
+        root: Option<&str>,
+    ) -> CliResult<DepKindsMap> {
+        let includes = |kind| self.cfg.includes_kind(kind, root);
+        let mut dep_kinds_map = HashMap::new();
+
+        for (dep_name, dep_table) in dependencies(
+            manifest_toml,
+            "dependencies",
+            workspace,
+            workspace_dir,
+            self.target.as_ref(),
+        )? {
+            let optional = dep_table.get("optional") == Some(&Value::Boolean(true))
+                && !enabled.is_some_and(|enabled| enabled.optional_deps.contains(&dep_name));
+            if optional {
+                if includes(DepKind::Optional) {
+                    add_kind(&mut dep_kinds_map, dep_name, DepKind::Optional);
+                }
+            } else if includes(DepKind::Regular) {
+                add_kind(&mut dep_kinds_map, dep_name, DepKind::Regular);
+            }
+        }
+
+        if includes(DepKind::Build) {
+            for (dep_name, _) in dependencies(
+                manifest_toml,
+                "build-dependencies",
+                workspace,
+                workspace_dir,
+                self.target.as_ref(),
+            )? {
+                add_kind(&mut dep_kinds_map, dep_name, DepKind::Build);
+            }
+        }
+
+        if includes(DepKind::Dev) {
+            for (dep_name, _) in dependencies(
+                manifest_toml,
+                "dev-dependencies",
+                workspace,
+                workspace_dir,
+                self.target.as_ref(),
+            )? {
+                add_kind(&mut dep_kinds_map, dep_name, DepKind::Dev);
+            }
+        }
+
+        Ok(dep_kinds_map)
     }
 
     /// Builds a graph of the resolved dependencies declared in the lock file.
     fn parse_lock_file(
         &self,
-        lock_path: PathBuf,
+        lock_toml: &Value,
         root_crates: &[RootCrate],
         root_deps_map: RootDepsMap,
+        path_deps_map: RootDepsMap,
     ) -> CliResult<DepGraph> {
-        let lock_toml = util::toml_from_file(lock_path)?;
+        // Version 1 lock files have no `version` field.
+        if let Some(version) = lock_toml.get("version").and_then(|v| v.as_integer()) {
+            if version > MAX_LOCK_VERSION {
+                eprintln!(
+                    "Lock file version {} is newer than the latest supported version {}, \
+                     the graph may be incomplete.",
+                    version, MAX_LOCK_VERSION
+                );
+            }
+        }
 
         let mut dg = DepGraph::new(self.cfg.clone());
         dg.root_deps_map = root_deps_map;
+        dg.path_deps_map = path_deps_map;
 
-        if let Some(root) = lock_toml.get("root") {
-            parse_package(&mut dg, root, root_crates)?;
-        }
+        let packages = lock_packages(lock_toml);
 
-        if let Some(&Value::Array(ref packages)) = lock_toml.get("package") {
-            for pkg in packages {
-                parse_package(&mut dg, pkg, root_crates)?;
+        // Since version 2, dependencies only include the version if there are several versions of
+        // the package in the lock file, so collect the available versions first.
+        let mut versions: HashMap<String, Vec<String>> = HashMap::new();
+        for pkg in &packages {
+            if let (Some(name), Some(ver)) = (
+                pkg.get("name").and_then(|n| n.as_str()),
+                pkg.get("version").and_then(|v| v.as_str()),
+            ) {
+                let vers = versions.entry(name.into()).or_default();
+                if !vers.iter().any(|v| v == ver) {
+                    vers.push(ver.into());
+                }
             }
         }
 
+        for pkg in packages {
+            parse_package(&mut dg, pkg, root_crates, &versions)?;
+        }
+
         // Check that all root crates were found in the lock files.
-        for &RootCrate { ref name, ref ver } in root_crates.iter() {
-            if dg.find(&name, &ver).is_none() {
+        for RootCrate { name, ver } in root_crates.iter() {
+            if dg.find(name, ver).is_none() {
                 return Err(CliError::Toml(format!(
                     "Missing 'name': {} and 'version': {} in lock file",
                     name, ver
@@ -166,12 +1280,401 @@ impl Project {
     }
 }
 
-fn add_kind(dep_kinds_map: &mut DepKindsMap, key: String, kind: DepKind) {
-    let kinds = dep_kinds_map.entry(key).or_insert_with(|| vec![]);
+/// The [root] (in version 1 lock files) and [[package]] tables of a lock file.
+fn lock_packages(lock_toml: &Value) -> Vec<&Value> {
+    let mut packages = vec![];
+    if let Some(root) = lock_toml.get("root") {
+        packages.push(root);
+    }
+    if let Some(Value::Array(pkgs)) = lock_toml.get("package") {
+        packages.extend(pkgs.iter());
+    }
+    packages
+}
+
+/// Gets the name and version of a root crate from its [package] table. The version may be inherited
+/// from [workspace.package].
+fn parse_root_crate(table: &Value, workspace: Option<&Value>) -> CliResult<RootCrate> {
+    if let Some(table) = table.as_table() {
+        let ver = match table.get("version") {
+            Some(ver) if is_inherited(ver) => workspace
+                .and_then(|workspace| workspace.get("package"))
+                .and_then(|package| package.get("version")),
+            ver => ver,
+        };
+
+        if let (Some(Value::String(name)), Some(Value::String(ver))) = (table.get("name"), ver) {
+            let (name, ver) = (name.to_string(), ver.to_string());
+            Ok(RootCrate { name, ver })
+        } else {
+            Err(CliError::Toml(
+                "No 'name' or 'version' fields in [package] table".into(),
+            ))
+        }
+    } else {
+        Err(CliError::Toml(
+            "Could not parse [package] as a table".into(),
+        ))
+    }
+}
+
+/// A crate overridden in a [patch.<registry>] table.
+struct Patch {
+    name: String,
+    /// `crates-io` or the URL of the overridden registry or git repository.
+    registry: String,
+}
+
+/// Reads the [patch] tables. Patches only take effect in the root manifest of a workspace.
+fn parse_patches(manifest_path: &Path, manifest_toml: &Value) -> CliResult<Vec<Patch>> {
+    let root_toml = if manifest_toml.get("workspace").is_some() {
+        Some(manifest_toml.clone())
+    } else {
+        find_workspace_root(manifest_path)?
+            .map(|(_, root_toml)| root_toml)
+            .or_else(|| Some(manifest_toml.clone()))
+    };
+
+    let mut patches = vec![];
+    let patch_tables = root_toml
+        .as_ref()
+        .and_then(|t| t.get("patch"))
+        .and_then(|t| t.as_table());
+    if let Some(patch_tables) = patch_tables {
+        for (registry, table) in patch_tables {
+            if let Some(table) = table.as_table() {
+                for (dep_name, dep) in table {
+                    patches.push(Patch {
+                        name: package_name(dep_name, dep),
+                        registry: registry.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(patches)
+}
+
+/// Whether a lock file `source` is the given registry (`crates-io` or a URL).
+fn source_is_registry(source: &Option<String>, registry: &str) -> bool {
+    match source {
+        None => false,
+        Some(source) if registry == "crates-io" => {
+            source.contains("github.com/rust-lang/crates.io-index")
+                || source.contains("index.crates.io")
+        }
+        Some(source) => source.contains(registry.trim_end_matches('/').trim_end_matches(".git")),
+    }
+}
+
+/// Whether a manifest field is inherited from the workspace, i.e. `{ workspace = true }`.
+fn is_inherited(value: &Value) -> bool {
+    value.get("workspace").and_then(|w| w.as_bool()) == Some(true)
+}
+
+/// Searches the parent directories of a manifest for the root manifest of a workspace, and returns
+/// its directory and contents.
+fn find_workspace_root(manifest_path: &Path) -> CliResult<Option<(PathBuf, Value)>> {
+    for dir in manifest_path.parent().unwrap().ancestors().skip(1) {
+        let candidate = dir.join("Cargo.toml");
+        if !candidate.is_file() {
+            continue;
+        }
+
+        let candidate_toml = util::toml_from_file(&candidate)?;
+        if candidate_toml.get("workspace").is_some() {
+            return Ok(Some((dir.to_path_buf(), candidate_toml)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// The entries of a dependency table (e.g. [dependencies]) of a manifest, keyed by the name of the
+/// package in the lock file, which differs from the key in the manifest for renamed dependencies
+/// (`foo = { package = "bar" }`). Dependencies inherited with `{ workspace = true }` are merged
+/// with their declaration in [workspace.dependencies], so that fields like `package` are
+/// available. Fields set in the manifest itself take priority. Inherited paths are made relative
+/// to `workspace_dir`, the directory of the workspace root.
+fn dependencies(
+    manifest_toml: &Value,
+    key: &str,
+    workspace: Option<&Value>,
+    workspace_dir: Option<&Path>,
+    target: Option<&Target>,
+) -> CliResult<Vec<(String, Value)>> {
+    // The dependencies of the platforms under [target] come after the ones for all platforms.
+    let mut tables: Vec<&toml::value::Table> = manifest_toml
+        .get(key)
+        .and_then(|t| t.as_table())
+        .into_iter()
+        .collect();
+    if let Some(platforms) = manifest_toml.get("target").and_then(|t| t.as_table()) {
+        for (platform, platform_toml) in platforms {
+            if target::applies(target, platform) {
+                tables.extend(platform_toml.get(key).and_then(|t| t.as_table()));
+            }
+        }
+    }
+
+    let mut deps = vec![];
+    for (dep_name, dep) in tables.into_iter().flatten() {
+        if !is_inherited(dep) {
+            deps.push((package_name(dep_name, dep), dep.clone()));
+            continue;
+        }
+
+        let inherited = workspace
+            .and_then(|workspace| workspace.get("dependencies"))
+            .and_then(|deps| deps.get(dep_name))
+            .ok_or_else(|| {
+                CliError::Toml(format!(
+                    "Dependency '{}' is inherited from the workspace, but is not in \
+                     [workspace.dependencies]",
+                    dep_name
+                ))
+            })?;
+
+        // The workspace may declare just a version requirement.
+        let mut merged = match inherited {
+            Value::Table(table) => table.clone(),
+            version => {
+                let mut table = toml::value::Table::new();
+                table.insert("version".into(), version.clone());
+                table
+            }
+        };
+        if let (Some(Value::String(path)), Some(workspace_dir)) =
+            (merged.get("path"), workspace_dir)
+        {
+            let path = workspace_dir.join(path).to_string_lossy().into_owned();
+            merged.insert("path".into(), Value::String(path));
+        }
+        for (k, v) in dep.as_table().unwrap() {
+            if k != "workspace" {
+                merged.insert(k.clone(), v.clone());
+            }
+        }
+
+        let merged = Value::Table(merged);
+        deps.push((package_name(dep_name, &merged), merged));
+    }
+
+    Ok(deps)
+}
+
+/// Whether the manifest is the one of a proc-macro crate.
+fn is_proc_macro(manifest_toml: &Value) -> bool {
+    let lib = manifest_toml.get("lib");
+    ["proc-macro", "proc_macro"]
+        .iter()
+        .any(|key| lib.and_then(|lib| lib.get(key)) == Some(&Value::Boolean(true)))
+}
+
+/// The name of the package behind a dependency declaration, which differs from its key for renamed
+/// dependencies.
+pub fn package_name(dep_name: &str, dep: &Value) -> String {
+    dep.get("package")
+        .and_then(|package| package.as_str())
+        .unwrap_or(dep_name)
+        .to_string()
+}
+
+/// The `description` of the package of a manifest, unless it is inherited from the workspace.
+fn package_description(manifest_toml: &Value) -> Option<String> {
+    manifest_toml
+        .get("package")?
+        .get("description")?
+        .as_str()
+        .map(String::from)
+}
+
+/// Prints the crates of the graph of `--diff` that were added, removed or changed in version on
+/// stderr.
+fn print_diff(dg: &DepGraph) {
+    let mut changes: Vec<(&str, String)> = (0..dg.nodes.len())
+        .filter(|&id| dg.is_shown(id))
+        .filter_map(|id| {
+            let dep = &dg.nodes[id];
+            let change = match dep.diff.as_ref()? {
+                DiffStatus::Added => format!("+ {} v{}", dep.name, dep.ver),
+                DiffStatus::Removed => format!("- {} v{}", dep.name, dep.ver),
+                DiffStatus::Changed(previous) => {
+                    format!("~ {} v{} -> v{}", dep.name, previous, dep.ver)
+                }
+            };
+            Some((dep.name.as_str(), change))
+        })
+        .collect();
+    changes.sort();
+
+    if changes.is_empty() {
+        eprintln!("No crate changed between the lock files.");
+    } else {
+        eprintln!("Found {} changed crates:", changes.len());
+        for (_, change) in changes {
+            eprintln!("  {}", change);
+        }
+    }
+}
+
+/// Prints the metrics of `--stats` on stderr.
+fn print_stats(dg: &DepGraph, stats: &Stats) {
+    let crate_id = |id: Node| format!("{} v{}", dg.nodes[id].name, dg.nodes[id].ver);
+
+    eprintln!("Crates: {}", stats.crates);
+    eprintln!("Edges: {}", stats.edges);
+    eprintln!("Depth: {}", stats.depth);
+    eprintln!("Crates with several versions: {}", stats.duplicates.len());
+    for (name, versions) in &stats.duplicates {
+        eprintln!("  {} ({})", name, versions.join(", "));
+    }
+    eprintln!("Largest subtrees:");
+    for &(id, count) in &stats.largest_subtrees {
+        eprintln!("  {:>5}  {}", count, crate_id(id));
+    }
+    eprintln!("Most depended upon:");
+    for &(id, count) in &stats.most_depended_upon {
+        eprintln!("  {:>5}  {}", count, crate_id(id));
+    }
+}
+
+/// The metrics of `--stats-file`, as JSON.
+fn stats_json(dg: &DepGraph, stats: &Stats) -> serde_json::Value {
+    let top = |counts: &[(Node, usize)], key: &str| -> Vec<serde_json::Value> {
+        counts
+            .iter()
+            .map(|&(id, count)| {
+                let mut entry = serde_json::Map::new();
+                entry.insert("name".into(), dg.nodes[id].name.clone().into());
+                entry.insert("version".into(), dg.nodes[id].ver.clone().into());
+                entry.insert(key.into(), count.into());
+                entry.into()
+            })
+            .collect()
+    };
+    let duplicates: serde_json::Map<String, serde_json::Value> = stats
+        .duplicates
+        .iter()
+        .map(|(name, versions)| (name.clone(), versions.clone().into()))
+        .collect();
+
+    serde_json::json!({
+        "crates": stats.crates,
+        "edges": stats.edges,
+        "depth": stats.depth,
+        "duplicates": duplicates,
+        "largest_subtrees": top(&stats.largest_subtrees, "crates"),
+        "most_depended_upon": top(&stats.most_depended_upon, "dependents"),
+    })
+}
+
+/// The license expression of the package of a manifest, which may be inherited from
+/// [workspace.package]. Packages with a custom license file report that instead, like
+/// `registry::license`.
+fn package_license(manifest_toml: &Value, workspace: Option<&Value>) -> Option<String> {
+    if let Some(license) = package_field(manifest_toml, workspace, "license") {
+        return Some(license.into());
+    }
+
+    package_field(manifest_toml, workspace, "license-file").map(|file| format!("see {}", file))
+}
+
+/// A text field of the package of a manifest, which may be inherited from [workspace.package].
+fn package_field<'a>(
+    manifest_toml: &'a Value,
+    workspace: Option<&'a Value>,
+    key: &str,
+) -> Option<&'a str> {
+    match manifest_toml.get("package")?.get(key) {
+        Some(value) if is_inherited(value) => workspace?.get("package")?.get(key)?.as_str(),
+        value => value?.as_str(),
+    }
+}
+
+/// Finds the manifests of the members of a workspace, expanding globs in `members` and leaving out
+/// anything in `exclude` as well as the workspace root itself.
+fn workspace_members(root_dir: &Path, workspace: &Value) -> CliResult<Vec<PathBuf>> {
+    let patterns = |key| -> Vec<&str> {
+        match workspace.get(key) {
+            Some(Value::Array(values)) => values.iter().filter_map(|v| v.as_str()).collect(),
+            _ => vec![],
+        }
+    };
+    let excluded: Vec<PathBuf> = patterns("exclude")
+        .iter()
+        .flat_map(|pattern| util::expand_glob(root_dir, pattern))
+        .collect();
+
+    let mut members = vec![];
+    for pattern in patterns("members") {
+        for dir in util::expand_glob(root_dir, pattern) {
+            if dir == root_dir || excluded.contains(&dir) {
+                continue;
+            }
+
+            let manifest = dir.join("Cargo.toml");
+            if !manifest.is_file() {
+                // Globs may also match plain files and directories that are not crates.
+                if pattern.contains(['*', '?']) {
+                    continue;
+                }
+
+                return Err(CliError::Generic(format!(
+                    "Workspace member {} does not have a Cargo.toml",
+                    dir.display()
+                )));
+            }
+
+            if !members.contains(&manifest) {
+                members.push(manifest);
+            }
+        }
+    }
+
+    Ok(members)
+}
+
+pub fn add_kind(dep_kinds_map: &mut DepKindsMap, key: String, kind: DepKind) {
+    let kinds = dep_kinds_map.entry(key).or_default();
     kinds.push(kind);
 }
 
-fn parse_package(dg: &mut DepGraph, pkg: &Value, root_crates: &[RootCrate]) -> CliResult<()> {
+/// Adds the version requirement of a dependency declaration, either a bare requirement string or a
+/// table with a `version`. Dependencies without one, like most path dependencies, have none.
+fn add_req(dep_reqs_map: &mut DepReqsMap, key: String, dep: &Value) {
+    let req = match dep {
+        Value::String(req) => req,
+        _ => match dep.get("version").and_then(|v| v.as_str()) {
+            Some(req) => req,
+            None => return,
+        },
+    };
+    let reqs = dep_reqs_map.entry(key).or_default();
+    if !reqs.iter().any(|r| r == req) {
+        reqs.push(req.to_string());
+    }
+}
+
+/// Splits a package reference from the lock file, `name version (source)`, into its parts.
+fn parse_dep_string(dep: &str) -> (&str, Option<&str>, Option<&str>) {
+    let mut parts = dep.splitn(3, ' ');
+    let name = parts.next().unwrap_or("");
+    let ver = parts.next();
+    let source = parts
+        .next()
+        .map(|source| source.trim_start_matches('(').trim_end_matches(')'));
+
+    (name, ver, source)
+}
+
+fn parse_package(
+    dg: &mut DepGraph,
+    pkg: &Value,
+    root_crates: &[RootCrate],
+    versions: &HashMap<String, Vec<String>>,
+) -> CliResult<()> {
     let name = pkg
         .get("name")
         .expect("No 'name' field in Cargo.lock [package] or [root] table")
@@ -196,14 +1699,30 @@ fn parse_package(dg: &mut DepGraph, pkg: &Value, root_crates: &[RootCrate]) -> C
     if let Some(ref filter_deps) = filter {
         // NOTE: This will filter out root crates if they are passed in. This is useful for e.g.
         // workspaces if the user does not want all roots.
-        if !filter_deps.contains(&name) {
+        if !util::matches_any(filter_deps, &name) {
             return Ok(());
         }
     }
 
     let id = dg.find_or_add(&name, &ver);
 
-    if dg.root_deps_map.contains_key(&name) {
+    // A package overridden by [replace] appears twice in the lock file with the same name and
+    // version: once with a `replace` field pointing to the replacement, and once as the
+    // replacement itself. Both end up in the same node, which gets the source of the replacement.
+    if let Some(replace) = pkg.get("replace").and_then(|replace| replace.as_str()) {
+        dg.nodes[id].source = parse_dep_string(replace).2.map(|source| source.into());
+        dg.nodes[id].patched = true;
+    } else if !dg.nodes[id].patched {
+        dg.nodes[id].source = pkg
+            .get("source")
+            .and_then(|source| source.as_str())
+            .map(|source| source.into());
+    }
+
+    // Root crates have no source, which tells them from crates of the same name that they depend
+    // on in another version.
+    let is_root = pkg.get("source").is_none() && dg.root_deps_map.contains_key(&name);
+    if is_root {
         // If this is a root crate, check that this crate is in `root_crates` with the same version.
         if !root_crates
             .iter()
@@ -217,19 +1736,36 @@ fn parse_package(dg: &mut DepGraph, pkg: &Value, root_crates: &[RootCrate]) -> C
         }
     }
 
-    if let Some(&Value::Array(ref deps)) = pkg.get("dependencies") {
+    if let Some(Value::Array(deps)) = pkg.get("dependencies") {
         for dep in deps {
-            let dep_vec = dep.as_str().unwrap_or("").split(' ').collect::<Vec<_>>();
-            let dep_name = dep_vec[0].to_string();
-            let dep_ver = dep_vec[1];
+            let (dep_name, dep_ver, _) = parse_dep_string(dep.as_str().unwrap_or(""));
+            let dep_name = dep_name.to_string();
+            let dep_ver = match dep_ver {
+                Some(dep_ver) => dep_ver,
+                None => match versions.get(&dep_name).map(|vers| vers.as_slice()) {
+                    Some([dep_ver]) => dep_ver,
+                    _ => {
+                        return Err(CliError::Toml(format!(
+                            "Dependency '{}' of '{}' in Cargo.lock has no version and does not \
+                             match exactly one package",
+                            dep_name, name
+                        )));
+                    }
+                },
+            };
 
             if let Some(ref filter_deps) = filter {
-                if !filter_deps.contains(&dep_name) {
+                if !util::matches_any(filter_deps, &dep_name) {
                     continue;
                 }
             }
 
-            if let Some(dep_kinds_map) = dg.root_deps_map.get(&name) {
+            if let Some(dep_kinds_map) = dg
+                .root_deps_map
+                .get(&name)
+                .filter(|_| is_root)
+                .or_else(|| dg.path_deps_map.get(&name))
+            {
                 if dep_kinds_map.get(&dep_name).is_none() {
                     // This dep was filtered out when adding root dependencies.
                     continue;
@@ -242,3 +1778,296 @@ fn parse_package(dg: &mut DepGraph, pkg: &Value, root_crates: &[RootCrate]) -> C
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CRATES_IO: &str = "registry+https://github.com/rust-lang/crates.io-index";
+
+    /// Parses a lock file for the root crate `app 0.1.0`, which declares `root_deps`.
+    fn parse(lock: &str, root_deps: &[&str]) -> CliResult<DepGraph> {
+        let m = crate::app().get_matches_from(["cargo", "deps"]);
+        let cfg = Config::from_matches(m.subcommand_matches("deps").unwrap())?;
+        let root_crates = vec![RootCrate {
+            name: "app".into(),
+            ver: "0.1.0".into(),
+        }];
+        let mut dep_kinds_map = DepKindsMap::new();
+        for &dep in root_deps {
+            add_kind(&mut dep_kinds_map, dep.into(), DepKind::Regular);
+        }
+        let mut root_deps_map = RootDepsMap::new();
+        root_deps_map.insert("app".into(), dep_kinds_map);
+
+        Project::with_config(cfg)?.parse_lock_file(
+            &toml::from_str(lock)?,
+            &root_crates,
+            root_deps_map,
+            RootDepsMap::new(),
+        )
+    }
+
+    /// Writes a lock file to the temporary directory, to be read on its own like with
+    /// `--lock-only`.
+    fn write_lock(file_name: &str, lock: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "cargo-deps-{}-{}.lock",
+            std::process::id(),
+            file_name
+        ));
+        fs::write(&path, lock).unwrap();
+        path
+    }
+
+    fn lock_only_project() -> Project {
+        let m = crate::app().get_matches_from(["cargo", "deps"]);
+        let cfg = Config::from_matches(m.subcommand_matches("deps").unwrap()).unwrap();
+        Project::with_config(cfg).unwrap()
+    }
+
+    fn children(dg: &DepGraph, name: &str, ver: &str) -> Vec<String> {
+        let id = dg.find(name, ver).unwrap();
+        let mut children: Vec<String> = dg.nodes[id]
+            .children
+            .iter()
+            .map(|&child| format!("{} {}", dg.nodes[child].name, dg.nodes[child].ver))
+            .collect();
+        children.sort();
+        children
+    }
+
+    #[test]
+    fn lock_file_v1() {
+        let lock = format!(
+            r#"
+            [root]
+            name = "app"
+            version = "0.1.0"
+            dependencies = [
+                "foo 1.0.0 ({0})",
+            ]
+
+            [[package]]
+            name = "foo"
+            version = "1.0.0"
+            source = "{0}"
+            dependencies = [
+                "bar 0.2.0 ({0})",
+            ]
+
+            [[package]]
+            name = "bar"
+            version = "0.2.0"
+            source = "{0}"
+
+            [metadata]
+            "checksum bar 0.2.0 ({0})" = "00"
+            "checksum foo 1.0.0 ({0})" = "00"
+            "#,
+            CRATES_IO
+        );
+        let dg = parse(&lock, &["foo"]).unwrap();
+
+        assert_eq!(dg.nodes.len(), 3);
+        assert_eq!(children(&dg, "app", "0.1.0"), ["foo 1.0.0"]);
+        assert_eq!(children(&dg, "foo", "1.0.0"), ["bar 0.2.0"]);
+        let foo = dg.find("foo", "1.0.0").unwrap();
+        assert_eq!(dg.nodes[foo].source.as_deref(), Some(CRATES_IO));
+        assert_eq!(dg.nodes[dg.find("app", "0.1.0").unwrap()].source, None);
+    }
+
+    #[test]
+    fn lock_file_v3() {
+        // Dependencies only have a version if there are several versions of the package.
+        let lock = format!(
+            r#"
+            version = 3
+
+            [[package]]
+            name = "app"
+            version = "0.1.0"
+            dependencies = [
+                "bar 0.1.0",
+                "foo",
+            ]
+
+            [[package]]
+            name = "bar"
+            version = "0.1.0"
+            source = "{0}"
+            checksum = "00"
+
+            [[package]]
+            name = "bar"
+            version = "0.2.0"
+            source = "{0}"
+            checksum = "00"
+
+            [[package]]
+            name = "foo"
+            version = "1.0.0"
+            source = "{0}"
+            checksum = "00"
+            dependencies = [
+                "bar 0.2.0",
+            ]
+            "#,
+            CRATES_IO
+        );
+        let dg = parse(&lock, &["bar", "foo"]).unwrap();
+
+        assert_eq!(dg.nodes.len(), 4);
+        assert_eq!(children(&dg, "app", "0.1.0"), ["bar 0.1.0", "foo 1.0.0"]);
+        assert_eq!(children(&dg, "foo", "1.0.0"), ["bar 0.2.0"]);
+    }
+
+    #[test]
+    fn lock_file_v3_ambiguous_dependency() {
+        let lock = format!(
+            r#"
+            version = 3
+
+            [[package]]
+            name = "app"
+            version = "0.1.0"
+            dependencies = [
+                "bar",
+            ]
+
+            [[package]]
+            name = "bar"
+            version = "0.1.0"
+            source = "{0}"
+
+            [[package]]
+            name = "bar"
+            version = "0.2.0"
+            source = "{0}"
+            "#,
+            CRATES_IO
+        );
+
+        assert!(parse(&lock, &["bar"]).is_err());
+    }
+
+    #[test]
+    fn lock_file_root_version_mismatch() {
+        let lock = r#"
+            version = 3
+
+            [[package]]
+            name = "app"
+            version = "0.2.0"
+            "#;
+
+        assert!(parse(lock, &[]).is_err());
+    }
+
+    #[test]
+    fn lock_file_roots_by_version() {
+        // The member `foo 0.1.0` is a root, although `app` depends on another version of `foo`.
+        let lock = format!(
+            r#"
+            version = 3
+
+            [[package]]
+            name = "app"
+            version = "0.1.0"
+            dependencies = [
+                "foo 1.0.0",
+            ]
+
+            [[package]]
+            name = "foo"
+            version = "0.1.0"
+
+            [[package]]
+            name = "foo"
+            version = "1.0.0"
+            source = "{0}"
+            "#,
+            CRATES_IO
+        );
+        let path = write_lock("roots-by-version", &lock);
+        let dg = lock_only_project().lock_file_graph(path.clone()).unwrap();
+        fs::remove_file(path).unwrap();
+
+        let mut roots: Vec<&String> = dg.root_deps_map.keys().collect();
+        roots.sort();
+        assert_eq!(roots, ["app", "foo"]);
+        assert_eq!(children(&dg, "app", "0.1.0"), ["foo 1.0.0"]);
+    }
+
+    #[test]
+    fn lock_file_diff() {
+        let old = format!(
+            r#"
+            version = 3
+
+            [[package]]
+            name = "app"
+            version = "0.1.0"
+            dependencies = [
+                "bar",
+                "foo",
+            ]
+
+            [[package]]
+            name = "bar"
+            version = "1.0.0"
+            source = "{0}"
+
+            [[package]]
+            name = "foo"
+            version = "1.0.0"
+            source = "{0}"
+            "#,
+            CRATES_IO
+        );
+        let new = format!(
+            r#"
+            version = 3
+
+            [[package]]
+            name = "app"
+            version = "0.1.0"
+            dependencies = [
+                "baz",
+                "foo",
+            ]
+
+            [[package]]
+            name = "baz"
+            version = "1.0.0"
+            source = "{0}"
+
+            [[package]]
+            name = "foo"
+            version = "1.1.0"
+            source = "{0}"
+            "#,
+            CRATES_IO
+        );
+        let old_path = write_lock("diff-old", &old);
+        let new_path = write_lock("diff-new", &new);
+        let dg = lock_only_project().diff_graph(old_path.clone(), new_path.clone());
+        fs::remove_file(old_path).unwrap();
+        fs::remove_file(new_path).unwrap();
+        let dg = dg.unwrap();
+
+        let diff = |name: &str, ver: &str| dg.nodes[dg.find(name, ver).unwrap()].diff.clone();
+        assert_eq!(dg.nodes.len(), 4);
+        assert_eq!(diff("app", "0.1.0"), None);
+        assert_eq!(diff("bar", "1.0.0"), Some(DiffStatus::Removed));
+        assert_eq!(diff("baz", "1.0.0"), Some(DiffStatus::Added));
+        assert_eq!(
+            diff("foo", "1.1.0"),
+            Some(DiffStatus::Changed("1.0.0".into()))
+        );
+        assert_eq!(
+            children(&dg, "app", "0.1.0"),
+            ["bar 1.0.0", "baz 1.0.0", "foo 1.1.0"]
+        );
+    }
+}
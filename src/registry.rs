@@ -0,0 +1,415 @@
+use crate::util;
+use semver::Version;
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::value::Table;
+use toml::Value;
+
+/// The cargo home directory, honoring `CARGO_HOME`.
+pub fn cargo_home() -> Option<PathBuf> {
+    if let Some(home) = env::var_os("CARGO_HOME") {
+        return Some(PathBuf::from(home));
+    }
+
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".cargo"))
+}
+
+/// Finds the manifest of a crate that cargo has unpacked into the local registry cache, or of a
+/// crate in a vendor directory.
+pub fn find_manifest(name: &str, ver: &str, vendor_dir: Option<&Path>) -> Option<PathBuf> {
+    // `cargo vendor` only adds the version to the directory name if there are several versions.
+    if let Some(vendor_dir) = vendor_dir {
+        return [format!("{}-{}", name, ver), name.to_string()]
+            .iter()
+            .map(|dir| vendor_dir.join(dir).join("Cargo.toml"))
+            .find(|path| {
+                util::toml_from_file(path)
+                    .ok()
+                    .and_then(|manifest| {
+                        let version = manifest.get("package")?.get("version")?.as_str()?;
+                        Some(version == ver)
+                    })
+                    .unwrap_or(false)
+            });
+    }
+
+    let src = cargo_home()?.join("registry").join("src");
+
+    // There is one directory per registry index.
+    fs::read_dir(src)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            entry
+                .path()
+                .join(format!("{}-{}", name, ver))
+                .join("Cargo.toml")
+        })
+        .find(|path| path.is_file())
+}
+
+/// Reads the `[package]` table of a crate from the local registry cache or a vendor directory.
+pub fn package_table(name: &str, ver: &str, vendor_dir: Option<&Path>) -> Option<Table> {
+    let manifest = util::toml_from_file(find_manifest(name, ver, vendor_dir)?).ok()?;
+
+    manifest.get("package")?.as_table().cloned()
+}
+
+/// The license expression of a crate. Crates with a custom license file report that instead.
+pub fn license(name: &str, ver: &str, vendor_dir: Option<&Path>) -> Option<String> {
+    let package = package_table(name, ver, vendor_dir)?;
+
+    if let Some(license) = package.get("license").and_then(|l| l.as_str()) {
+        return Some(license.into());
+    }
+
+    package
+        .get("license-file")
+        .and_then(|l| l.as_str())
+        .map(|file| format!("see {}", file))
+}
+
+/// The `repository` of a crate, usually the URL of its source code.
+pub fn repository(name: &str, ver: &str, vendor_dir: Option<&Path>) -> Option<String> {
+    let package = package_table(name, ver, vendor_dir)?;
+
+    package
+        .get("repository")
+        .and_then(|r| r.as_str())
+        .map(String::from)
+}
+
+/// The `rust-version` of a crate in the local registry cache or a vendor directory.
+pub fn rust_version(name: &str, ver: &str, vendor_dir: Option<&Path>) -> Option<String> {
+    let package = package_table(name, ver, vendor_dir)?;
+
+    package
+        .get("rust-version")
+        .and_then(|r| r.as_str())
+        .map(String::from)
+}
+
+/// The `description` of a crate in the local registry cache or a vendor directory.
+pub fn description(name: &str, ver: &str, vendor_dir: Option<&Path>) -> Option<String> {
+    let package = package_table(name, ver, vendor_dir)?;
+
+    package
+        .get("description")
+        .and_then(|d| d.as_str())
+        .map(String::from)
+}
+
+/// The size of the `.crate` file of a crate in the local registry cache, as it was downloaded.
+pub fn download_size(name: &str, ver: &str) -> Option<u64> {
+    let cache = cargo_home()?.join("registry").join("cache");
+
+    // There is one directory per registry index.
+    fs::read_dir(cache)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .find_map(|entry| {
+            let path = entry.path().join(format!("{}-{}.crate", name, ver));
+            fs::metadata(path).ok().map(|metadata| metadata.len())
+        })
+}
+
+/// The size of the files of a crate unpacked into the local registry cache or a vendor directory.
+pub fn unpacked_size(name: &str, ver: &str, vendor_dir: Option<&Path>) -> Option<u64> {
+    let manifest = find_manifest(name, ver, vendor_dir)?;
+
+    let mut size = 0;
+    let mut dirs = vec![manifest.parent()?.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir).ok()?.filter_map(|entry| entry.ok()) {
+            let metadata = entry.metadata().ok()?;
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                size += metadata.len();
+            }
+        }
+    }
+    Some(size)
+}
+
+fn crates_io_request(url: &str) -> Option<serde_json::Value> {
+    serde_json::from_slice(&curl(url)?).ok()
+}
+
+fn curl(url: &str) -> Option<Vec<u8>> {
+    // crates.io rejects requests without a user agent.
+    let user_agent = concat!("cargo-deps/", env!("CARGO_PKG_VERSION"));
+    util::run(
+        OsStr::new("curl"),
+        None,
+        ["--silent", "--fail", "--user-agent", user_agent, url],
+    )
+    .ok()
+}
+
+/// A version of a crate in the crates.io index.
+#[derive(Clone, Debug)]
+pub struct IndexVersion {
+    pub version: String,
+    pub yanked: bool,
+    /// The `rust-version` of the version, which older entries do not have.
+    pub rust_version: Option<String>,
+}
+
+/// The versions of a crate in the crates.io index. They are read from `index_path` if it is given,
+/// a local clone of the index. Otherwise they are asked from the sparse index with curl, and
+/// failing that read from the copy of the index that cargo keeps in its home directory, which is
+/// only as recent as the last time cargo updated it.
+pub fn index_versions(name: &str, index_path: Option<&Path>) -> Option<Vec<IndexVersion>> {
+    let name = name.to_lowercase();
+    let path = index_file(&name);
+
+    let entries = if let Some(index_path) = index_path {
+        fs::read(index_path.join(&path)).ok()?
+    } else if let Some(entries) = curl(&format!("https://index.crates.io/{}", path)) {
+        entries
+    } else {
+        // The cache has a binary header, and separates the versions and their entries with NUL
+        // bytes rather than newlines.
+        let index_dirs = fs::read_dir(cargo_home()?.join("registry").join("index")).ok()?;
+        index_dirs
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                let dir_name = entry.file_name();
+                let dir_name = dir_name.to_string_lossy();
+                dir_name.starts_with("index.crates.io-") || dir_name.starts_with("github.com-")
+            })
+            .find_map(|entry| fs::read(entry.path().join(".cache").join(&path)).ok())?
+            .into_iter()
+            .map(|b| if b == 0 { b'\n' } else { b })
+            .collect()
+    };
+
+    let versions: Vec<IndexVersion> = entries
+        .split(|&b| b == b'\n')
+        .filter(|line| line.starts_with(b"{"))
+        .filter_map(|line| {
+            let entry: serde_json::Value = serde_json::from_slice(line).ok()?;
+            Some(IndexVersion {
+                version: entry["vers"].as_str()?.to_string(),
+                yanked: entry["yanked"].as_bool().unwrap_or(false),
+                rust_version: entry["rust_version"].as_str().map(String::from),
+            })
+        })
+        .collect();
+    if versions.is_empty() {
+        None
+    } else {
+        Some(versions)
+    }
+}
+
+/// The newest version that is neither yanked nor a pre-release, if there is one.
+pub fn latest_version(versions: &[IndexVersion]) -> Option<Version> {
+    versions
+        .iter()
+        .filter(|v| !v.yanked)
+        .filter_map(|v| Version::parse(&v.version).ok())
+        .filter(|v| v.pre.is_empty())
+        .max()
+}
+
+/// The path of the file of a crate in the index, which depends on the length of its name.
+fn index_file(name: &str) -> String {
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+/// The lookups in the crates.io index and API, which are remembered so that each crate is only
+/// asked for once, however many times and in however many passes it comes up.
+#[derive(Debug, Default)]
+pub struct CratesIo {
+    index: HashMap<String, Option<Vec<IndexVersion>>>,
+    responses: HashMap<String, Option<serde_json::Value>>,
+}
+
+impl CratesIo {
+    /// The versions of a crate in the crates.io index, as read by [`index_versions`].
+    pub fn index_versions(
+        &mut self,
+        name: &str,
+        index_path: Option<&Path>,
+    ) -> Option<Vec<IndexVersion>> {
+        self.index
+            .entry(name.to_string())
+            .or_insert_with(|| index_versions(name, index_path))
+            .clone()
+    }
+
+    /// Asks the crates.io API for the license expression of a version of a crate, with curl.
+    /// Returns `None` if curl is not installed, the request fails or the version has no license.
+    pub fn license(&mut self, name: &str, ver: &str) -> Option<String> {
+        let response = self.request(format!("https://crates.io/api/v1/crates/{}/{}", name, ver))?;
+
+        response["version"]["license"].as_str().map(String::from)
+    }
+
+    /// Asks the crates.io API for the size of the `.crate` file of a version of a crate, with
+    /// curl.
+    pub fn download_size(&mut self, name: &str, ver: &str) -> Option<u64> {
+        let response = self.request(format!("https://crates.io/api/v1/crates/{}/{}", name, ver))?;
+
+        response["version"]["crate_size"].as_u64()
+    }
+
+    /// Asks the crates.io API for the description of a crate, with curl. Returns `None` if curl
+    /// is not installed or the request fails.
+    pub fn description(&mut self, name: &str) -> Option<String> {
+        let response = self.request(format!("https://crates.io/api/v1/crates/{}", name))?;
+
+        response["crate"]["description"].as_str().map(String::from)
+    }
+
+    /// Asks the crates.io API for the number of times all versions of a crate have been
+    /// downloaded, with curl.
+    pub fn downloads(&mut self, name: &str) -> Option<u64> {
+        let response = self.request(format!("https://crates.io/api/v1/crates/{}", name))?;
+
+        response["crate"]["downloads"].as_u64()
+    }
+
+    /// The response of the crates.io API at `url`, which is shared by the lookups that read
+    /// different fields of it.
+    fn request(&mut self, url: String) -> Option<&serde_json::Value> {
+        self.responses
+            .entry(url)
+            .or_insert_with_key(|url| crates_io_request(url))
+            .as_ref()
+    }
+}
+
+/// The index URL of a lock file `source`, or `None` if the source is not a registry. Sparse
+/// registries keep their `sparse+` prefix, as they do in the cargo configuration.
+pub fn index_url(source: &str) -> Option<String> {
+    if source.starts_with("registry+") || source.starts_with("sparse+") {
+        Some(normalize_index(source))
+    } else {
+        None
+    }
+}
+
+/// Maps the index URLs of the registries in the cargo configuration to their names. The
+/// configuration is read from `dir` and its parents, the cargo home directory and
+/// `CARGO_REGISTRIES_<NAME>_INDEX` environment variables, in order of decreasing priority.
+pub fn registry_names(dir: &Path) -> HashMap<String, String> {
+    let mut names = HashMap::new();
+
+    for (key, index) in env::vars() {
+        if let Some(name) = key
+            .strip_prefix("CARGO_REGISTRIES_")
+            .and_then(|key| key.strip_suffix("_INDEX"))
+        {
+            names.insert(
+                normalize_index(&index),
+                name.to_lowercase().replace('_', "-"),
+            );
+        }
+    }
+
+    for (_, config) in cargo_configs(dir) {
+        if let Some(Value::Table(registries)) = config.get("registries") {
+            for (name, registry) in registries {
+                if let Some(index) = registry.get("index").and_then(|i| i.as_str()) {
+                    names
+                        .entry(normalize_index(index))
+                        .or_insert_with(|| name.clone());
+                }
+            }
+        }
+    }
+
+    names
+}
+
+/// The source that replaces another one, from the `[source]` tables of the cargo configuration.
+#[derive(Clone, Debug)]
+pub struct SourceReplacement {
+    pub name: String,
+    /// The directory of a directory source, like the `vendor` directory of `cargo vendor`.
+    pub directory: Option<PathBuf>,
+}
+
+/// Maps the names of replaced sources (e.g. `crates-io`) to the source that ends up replacing
+/// them, following chains of replacements.
+pub fn source_replacements(dir: &Path) -> HashMap<String, SourceReplacement> {
+    let mut replace_with = HashMap::new();
+    let mut directories = HashMap::new();
+    for (base_dir, config) in cargo_configs(dir) {
+        if let Some(Value::Table(sources)) = config.get("source") {
+            for (name, source) in sources {
+                if let Some(replacement) = source.get("replace-with").and_then(|r| r.as_str()) {
+                    replace_with
+                        .entry(name.clone())
+                        .or_insert_with(|| replacement.to_string());
+                }
+                if let Some(directory) = source.get("directory").and_then(|d| d.as_str()) {
+                    directories
+                        .entry(name.clone())
+                        .or_insert_with(|| base_dir.join(directory));
+                }
+            }
+        }
+    }
+
+    let mut replacements = HashMap::new();
+    for name in replace_with.keys() {
+        let mut replacement = name;
+        // Cargo rejects cycles, but do not loop forever on a bad configuration.
+        for _ in 0..replace_with.len() {
+            match replace_with.get(replacement) {
+                Some(next) => replacement = next,
+                None => break,
+            }
+        }
+
+        replacements.insert(
+            name.clone(),
+            SourceReplacement {
+                name: replacement.clone(),
+                directory: directories.get(replacement).cloned(),
+            },
+        );
+    }
+
+    replacements
+}
+
+/// The cargo configuration files that apply in `dir`, in order of decreasing priority, with the
+/// directory that relative paths in them are resolved against.
+fn cargo_configs(dir: &Path) -> Vec<(PathBuf, Value)> {
+    let mut config_dirs: Vec<PathBuf> = dir.ancestors().map(|dir| dir.join(".cargo")).collect();
+    config_dirs.extend(cargo_home());
+
+    let mut configs = vec![];
+    for config_dir in config_dirs {
+        for file in &["config.toml", "config"] {
+            if let Ok(config) = util::toml_from_file(config_dir.join(file)) {
+                let base_dir = config_dir.parent().unwrap_or(&config_dir).to_path_buf();
+                configs.push((base_dir, config));
+            }
+        }
+    }
+
+    configs
+}
+
+fn normalize_index(index: &str) -> String {
+    index
+        .trim_start_matches("registry+")
+        .trim_end_matches('/')
+        .to_string()
+}
@@ -0,0 +1,72 @@
+use crate::error::CliResult;
+use crate::graph::{DepGraph, Node};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Writes one row per edge: parent, parent version, child, child version and the edge kind.
+pub fn render<W: Write>(dg: &DepGraph, w: &mut W) -> CliResult<()> {
+    writeln!(w, "parent,parent_version,child,child_version,kind")?;
+
+    for ed in &dg.edges {
+        if !(dg.is_shown(ed.0) && dg.is_shown(ed.1)) {
+            continue;
+        }
+
+        let parent = &dg.nodes[ed.0];
+        let child = &dg.nodes[ed.1];
+        writeln!(
+            w,
+            "{},{},{},{},{}",
+            escape(&parent.name),
+            escape(&parent.ver),
+            escape(&child.name),
+            escape(&child.ver),
+            ed.kind(dg)?.name()
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes an adjacency matrix with one row and column per crate. The cell in row `parent` and
+/// column `child` holds the kind of the edge between them, and is empty if there is no edge.
+pub fn render_matrix<W: Write>(dg: &DepGraph, w: &mut W) -> CliResult<()> {
+    let ids: Vec<Node> = (0..dg.nodes.len()).filter(|&i| dg.is_shown(i)).collect();
+    let labels: Vec<String> = ids
+        .iter()
+        .map(|&i| format!("{} v{}", dg.nodes[i].name, dg.nodes[i].ver))
+        .collect();
+
+    let mut kinds = HashMap::new();
+    for ed in &dg.edges {
+        if dg.is_shown(ed.0) && dg.is_shown(ed.1) {
+            kinds.insert((ed.0, ed.1), ed.kind(dg)?.name());
+        }
+    }
+
+    write!(w, "crate")?;
+    for label in &labels {
+        write!(w, ",{}", escape(label))?;
+    }
+    writeln!(w)?;
+
+    for (&parent, label) in ids.iter().zip(&labels) {
+        write!(w, "{}", escape(label))?;
+        for &child in &ids {
+            write!(w, ",{}", kinds.get(&(parent, child)).unwrap_or(&""))?;
+        }
+        writeln!(w)?;
+    }
+
+    Ok(())
+}
+
+/// Quotes a field if it contains characters that are special in CSV.
+fn escape(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
@@ -0,0 +1,88 @@
+use crate::dep::{DepKind, ResolvedDep};
+use crate::error::CliResult;
+use crate::graph::{DepGraph, Edge};
+use serde_json::{json, Value};
+use std::io::Write;
+
+/// Writes a CycloneDX 1.5 JSON SBOM of the shown packages and their dependency relationships.
+pub fn render<W: Write>(dg: &DepGraph, w: &mut W) -> CliResult<()> {
+    let roots: Vec<usize> = (0..dg.nodes.len())
+        .filter(|&i| dg.is_shown(i) && dg.root_deps_map.contains_key(&dg.nodes[i].name))
+        .collect();
+
+    let mut metadata = json!({
+        "tools": [{
+            "name": "cargo-deps",
+            "version": env!("CARGO_PKG_VERSION"),
+        }],
+    });
+    // A single root crate describes the whole BOM. With several roots (e.g. in a workspace) they
+    // are listed as components instead.
+    if roots.len() == 1 {
+        metadata["component"] = component(&dg.nodes[roots[0]], "application", None);
+    }
+
+    let mut components = vec![];
+    for (i, dep) in dg.nodes.iter().enumerate() {
+        if !dg.is_shown(i) || roots.len() == 1 && roots[0] == i {
+            continue;
+        }
+
+        if roots.contains(&i) {
+            components.push(component(dep, "application", None));
+        } else {
+            let scope = match dep.kind() {
+                DepKind::Regular => "required",
+                DepKind::Optional => "optional",
+                DepKind::Build | DepKind::Dev | DepKind::Unknown => "excluded",
+            };
+            components.push(component(dep, "library", Some(scope)));
+        }
+    }
+
+    let dependencies: Vec<Value> = dg
+        .nodes
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| dg.is_shown(i))
+        .map(|(i, dep)| {
+            // The edges point the other way in an inverted graph.
+            let depends_on: Vec<String> = dg
+                .edges
+                .iter()
+                .map(|ed| ed.declared(dg))
+                .filter(|&Edge(parent, child)| parent == i && dg.is_shown(child))
+                .map(|Edge(_, child)| dg.nodes[child].purl())
+                .collect();
+            json!({ "ref": dep.purl(), "dependsOn": depends_on })
+        })
+        .collect();
+
+    let bom = json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": metadata,
+        "components": components,
+        "dependencies": dependencies,
+    });
+
+    serde_json::to_writer_pretty(&mut *w, &bom)?;
+    writeln!(w)?;
+
+    Ok(())
+}
+
+fn component(dep: &ResolvedDep, component_type: &str, scope: Option<&str>) -> Value {
+    let mut component = json!({
+        "type": component_type,
+        "bom-ref": dep.purl(),
+        "name": dep.name,
+        "version": dep.ver,
+        "purl": dep.purl(),
+    });
+    if let Some(scope) = scope {
+        component["scope"] = json!(scope);
+    }
+    component
+}
@@ -0,0 +1,44 @@
+use crate::dep::ResolvedDep;
+use crate::error::CliResult;
+use crate::graph::DepGraph;
+use std::io::Write;
+
+/// Writes Cypher `MERGE` statements that create a `Crate` node per package and a `DEPENDS_ON`
+/// relationship per edge. Using `MERGE` means the output of several projects can be loaded into the
+/// same database, with shared crates ending up as the same node.
+pub fn render<W: Write>(dg: &DepGraph, w: &mut W) -> CliResult<()> {
+    for (i, dep) in dg.nodes.iter().enumerate() {
+        if dg.is_shown(i) {
+            writeln!(w, "MERGE (:Crate {});", properties(dep))?;
+        }
+    }
+
+    for ed in &dg.edges {
+        if !(dg.is_shown(ed.0) && dg.is_shown(ed.1)) {
+            continue;
+        }
+
+        writeln!(
+            w,
+            "MATCH (a:Crate {}), (b:Crate {}) MERGE (a)-[:DEPENDS_ON {{kind: '{}'}}]->(b);",
+            properties(&dg.nodes[ed.0]),
+            properties(&dg.nodes[ed.1]),
+            ed.kind(dg)?.name()
+        )?;
+    }
+
+    Ok(())
+}
+
+/// The properties identifying a crate node.
+fn properties(dep: &ResolvedDep) -> String {
+    format!(
+        "{{name: '{}', version: '{}'}}",
+        escape(&dep.name),
+        escape(&dep.ver)
+    )
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\'', "\\'")
+}
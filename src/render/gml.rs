@@ -0,0 +1,53 @@
+use crate::error::CliResult;
+use crate::graph::DepGraph;
+use std::io::Write;
+
+/// Writes the graph in the Graph Modelling Language. Node ids are the node indices, as GML requires
+/// integer ids.
+pub fn render<W: Write>(dg: &DepGraph, w: &mut W) -> CliResult<()> {
+    writeln!(w, "graph [")?;
+    writeln!(w, "  directed 1")?;
+
+    for (i, dep) in dg.nodes.iter().enumerate() {
+        if !dg.is_shown(i) {
+            continue;
+        }
+
+        writeln!(w, "  node [")?;
+        writeln!(w, "    id {}", i)?;
+        writeln!(
+            w,
+            "    label \"{} v{}\"",
+            escape(&dep.name),
+            escape(&dep.ver)
+        )?;
+        writeln!(w, "    name \"{}\"", escape(&dep.name))?;
+        writeln!(w, "    version \"{}\"", escape(&dep.ver))?;
+        writeln!(w, "    kind \"{}\"", dep.kind().name())?;
+        if let Some(registry) = &dep.registry {
+            writeln!(w, "    registry \"{}\"", escape(registry))?;
+        }
+        writeln!(w, "  ]")?;
+    }
+
+    for ed in &dg.edges {
+        if !(dg.is_shown(ed.0) && dg.is_shown(ed.1)) {
+            continue;
+        }
+
+        writeln!(w, "  edge [")?;
+        writeln!(w, "    source {}", ed.0)?;
+        writeln!(w, "    target {}", ed.1)?;
+        writeln!(w, "    kind \"{}\"", ed.kind(dg)?.name())?;
+        writeln!(w, "  ]")?;
+    }
+
+    writeln!(w, "]")?;
+
+    Ok(())
+}
+
+/// GML strings cannot contain double quotes, so they are written as HTML entities.
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('"', "&quot;")
+}
@@ -0,0 +1,68 @@
+use crate::error::{CliError, CliResult};
+use std::env;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+#[cfg(windows)]
+const DOT_BINARY: &str = "dot.exe";
+#[cfg(not(windows))]
+const DOT_BINARY: &str = "dot";
+
+/// Searches the directories in `PATH` for the graphviz `dot` binary.
+fn find_dot() -> Option<PathBuf> {
+    let paths = env::var_os("PATH")?;
+
+    env::split_paths(&paths)
+        .map(|dir| dir.join(DOT_BINARY))
+        .find(|path| path.is_file())
+}
+
+/// Pipes the DOT source through `dot -T<output_type>` and writes the result to `w`. With a layout
+/// engine, `dot` is told to lay the graph out with it.
+pub fn convert<W: Write>(
+    dot: &[u8],
+    output_type: &str,
+    layout: Option<&str>,
+    w: &mut W,
+) -> CliResult<()> {
+    let dot_path = find_dot().ok_or_else(|| {
+        CliError::Generic(format!(
+            "Could not find graphviz '{}' on PATH, which is required for {} output. \
+             Install graphviz or use '--format dot' and render the graph yourself",
+            DOT_BINARY, output_type
+        ))
+    })?;
+
+    let mut command = Command::new(&dot_path);
+    if let Some(layout) = layout {
+        command.arg(format!("-K{}", layout));
+    }
+    let mut child = command
+        .arg(format!("-T{}", output_type))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // `dot` reads its whole input before producing any output, so the input can be written in
+    // one go without deadlocking on the output pipe.
+    child
+        .stdin
+        .take()
+        .expect("Failed to open stdin of graphviz")
+        .write_all(dot)?;
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(CliError::Generic(format!(
+            "graphviz exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    w.write_all(&output.stdout)?;
+
+    Ok(())
+}
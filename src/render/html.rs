@@ -0,0 +1,199 @@
+use crate::dep::DepKind;
+use crate::error::CliResult;
+use crate::graph::DepGraph;
+use serde_json::json;
+use std::io::Write;
+
+/// Writes a standalone HTML page that lays out and draws the graph in the browser, with pan (drag)
+/// and zoom (mouse wheel). Nothing is loaded from the network.
+pub fn render<W: Write>(dg: &DepGraph, w: &mut W) -> CliResult<()> {
+    // Longest distance from a node without parents. The edges are in topological order, so a
+    // single pass is enough. The back edges of dependency cycles come last, and are left out.
+    let back_edges = dg.back_edges();
+    let mut depths = vec![0; dg.nodes.len()];
+    for ed in &dg.edges {
+        if dg.is_shown(ed.0) && dg.is_shown(ed.1) && !back_edges.contains(ed) {
+            depths[ed.1] = depths[ed.1].max(depths[ed.0] + 1);
+        }
+    }
+
+    let nodes: Vec<_> = dg
+        .nodes
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| dg.is_shown(i))
+        .map(|(i, dep)| {
+            json!({
+                "id": i,
+                "name": dep.name,
+                "version": dep.ver,
+                "kind": dep.kind().name(),
+                "root": dg.root_deps_map.contains_key(&dep.name),
+                "depth": depths[i],
+            })
+        })
+        .collect();
+
+    let mut edges = vec![];
+    for ed in &dg.edges {
+        if dg.is_shown(ed.0) && dg.is_shown(ed.1) {
+            edges.push(json!({
+                "from": ed.0,
+                "to": ed.1,
+                "kind": ed.kind(dg)?.name(),
+            }));
+        }
+    }
+
+    let colors: serde_json::Map<String, serde_json::Value> = DepKind::ALL
+        .iter()
+        .map(|&kind| (kind.name().into(), dg.cfg.color(kind).into()))
+        .collect();
+
+    // Crate names cannot contain '<', but escape it anyway so the data can never close the
+    // script tag.
+    let data = json!({ "nodes": nodes, "edges": edges, "colors": colors })
+        .to_string()
+        .replace('<', "\\u003c");
+
+    write!(w, "{}", HTML_HEAD)?;
+    writeln!(w, "const GRAPH = {};", data)?;
+    write!(w, "{}", HTML_TAIL)?;
+
+    Ok(())
+}
+
+const HTML_HEAD: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>dependencies</title>
+<style>
+  html, body { margin: 0; height: 100%; overflow: hidden; font-family: sans-serif; }
+  svg { width: 100%; height: 100%; cursor: grab; }
+  svg.dragging { cursor: grabbing; }
+  .node rect { fill: white; stroke-width: 1.5; }
+  .node.root rect { stroke-width: 3; }
+  .node text { font-size: 12px; text-anchor: middle; dominant-baseline: central; }
+  .edge { fill: none; stroke-width: 1.2; }
+  .edge.dashed { stroke-dasharray: 5 3; }
+  .dim { opacity: 0.15; }
+</style>
+</head>
+<body>
+<svg id="graph"><g id="viewport"></g></svg>
+<script>
+"#;
+
+const HTML_TAIL: &str = r#"
+const COLORS = GRAPH.colors;
+const NODE_WIDTH = 160, NODE_HEIGHT = 28, X_GAP = 30, Y_GAP = 80;
+const SVG_NS = "http://www.w3.org/2000/svg";
+
+const svg = document.getElementById("graph");
+const viewport = document.getElementById("viewport");
+
+function el(name, attrs, parent) {
+  const e = document.createElementNS(SVG_NS, name);
+  for (const k in attrs) e.setAttribute(k, attrs[k]);
+  parent.appendChild(e);
+  return e;
+}
+
+// Lay out the nodes in rows by depth, centering each row.
+const rows = [];
+for (const n of GRAPH.nodes) (rows[n.depth] = rows[n.depth] || []).push(n);
+const pos = {};
+rows.forEach((row, depth) => {
+  row.sort((a, b) => a.name.localeCompare(b.name));
+  const width = row.length * (NODE_WIDTH + X_GAP);
+  row.forEach((n, i) => {
+    pos[n.id] = { x: i * (NODE_WIDTH + X_GAP) - width / 2, y: depth * (NODE_HEIGHT + Y_GAP) };
+  });
+});
+
+const defs = el("defs", {}, viewport);
+for (const kind in COLORS) {
+  const marker = el("marker", {
+    id: "arrow-" + kind, viewBox: "0 0 10 10", refX: 10, refY: 5,
+    markerWidth: 6, markerHeight: 6, orient: "auto",
+  }, defs);
+  el("path", { d: "M0,0 L10,5 L0,10 z", fill: COLORS[kind] }, marker);
+}
+
+const edgeEls = [];
+for (const e of GRAPH.edges) {
+  const a = pos[e.from], b = pos[e.to];
+  const x1 = a.x + NODE_WIDTH / 2, y1 = a.y + NODE_HEIGHT;
+  const x2 = b.x + NODE_WIDTH / 2, y2 = b.y;
+  const my = (y1 + y2) / 2;
+  const path = el("path", {
+    class: "edge" + (e.kind === "regular" ? "" : " dashed"),
+    d: `M${x1},${y1} C${x1},${my} ${x2},${my} ${x2},${y2}`,
+    stroke: COLORS[e.kind],
+    "marker-end": `url(#arrow-${e.kind})`,
+  }, viewport);
+  edgeEls.push({ edge: e, el: path });
+}
+
+const nodeEls = {};
+for (const n of GRAPH.nodes) {
+  const p = pos[n.id];
+  const g = el("g", {
+    class: "node" + (n.root ? " root" : ""),
+    transform: `translate(${p.x},${p.y})`,
+  }, viewport);
+  el("rect", { width: NODE_WIDTH, height: NODE_HEIGHT, rx: 4, stroke: COLORS[n.kind] }, g);
+  el("text", { x: NODE_WIDTH / 2, y: NODE_HEIGHT / 2 }, g).textContent = n.name + " v" + n.version;
+  el("title", {}, g).textContent = `${n.name} v${n.version} (${n.kind})`;
+  nodeEls[n.id] = g;
+
+  // Hovering a node dims everything that is not directly connected to it.
+  g.addEventListener("mouseenter", () => {
+    const keep = new Set([n.id]);
+    for (const { edge, el } of edgeEls) {
+      const connected = edge.from === n.id || edge.to === n.id;
+      if (connected) { keep.add(edge.from); keep.add(edge.to); }
+      el.classList.toggle("dim", !connected);
+    }
+    for (const id in nodeEls) nodeEls[id].classList.toggle("dim", !keep.has(Number(id)));
+  });
+  g.addEventListener("mouseleave", () => {
+    for (const { el } of edgeEls) el.classList.remove("dim");
+    for (const id in nodeEls) nodeEls[id].classList.remove("dim");
+  });
+}
+
+// Pan and zoom by transforming the viewport group.
+let scale = 1, tx = svg.clientWidth / 2, ty = 40;
+function update() {
+  viewport.setAttribute("transform", `translate(${tx},${ty}) scale(${scale})`);
+}
+svg.addEventListener("wheel", ev => {
+  ev.preventDefault();
+  const factor = Math.exp(-ev.deltaY * 0.001);
+  tx = ev.clientX - (ev.clientX - tx) * factor;
+  ty = ev.clientY - (ev.clientY - ty) * factor;
+  scale *= factor;
+  update();
+}, { passive: false });
+let drag = null;
+svg.addEventListener("mousedown", ev => {
+  drag = { x: ev.clientX - tx, y: ev.clientY - ty };
+  svg.classList.add("dragging");
+});
+window.addEventListener("mousemove", ev => {
+  if (!drag) return;
+  tx = ev.clientX - drag.x;
+  ty = ev.clientY - drag.y;
+  update();
+});
+window.addEventListener("mouseup", () => {
+  drag = null;
+  svg.classList.remove("dragging");
+});
+update();
+</script>
+</body>
+</html>
+"#;
@@ -0,0 +1,80 @@
+use crate::error::CliResult;
+use crate::graph::{DepGraph, Node};
+use std::io::Write;
+
+/// Writes tables of the direct and transitive dependencies of the root crates.
+pub fn render<W: Write>(dg: &DepGraph, w: &mut W) -> CliResult<()> {
+    let is_root = |id: Node| dg.root_deps_map.contains_key(&dg.nodes[id].name);
+
+    let mut roots = vec![];
+    let mut direct = vec![];
+    let mut transitive = vec![];
+    for id in 0..dg.nodes.len() {
+        if !dg.is_shown(id) {
+            continue;
+        }
+
+        if is_root(id) {
+            roots.push(id);
+        } else if dg.nodes[id]
+            .parents
+            .iter()
+            .any(|&parent| is_root(parent) && dg.is_shown(parent))
+        {
+            direct.push(id);
+        } else {
+            transitive.push(id);
+        }
+    }
+
+    let by_name = |&id: &Node| (&dg.nodes[id].name, &dg.nodes[id].ver);
+    roots.sort_by_key(by_name);
+    direct.sort_by_key(by_name);
+    transitive.sort_by_key(by_name);
+
+    writeln!(w, "# Dependencies")?;
+    writeln!(w)?;
+    for &id in &roots {
+        writeln!(w, "* `{} v{}`", dg.nodes[id].name, dg.nodes[id].ver)?;
+    }
+
+    write_table(dg, "Direct dependencies", &direct, w)?;
+    write_table(dg, "Transitive dependencies", &transitive, w)?;
+
+    Ok(())
+}
+
+fn write_table<W: Write>(dg: &DepGraph, title: &str, ids: &[Node], w: &mut W) -> CliResult<()> {
+    writeln!(w)?;
+    writeln!(w, "## {} ({})", title, ids.len())?;
+    writeln!(w)?;
+
+    if ids.is_empty() {
+        writeln!(w, "None.")?;
+        return Ok(());
+    }
+
+    writeln!(w, "| Name | Version | Kind | Parents |")?;
+    writeln!(w, "|------|---------|------|---------|")?;
+    for &id in ids {
+        let dep = &dg.nodes[id];
+        let mut parents: Vec<String> = dep
+            .parents
+            .iter()
+            .filter(|&&parent| dg.is_shown(parent))
+            .map(|&parent| format!("`{} v{}`", dg.nodes[parent].name, dg.nodes[parent].ver))
+            .collect();
+        parents.sort();
+
+        writeln!(
+            w,
+            "| `{}` | {} | {} | {} |",
+            dep.name,
+            dep.ver,
+            dep.kind().name(),
+            parents.join(", ")
+        )?;
+    }
+
+    Ok(())
+}
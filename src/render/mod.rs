@@ -0,0 +1,118 @@
+use crate::error::CliResult;
+use crate::graph::DepGraph;
+use std::io::Write;
+use std::path::Path;
+
+mod csv;
+mod cyclonedx;
+mod cypher;
+mod gml;
+mod graphviz;
+mod html;
+mod markdown;
+mod spdx;
+mod template;
+mod tgf;
+mod tree;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Format {
+    Dot,
+    Svg,
+    Png,
+    Pdf,
+    Tree,
+    Html,
+    Csv,
+    Matrix,
+    Tgf,
+    CycloneDx,
+    Spdx,
+    SpdxJson,
+    Markdown,
+    Gml,
+    Cypher,
+}
+
+impl Format {
+    /// Names accepted by `--format`.
+    pub const NAMES: &'static [&'static str] = &[
+        "dot",
+        "svg",
+        "png",
+        "pdf",
+        "tree",
+        "html",
+        "csv",
+        "matrix",
+        "tgf",
+        "cyclonedx",
+        "spdx",
+        "spdx-json",
+        "markdown",
+        "gml",
+        "cypher",
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dot" | "gv" => Some(Format::Dot),
+            "svg" => Some(Format::Svg),
+            "png" => Some(Format::Png),
+            "pdf" => Some(Format::Pdf),
+            "tree" => Some(Format::Tree),
+            "html" | "htm" => Some(Format::Html),
+            "csv" => Some(Format::Csv),
+            "matrix" => Some(Format::Matrix),
+            "tgf" => Some(Format::Tgf),
+            "cyclonedx" => Some(Format::CycloneDx),
+            "spdx" => Some(Format::Spdx),
+            "spdx-json" => Some(Format::SpdxJson),
+            "markdown" | "md" => Some(Format::Markdown),
+            "gml" => Some(Format::Gml),
+            "cypher" => Some(Format::Cypher),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the extension of the output file, if it has a known one.
+    pub fn from_path(path: &str) -> Option<Self> {
+        Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| Self::from_name(&ext.to_lowercase()))
+    }
+}
+
+/// Renders the graph in the given format, or with the template of `--template`.
+pub fn render<W: Write>(graph: DepGraph, format: Format, w: &mut W) -> CliResult<()> {
+    if let Some(path) = &graph.cfg.template {
+        return template::render(&graph, path, w);
+    }
+
+    match format {
+        Format::Dot => graph.render_to(w),
+        Format::Svg => render_with_graphviz(graph, "svg", w),
+        Format::Png => render_with_graphviz(graph, "png", w),
+        Format::Pdf => render_with_graphviz(graph, "pdf", w),
+        Format::Tree => tree::render(&graph, w),
+        Format::Html => html::render(&graph, w),
+        Format::Csv => csv::render(&graph, w),
+        Format::Matrix => csv::render_matrix(&graph, w),
+        Format::Tgf => tgf::render(&graph, w),
+        Format::CycloneDx => cyclonedx::render(&graph, w),
+        Format::Spdx => spdx::render_tag_value(&graph, w),
+        Format::SpdxJson => spdx::render_json(&graph, w),
+        Format::Markdown => markdown::render(&graph, w),
+        Format::Gml => gml::render(&graph, w),
+        Format::Cypher => cypher::render(&graph, w),
+    }
+}
+
+/// Renders the graph to DOT and converts it with graphviz to the given `-T` output type.
+fn render_with_graphviz<W: Write>(graph: DepGraph, output_type: &str, w: &mut W) -> CliResult<()> {
+    let layout = graph.cfg.layout.clone();
+    let mut dot = vec![];
+    graph.render_to(&mut dot)?;
+    graphviz::convert(&dot, output_type, layout.as_deref(), w)
+}
@@ -0,0 +1,202 @@
+use crate::dep::{DepKind, ResolvedDep};
+use crate::error::CliResult;
+use crate::graph::{DepGraph, Edge};
+use crate::util;
+use serde_json::json;
+use std::io::Write;
+
+const NOASSERTION: &str = "NOASSERTION";
+
+/// The parts of an SPDX 2.3 document that do not depend on the serialization.
+struct Document<'a> {
+    name: String,
+    namespace: String,
+    created: String,
+    packages: Vec<&'a ResolvedDep>,
+    /// (SPDX id, relationship type, related SPDX id)
+    relationships: Vec<(String, &'static str, String)>,
+}
+
+impl<'a> Document<'a> {
+    fn new(dg: &'a DepGraph) -> CliResult<Self> {
+        let mut packages = vec![];
+        let mut relationships = vec![];
+
+        for (i, dep) in dg.nodes.iter().enumerate() {
+            if !dg.is_shown(i) {
+                continue;
+            }
+
+            packages.push(dep);
+            if dg.root_deps_map.contains_key(&dep.name) {
+                relationships.push(("SPDXRef-DOCUMENT".into(), "DESCRIBES", spdx_id(dep)));
+            }
+        }
+
+        for ed in &dg.edges {
+            if !(dg.is_shown(ed.0) && dg.is_shown(ed.1)) {
+                continue;
+            }
+
+            let Edge(parent, child) = ed.declared(dg);
+            let parent = spdx_id(&dg.nodes[parent]);
+            let child = spdx_id(&dg.nodes[child]);
+            relationships.push(match ed.kind(dg)? {
+                DepKind::Regular | DepKind::Unknown => (parent, "DEPENDS_ON", child),
+                DepKind::Build => (child, "BUILD_DEPENDENCY_OF", parent),
+                DepKind::Dev => (child, "DEV_DEPENDENCY_OF", parent),
+                DepKind::Optional => (child, "OPTIONAL_DEPENDENCY_OF", parent),
+            });
+        }
+
+        let name = match dg
+            .nodes
+            .iter()
+            .find(|dep| dg.root_deps_map.contains_key(&dep.name))
+        {
+            Some(root) => format!("{}-{}", root.name, root.ver),
+            None => "dependencies".into(),
+        };
+        let created = util::utc_timestamp();
+
+        Ok(Document {
+            namespace: format!("https://spdx.org/spdxdocs/{}-{}", name, created),
+            name,
+            created,
+            packages,
+            relationships,
+        })
+    }
+}
+
+/// SPDX identifiers may only contain letters, numbers, `.` and `-`.
+fn spdx_id(dep: &ResolvedDep) -> String {
+    let id = format!("SPDXRef-Package-{}-{}", dep.name, dep.ver);
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Where the `.crate` file can be downloaded from, which is only known for crates from crates.io.
+fn download_location(dep: &ResolvedDep) -> String {
+    if dep.source.is_some() && dep.source_label(false) == "crates.io" {
+        format!(
+            "https://crates.io/api/v1/crates/{}/{}/download",
+            dep.name, dep.ver
+        )
+    } else {
+        NOASSERTION.into()
+    }
+}
+
+/// The license expression of the manifest, with the `/` of old manifests written as `OR`.
+fn license_declared(dep: &ResolvedDep) -> String {
+    match &dep.license {
+        Some(license) => license
+            .split('/')
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join(" OR "),
+        None => NOASSERTION.into(),
+    }
+}
+
+fn creator() -> String {
+    format!("Tool: cargo-deps-{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Writes an SPDX 2.3 document in JSON.
+pub fn render_json<W: Write>(dg: &DepGraph, w: &mut W) -> CliResult<()> {
+    let doc = Document::new(dg)?;
+
+    let packages: Vec<_> = doc
+        .packages
+        .iter()
+        .map(|dep| {
+            json!({
+                "name": dep.name,
+                "SPDXID": spdx_id(dep),
+                "versionInfo": dep.ver,
+                "downloadLocation": download_location(dep),
+                "filesAnalyzed": false,
+                "licenseConcluded": NOASSERTION,
+                "licenseDeclared": license_declared(dep),
+                "copyrightText": NOASSERTION,
+                "externalRefs": [{
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": dep.purl(),
+                }],
+            })
+        })
+        .collect();
+    let relationships: Vec<_> = doc
+        .relationships
+        .iter()
+        .map(|(id, ty, related)| {
+            json!({
+                "spdxElementId": id,
+                "relationshipType": ty,
+                "relatedSpdxElement": related,
+            })
+        })
+        .collect();
+
+    let document = json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": doc.name,
+        "documentNamespace": doc.namespace,
+        "creationInfo": {
+            "created": doc.created,
+            "creators": [creator()],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    });
+
+    serde_json::to_writer_pretty(&mut *w, &document)?;
+    writeln!(w)?;
+
+    Ok(())
+}
+
+/// Writes an SPDX 2.3 document in the tag-value format.
+pub fn render_tag_value<W: Write>(dg: &DepGraph, w: &mut W) -> CliResult<()> {
+    let doc = Document::new(dg)?;
+
+    writeln!(w, "SPDXVersion: SPDX-2.3")?;
+    writeln!(w, "DataLicense: CC0-1.0")?;
+    writeln!(w, "SPDXID: SPDXRef-DOCUMENT")?;
+    writeln!(w, "DocumentName: {}", doc.name)?;
+    writeln!(w, "DocumentNamespace: {}", doc.namespace)?;
+    writeln!(w, "Creator: {}", creator())?;
+    writeln!(w, "Created: {}", doc.created)?;
+
+    for dep in &doc.packages {
+        writeln!(w)?;
+        writeln!(w, "PackageName: {}", dep.name)?;
+        writeln!(w, "SPDXID: {}", spdx_id(dep))?;
+        writeln!(w, "PackageVersion: {}", dep.ver)?;
+        writeln!(w, "PackageDownloadLocation: {}", download_location(dep))?;
+        writeln!(w, "FilesAnalyzed: false")?;
+        writeln!(w, "PackageLicenseConcluded: {}", NOASSERTION)?;
+        writeln!(w, "PackageLicenseDeclared: {}", license_declared(dep))?;
+        writeln!(w, "PackageCopyrightText: {}", NOASSERTION)?;
+        writeln!(w, "ExternalRef: PACKAGE-MANAGER purl {}", dep.purl())?;
+    }
+
+    writeln!(w)?;
+    for (id, ty, related) in &doc.relationships {
+        writeln!(w, "Relationship: {} {} {}", id, ty, related)?;
+    }
+
+    Ok(())
+}
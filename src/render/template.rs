@@ -0,0 +1,97 @@
+use crate::dep::{DiffStatus, SourceKind};
+use crate::error::{CliError, CliResult};
+use crate::graph::DepGraph;
+use handlebars::Handlebars;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::Write;
+
+/// Renders the graph with a Handlebars template, which gets the shown nodes and the edges between
+/// them. The output is not HTML-escaped, as templates produce any kind of text.
+pub fn render<W: Write>(dg: &DepGraph, path: &str, w: &mut W) -> CliResult<()> {
+    let template = fs::read_to_string(path)
+        .map_err(|e| CliError::Generic(format!("Could not read the template {}: {}", path, e)))?;
+
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars
+        .register_template_string(path, template)
+        .map_err(|e| CliError::Generic(format!("Invalid template {}: {}", path, e)))?;
+
+    let output = handlebars
+        .render(path, &data(dg)?)
+        .map_err(|e| CliError::Generic(format!("Could not render the template {}: {}", path, e)))?;
+    write!(w, "{}", output)?;
+
+    Ok(())
+}
+
+/// The data that templates get: `nodes`, `edges` and the names of the `roots`. Edges refer to
+/// nodes by their `id`. Fields that are not known are null.
+fn data(dg: &DepGraph) -> CliResult<Value> {
+    let nodes: Vec<Value> = dg
+        .nodes
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| dg.is_shown(i))
+        .map(|(i, dep)| {
+            let source_kind = match dep.source_kind() {
+                SourceKind::Path => "path",
+                SourceKind::Git => "git",
+                SourceKind::Registry => "registry",
+            };
+            json!({
+                "id": i,
+                "name": dep.name,
+                "version": dep.ver,
+                "kind": dep.kind().name(),
+                "root": dg.root_deps_map.contains_key(&dep.name),
+                "source": dep.source,
+                "source_kind": source_kind,
+                "registry": dep.registry,
+                "license": dep.license,
+                "proc_macro": dep.proc_macro,
+                "purl": dep.purl(),
+                "advisories": dep.advisory_ids(),
+                "latest": dep.latest,
+                "yanked": dep.yanked,
+                "duplicate": dep.duplicate,
+                "critical_path": dep.critical_path,
+                "heavy": dep.heavy,
+                "build_time": dep.build_time,
+                "download_size": dep.download_size,
+                "unpacked_size": dep.unpacked_size,
+                "downloads": dep.downloads,
+                "obscure": dep.obscure,
+                "diff": dep.diff.as_ref().map(|diff| diff.name()),
+                "previous_version": match &dep.diff {
+                    Some(DiffStatus::Changed(previous)) => Some(previous),
+                    _ => None,
+                },
+                "rust_version": dep.rust_version,
+                "transitive": dep.transitive,
+            })
+        })
+        .collect();
+
+    let mut edges = vec![];
+    for ed in &dg.edges {
+        if dg.is_shown(ed.0) && dg.is_shown(ed.1) {
+            let kinds: Vec<&str> = ed.kinds(dg)?.iter().map(|kind| kind.name()).collect();
+            edges.push(json!({
+                "from": ed.0,
+                "to": ed.1,
+                "from_name": dg.nodes[ed.0].name,
+                "to_name": dg.nodes[ed.1].name,
+                "kind": kinds[0],
+                "kinds": kinds,
+                "reqs": ed.reqs(dg),
+            }));
+        }
+    }
+
+    let mut roots: Vec<&String> = dg.root_deps_map.keys().collect();
+    roots.sort();
+
+    Ok(json!({ "nodes": nodes, "edges": edges, "roots": roots }))
+}
@@ -0,0 +1,29 @@
+use crate::error::CliResult;
+use crate::graph::DepGraph;
+use std::io::Write;
+
+/// Writes the graph in Trivial Graph Format: a list of nodes, a `#` separator, then a list of
+/// edges labeled with their kind.
+pub fn render<W: Write>(dg: &DepGraph, w: &mut W) -> CliResult<()> {
+    for (i, dep) in dg.nodes.iter().enumerate() {
+        if dg.is_shown(i) {
+            writeln!(w, "{} {} v{}", DepGraph::node_id(i), dep.name, dep.ver)?;
+        }
+    }
+
+    writeln!(w, "#")?;
+
+    for ed in &dg.edges {
+        if dg.is_shown(ed.0) && dg.is_shown(ed.1) {
+            writeln!(
+                w,
+                "{} {} {}",
+                DepGraph::node_id(ed.0),
+                DepGraph::node_id(ed.1),
+                ed.kind(dg)?.name()
+            )?;
+        }
+    }
+
+    Ok(())
+}
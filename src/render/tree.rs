@@ -0,0 +1,155 @@
+use crate::dep::{DepKind, DiffStatus};
+use crate::error::CliResult;
+use crate::graph::{DepGraph, Edge, Node};
+use std::collections::HashSet;
+use std::io::Write;
+
+struct Charset {
+    branch: &'static str,
+    last_branch: &'static str,
+    pipe: &'static str,
+    ellipsis: &'static str,
+}
+
+const UTF8: Charset = Charset {
+    branch: "├── ",
+    last_branch: "└── ",
+    pipe: "│   ",
+    ellipsis: "…",
+};
+
+const ASCII: Charset = Charset {
+    branch: "|-- ",
+    last_branch: "`-- ",
+    pipe: "|   ",
+    ellipsis: "...",
+};
+
+/// Prints the graph as an indented tree, starting from every shown node without shown parents.
+/// Subtrees that were already printed are marked with `(*)` instead of being repeated, and the
+/// dependencies that close a cycle with `(cycle)`.
+pub fn render<W: Write>(dg: &DepGraph, w: &mut W) -> CliResult<()> {
+    let charset = if dg.cfg.ascii { &ASCII } else { &UTF8 };
+    let back_edges = dg.back_edges();
+    let mut visited = HashSet::new();
+
+    for (i, node) in dg.nodes.iter().enumerate() {
+        if !dg.is_shown(i)
+            || node
+                .parents
+                .iter()
+                .any(|&parent| dg.is_shown(parent) && !back_edges.contains(&Edge(parent, i)))
+        {
+            continue;
+        }
+
+        writeln!(w, "{} v{}", node.name, node.ver)?;
+        visited.insert(i);
+        render_children(dg, i, "", charset, &back_edges, &mut visited, w)?;
+    }
+
+    Ok(())
+}
+
+fn render_children<W: Write>(
+    dg: &DepGraph,
+    parent: Node,
+    prefix: &str,
+    charset: &Charset,
+    back_edges: &HashSet<Edge>,
+    visited: &mut HashSet<Node>,
+    w: &mut W,
+) -> CliResult<()> {
+    let mut children: Vec<Node> = dg.nodes[parent]
+        .children
+        .iter()
+        .cloned()
+        .filter(|&child| dg.is_shown(child))
+        .collect();
+    children.sort_by_key(|&child| (&dg.nodes[child].name, &dg.nodes[child].ver));
+    let truncated = dg.nodes[parent].truncated;
+
+    for (i, &child) in children.iter().enumerate() {
+        let is_last = i == children.len() - 1 && truncated.is_none();
+        let dep = &dg.nodes[child];
+
+        write!(
+            w,
+            "{}{}{} v{}",
+            prefix,
+            if is_last {
+                charset.last_branch
+            } else {
+                charset.branch
+            },
+            dep.name,
+            dep.ver
+        )?;
+        match Edge(parent, child).kind(dg)? {
+            DepKind::Regular => (),
+            DepKind::Build => write!(w, " (build)")?,
+            DepKind::Dev => write!(w, " (dev)")?,
+            DepKind::Optional => write!(w, " (optional)")?,
+            DepKind::Unknown => write!(w, " (orphan)")?,
+        }
+
+        if let Some(count) = dep.collapsed {
+            write!(w, " (+{} deps)", count)?;
+        }
+        if let Some(count) = dep.transitive.filter(|&count| count > 0) {
+            write!(w, " [+{}]", count)?;
+        }
+        if let Some(registry) = dep.shown_registry(dg) {
+            write!(w, " [{}]", registry)?;
+        }
+        if dg.cfg.show_source {
+            write!(w, " [{}]", dep.source_label(false))?;
+        }
+        match &dep.diff {
+            Some(DiffStatus::Changed(previous)) => write!(w, " (was v{})", previous)?,
+            Some(diff) => write!(w, " ({})", diff.name())?,
+            None => (),
+        }
+        if dep.patched {
+            write!(w, " (patched)")?;
+        }
+        if dep.proc_macro && dg.cfg.highlight_proc_macros {
+            write!(w, " (proc-macro)")?;
+        }
+        let reqs = Edge(parent, child).reqs(dg);
+        if dg.cfg.edge_version_reqs && !reqs.is_empty() {
+            write!(w, " (req {})", reqs.join(", "))?;
+        }
+        if dg.cfg.flag_loose_reqs {
+            if let Some(req) = Edge(parent, child).loose_req(dg) {
+                write!(w, " (loose: {})", req)?;
+            }
+        }
+        if back_edges.contains(&Edge(parent, child)) {
+            write!(w, " (cycle)")?;
+        }
+
+        if !visited.insert(child) {
+            if !dep.children.is_empty() || dep.truncated.is_some() {
+                write!(w, " (*)")?;
+            }
+            writeln!(w)?;
+            continue;
+        }
+        writeln!(w)?;
+
+        let child_prefix = format!("{}{}", prefix, if is_last { "    " } else { charset.pipe });
+        render_children(dg, child, &child_prefix, charset, back_edges, visited, w)?;
+    }
+
+    // Show how many crates were left out below the parent.
+    if let Some(count) = truncated {
+        writeln!(
+            w,
+            "{}{}{} {} more",
+            prefix, charset.last_branch, charset.ellipsis, count
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,219 @@
+use crate::error::{CliError, CliResult};
+use crate::util;
+use std::env;
+
+/// A compilation target, with the `cfg` values that rustc sets for it.
+#[derive(Debug)]
+pub struct Target {
+    triple: String,
+    /// Names like `unix` and key-value pairs like `target_os="linux"`.
+    cfgs: Vec<(String, Option<String>)>,
+}
+
+impl Target {
+    /// Asks rustc for the `cfg` values of the target, honoring `RUSTC`.
+    pub fn new(triple: &str) -> CliResult<Self> {
+        let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+        let output = util::run(&rustc, None, ["--print", "cfg", "--target", triple])?;
+
+        let cfgs = String::from_utf8_lossy(&output)
+            .lines()
+            .map(|line| match line.find('=') {
+                Some(i) => (
+                    line[..i].to_string(),
+                    Some(line[i + 1..].trim_matches('"').to_string()),
+                ),
+                None => (line.to_string(), None),
+            })
+            .collect();
+
+        Ok(Target {
+            triple: triple.into(),
+            cfgs,
+        })
+    }
+
+    /// Whether the dependencies of a `[target.<platform>]` table apply to this target. The platform
+    /// is either a target triple or a `cfg(...)` expression. Expressions that cannot be parsed are
+    /// assumed to apply, so that no dependency goes missing.
+    pub fn matches(&self, platform: &str) -> bool {
+        let platform = platform.trim();
+        match platform
+            .strip_prefix("cfg(")
+            .and_then(|expr| expr.strip_suffix(')'))
+        {
+            Some(expr) => {
+                let mut parser = CfgParser {
+                    target: self,
+                    rest: expr,
+                };
+                match parser.expr() {
+                    Ok(matches) if parser.rest.trim().is_empty() => matches,
+                    _ => {
+                        eprintln!("Could not parse cfg({}), assuming it applies.", expr);
+                        true
+                    }
+                }
+            }
+            None => platform == self.triple,
+        }
+    }
+}
+
+/// Evaluates a `cfg` expression like `all(unix, not(target_os = "macos"))` as it is parsed.
+struct CfgParser<'a> {
+    target: &'a Target,
+    rest: &'a str,
+}
+
+impl<'a> CfgParser<'a> {
+    fn expr(&mut self) -> CliResult<bool> {
+        let ident = self.ident()?;
+        if self.eat('(') {
+            let mut values = vec![];
+            while !self.eat(')') {
+                values.push(self.expr()?);
+                if !self.eat(',') && !self.peek(')') {
+                    return Err(self.error());
+                }
+            }
+            return match ident {
+                "all" => Ok(values.iter().all(|&v| v)),
+                "any" => Ok(values.iter().any(|&v| v)),
+                "not" if values.len() == 1 => Ok(!values[0]),
+                _ => Err(self.error()),
+            };
+        }
+
+        let value = if self.eat('=') {
+            Some(self.string()?)
+        } else {
+            None
+        };
+        Ok(self
+            .target
+            .cfgs
+            .iter()
+            .any(|(name, v)| name == ident && v.as_deref() == value))
+    }
+
+    fn ident(&mut self) -> CliResult<&'a str> {
+        self.rest = self.rest.trim_start();
+        let end = self
+            .rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(self.rest.len());
+        if end == 0 {
+            return Err(self.error());
+        }
+        let (ident, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Ok(ident)
+    }
+
+    fn string(&mut self) -> CliResult<&'a str> {
+        self.rest = self.rest.trim_start();
+        let rest = self.rest.strip_prefix('"').ok_or_else(|| self.error())?;
+        let end = rest.find('"').ok_or_else(|| self.error())?;
+        self.rest = &rest[end + 1..];
+        Ok(&rest[..end])
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek(c) {
+            self.rest = &self.rest.trim_start()[c.len_utf8()..];
+            true
+        } else {
+            false
+        }
+    }
+
+    fn peek(&self, c: char) -> bool {
+        self.rest.trim_start().starts_with(c)
+    }
+
+    fn error(&self) -> CliError {
+        CliError::Generic(format!("Unexpected cfg syntax at '{}'", self.rest))
+    }
+}
+
+/// Whether the dependency tables of a platform under `[target]` in a manifest apply. Without a
+/// target, the dependencies of all platforms apply.
+pub fn applies(target: Option<&Target>, platform: &str) -> bool {
+    target.is_none_or(|target| target.matches(platform))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linux() -> Target {
+        Target {
+            triple: "x86_64-unknown-linux-gnu".into(),
+            cfgs: vec![
+                ("unix".into(), None),
+                ("debug_assertions".into(), None),
+                ("target_os".into(), Some("linux".into())),
+                ("target_arch".into(), Some("x86_64".into())),
+                ("target_feature".into(), Some("sse2".into())),
+                ("target_feature".into(), Some("fxsr".into())),
+            ],
+        }
+    }
+
+    #[test]
+    fn triple() {
+        let target = linux();
+        assert!(target.matches("x86_64-unknown-linux-gnu"));
+        assert!(!target.matches("thumbv7em-none-eabihf"));
+    }
+
+    #[test]
+    fn names_and_values() {
+        let target = linux();
+        assert!(target.matches("cfg(unix)"));
+        assert!(!target.matches("cfg(windows)"));
+        assert!(target.matches(r#"cfg(target_os = "linux")"#));
+        assert!(!target.matches(r#"cfg(target_os = "macos")"#));
+        assert!(target.matches(r#"cfg(target_feature = "fxsr")"#));
+        // A name does not match a key that only has values.
+        assert!(!target.matches("cfg(target_os)"));
+    }
+
+    #[test]
+    fn all_any_not() {
+        let target = linux();
+        assert!(target.matches(r#"cfg(all(unix, target_arch = "x86_64"))"#));
+        assert!(!target.matches(r#"cfg(all(unix, target_arch = "arm"))"#));
+        assert!(target.matches("cfg(any(windows, unix))"));
+        assert!(!target.matches("cfg(any(windows, target_os = \"macos\"))"));
+        assert!(target.matches("cfg(not(windows))"));
+        assert!(!target.matches("cfg(not(unix))"));
+        // Like in rustc, an empty all() is true and an empty any() is false.
+        assert!(target.matches("cfg(all())"));
+        assert!(!target.matches("cfg(any())"));
+        assert!(target.matches("cfg(all(unix,))"));
+    }
+
+    #[test]
+    fn nested() {
+        let target = linux();
+        assert!(target.matches(r#"cfg(all(unix, not(target_os = "macos")))"#));
+        assert!(!target.matches(r#"cfg(all(unix, not(any(target_os = "linux", windows))))"#));
+        assert!(target.matches(r#"cfg(any(windows, all(unix, not(debug_assertions)), unix))"#));
+        assert!(target.matches("  cfg( any ( windows , unix ) )  "));
+    }
+
+    #[test]
+    fn malformed() {
+        // Expressions that cannot be parsed apply, so that no dependency goes missing.
+        let target = linux();
+        assert!(target.matches("cfg(all(windows)"));
+        assert!(target.matches("cfg(not(unix, windows))"));
+        assert!(target.matches("cfg(unknown(windows))"));
+        assert!(target.matches("cfg(all(windows) windows)"));
+        assert!(target.matches(r#"cfg(target_os = "macos)"#));
+        assert!(target.matches("cfg(target_os = macos)"));
+        assert!(target.matches("cfg()"));
+    }
+}
@@ -0,0 +1,76 @@
+use crate::dep::DepKind;
+
+/// A color scheme for the DOT output, selected with `--theme`.
+#[derive(Debug)]
+pub struct Theme {
+    pub name: &'static str,
+    /// The background of the graph, if it is not the graphviz default, white.
+    pub background: Option<&'static str>,
+    /// The color of the labels of nodes, edges and clusters.
+    pub font: &'static str,
+    /// The fill of the nodes, if they are filled.
+    pub fill: Option<&'static str>,
+    /// The colors of the kinds, in the order of `DepKind::ALL`.
+    pub kinds: [&'static str; 5],
+}
+
+/// The graphviz defaults and the original colors of the kinds.
+pub const LIGHT: Theme = Theme {
+    name: "light",
+    background: None,
+    font: "black",
+    fill: None,
+    kinds: ["black", "purple", "blue", "red", "orange"],
+};
+
+pub const DARK: Theme = Theme {
+    name: "dark",
+    background: Some("#1e1e1e"),
+    font: "#d4d4d4",
+    fill: Some("#2d2d2d"),
+    kinds: ["#d4d4d4", "#c586c0", "#569cd6", "#f44747", "#ce9178"],
+};
+
+/// The Okabe-Ito palette, which stays distinguishable with the common kinds of color blindness,
+/// deuteranopia included.
+pub const COLORBLIND: Theme = Theme {
+    name: "colorblind",
+    background: None,
+    font: "black",
+    fill: None,
+    kinds: ["black", "#cc79a7", "#0072b2", "#d55e00", "#e69f00"],
+};
+
+pub const MONOKAI: Theme = Theme {
+    name: "monokai",
+    background: Some("#272822"),
+    font: "#f8f8f2",
+    fill: Some("#3e3d32"),
+    kinds: ["#f8f8f2", "#ae81ff", "#66d9ef", "#f92672", "#fd971f"],
+};
+
+pub const SOLARIZED: Theme = Theme {
+    name: "solarized",
+    background: Some("#fdf6e3"),
+    font: "#657b83",
+    fill: Some("#eee8d5"),
+    kinds: ["#586e75", "#6c71c4", "#268bd2", "#dc322f", "#cb4b16"],
+};
+
+const THEMES: &[&Theme] = &[&LIGHT, &DARK, &COLORBLIND, &MONOKAI, &SOLARIZED];
+
+impl Theme {
+    /// Names accepted by `--theme`.
+    pub const NAMES: &'static [&'static str] =
+        &["light", "dark", "colorblind", "monokai", "solarized"];
+
+    pub fn from_name(name: &str) -> Option<&'static Theme> {
+        THEMES.iter().copied().find(|theme| theme.name == name)
+    }
+
+    /// The color of a kind of dependency.
+    pub fn color(&self, kind: DepKind) -> &'static str {
+        let i = DepKind::ALL.iter().position(|&k| k == kind).unwrap();
+        self.kinds[i]
+    }
+}
@@ -0,0 +1,55 @@
+use crate::error::{CliError, CliResult};
+use std::collections::HashMap;
+use std::fs;
+
+/// Reads the compile times from the output of `cargo build --timings=json`, in seconds by crate
+/// name and version. The times of all units of a crate, like its build script and its library, are
+/// added up. Other messages, e.g. of `--message-format=json`, are skipped.
+pub fn load(path: &str) -> CliResult<HashMap<(String, String), f64>> {
+    let text = fs::read_to_string(path).map_err(|e| {
+        CliError::Generic(format!("Could not read the build timings {}: {}", path, e))
+    })?;
+
+    let mut times = HashMap::new();
+    for line in text.lines().filter(|line| line.starts_with('{')) {
+        let message: serde_json::Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(_) => continue,
+        };
+        if message["reason"] != "timing-info" {
+            continue;
+        }
+        let id = message["package_id"].as_str().and_then(parse_package_id);
+        if let (Some(id), Some(duration)) = (id, message["duration"].as_f64()) {
+            *times.entry(id).or_insert(0.0) += duration;
+        }
+    }
+
+    if times.is_empty() {
+        return Err(CliError::Generic(format!(
+            "No build timings found in {}, expected the output of cargo build --timings=json",
+            path
+        )));
+    }
+
+    Ok(times)
+}
+
+/// The name and version of a package id, which is either like `rand 0.8.5 (registry+...)` or, since
+/// cargo 1.77, a package id spec like `registry+...#rand@0.8.5`. The spec leaves out the name if it
+/// is the last segment of the URL, as for path dependencies.
+fn parse_package_id(id: &str) -> Option<(String, String)> {
+    if let Some((url, fragment)) = id.rsplit_once('#') {
+        return match fragment.split_once('@') {
+            Some((name, ver)) => Some((name.into(), ver.into())),
+            None => {
+                let path = url.split('?').next()?.trim_end_matches('/');
+                let name = path.rsplit('/').next()?;
+                Some((name.into(), fragment.into()))
+            }
+        };
+    }
+
+    let mut parts = id.split_whitespace();
+    Some((parts.next()?.into(), parts.next()?.into()))
+}
@@ -1,8 +1,13 @@
 use crate::error::{CliError, CliResult};
+use regex::Regex;
+use std::cmp::Ordering;
 use std::env;
+use std::ffi::OsStr;
 use std::fs::{self, File};
-use std::io::Read;
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+use std::time::{SystemTime, UNIX_EPOCH};
 use toml::{self, Value};
 
 pub fn toml_from_file<P: AsRef<Path>>(p: P) -> CliResult<Value> {
@@ -53,7 +58,277 @@ pub fn find_manifest_file(file: &str) -> CliResult<PathBuf> {
                     manifest.parent().unwrap()
                 )));
             }
-            Some(ref dir) => dir.to_path_buf(),
+            Some(dir) => dir.to_path_buf(),
         };
     }
 }
+
+/// Runs cargo with the given arguments and returns its standard output. This is the cargo that runs
+/// us as a subcommand if there is one.
+pub fn run_cargo<I, S>(args: I) -> CliResult<Vec<u8>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    run(&cargo, None, args)
+}
+
+/// Runs a program with the given arguments, optionally in another directory, and returns its
+/// standard output. The first argument names the command in error messages.
+pub fn run<I, S>(program: &OsStr, dir: Option<&Path>, args: I) -> CliResult<Vec<u8>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let args: Vec<S> = args.into_iter().collect();
+    let command = match args.first() {
+        Some(arg) => format!(
+            "{} {}",
+            program.to_string_lossy(),
+            arg.as_ref().to_string_lossy()
+        ),
+        None => program.to_string_lossy().into_owned(),
+    };
+
+    let mut cmd = Command::new(program);
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
+    let output = cmd
+        .args(&args)
+        .output()
+        .map_err(|e| CliError::Generic(format!("Could not run {}: {}", command, e)))?;
+    if !output.status.success() {
+        return Err(CliError::Generic(format!(
+            "{} failed: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Recursively copies the contents of a directory.
+pub fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A temporary directory that is removed again when dropped.
+#[derive(Debug)]
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(prefix: &str) -> CliResult<Self> {
+        let path = env::temp_dir().join(format!("cargo-deps-{}-{}", prefix, process::id()));
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
+        }
+        fs::create_dir_all(&path)?;
+
+        Ok(TempDir { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// The current time as an ISO 8601 UTC timestamp, e.g. `2019-02-20T13:37:00Z`.
+pub fn utc_timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, secs_of_day) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // Convert days since the epoch to a civil date (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// A pattern matching crate names. Crate names cannot contain regex syntax, so a pattern with any
+/// of it is a regex. Otherwise, a pattern with `*` or `?` is a glob, and anything else must match
+/// exactly.
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    Exact(String),
+    Glob(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> CliResult<Self> {
+        const REGEX_CHARS: &[char] = &['^', '$', '.', '+', '(', ')', '[', ']', '{', '}', '|', '\\'];
+
+        if pattern.contains(REGEX_CHARS) {
+            Regex::new(pattern)
+                .map(Pattern::Regex)
+                .map_err(|e| CliError::Generic(format!("Invalid pattern '{}': {}", pattern, e)))
+        } else if pattern.contains(&['*', '?'][..]) {
+            Ok(Pattern::Glob(pattern.into()))
+        } else {
+            Ok(Pattern::Exact(pattern.into()))
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Pattern::Exact(pattern) => pattern == name,
+            Pattern::Glob(pattern) => glob_match(pattern, name),
+            Pattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+/// Whether a crate name matches any of the patterns.
+pub fn matches_any(patterns: &[Pattern], name: &str) -> bool {
+    patterns.iter().any(|pattern| pattern.matches(name))
+}
+
+/// Whether a version requirement lets through any version, like `*`, or any version of a major
+/// version 0, like `0` or `0.*`, which allows breaking changes.
+pub fn is_loose_req(req: &str) -> bool {
+    req.split(',').any(|comparator| {
+        let comparator = comparator
+            .trim()
+            .trim_start_matches(&['=', '^', '~'][..])
+            .trim();
+        matches!(comparator, "*" | "x" | "X" | "0" | "0.*" | "0.x" | "0.X")
+    })
+}
+
+/// Compares two versions like `1.9.2` and `1.10.0` by their dot-separated parts, numerically where
+/// both parts are numbers and as text otherwise.
+pub fn cmp_versions(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split('.');
+    let mut b_parts = b.split('.');
+    loop {
+        let ordering = match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                _ => a.cmp(b),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Parses a Rust version like `1.70` or `1.70.0`, as `rust-version` and `--check-msrv` give it.
+/// Missing parts are zero.
+pub fn parse_rust_version(ver: &str) -> Option<semver::Version> {
+    let mut parts = ver.trim().splitn(3, '.');
+    let mut part = || -> Option<u64> {
+        match parts.next() {
+            Some(part) => part.parse().ok(),
+            None => Some(0),
+        }
+    };
+
+    Some(semver::Version::new(part()?, part()?, part()?))
+}
+
+/// Matches `text` against a glob pattern supporting `*` (any sequence) and `?` (any character).
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    // Iterative matching with backtracking to the last `*`.
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Expands a relative path pattern, whose components may contain glob wildcards, into the existing
+/// paths under `base` that match it.
+pub fn expand_glob(base: &Path, pattern: &str) -> Vec<PathBuf> {
+    let mut paths = vec![base.to_path_buf()];
+
+    for component in pattern.split(['/', '\\']) {
+        if component.is_empty() || component == "." {
+            continue;
+        }
+
+        if !component.contains(['*', '?']) {
+            paths = paths.into_iter().map(|path| path.join(component)).collect();
+            continue;
+        }
+
+        let mut matches = vec![];
+        for path in &paths {
+            if let Ok(entries) = fs::read_dir(path) {
+                for entry in entries.filter_map(|entry| entry.ok()) {
+                    if let Some(name) = entry.file_name().to_str() {
+                        if glob_match(component, name) {
+                            matches.push(entry.path());
+                        }
+                    }
+                }
+            }
+        }
+        matches.sort();
+        paths = matches;
+    }
+
+    paths
+}